@@ -1,13 +1,28 @@
 use std::error::Error;
-use std::fs::read_to_string;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-mod build_for_wasm;
-use build_for_wasm::ShaderMapGenerator;
+// 与运行时共用同一个预处理器
+#[path = "src/util/preprocessor.rs"]
+mod preprocessor;
+use preprocessor::{entry_shaders, wgsl_files, ShaderPreprocessor};
 
 // build.rs 配置：https://blog.csdn.net/weixin_33910434/article/details/87943334
 fn main() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/util/preprocessor.rs");
+    println!("cargo:rerun-if-changed=shader-wgsl");
+    println!("cargo:rerun-if-env-changed=PREPROCESS_SHADER");
+
+    // 构建时只检查 include 是否存在，宏、条件编译与 code slot 在运行时处理
+    let shader_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("shader-wgsl");
+    let preprocessor = ShaderPreprocessor::from_dir(shader_dir.clone()).includes_only();
+    let shader_files = entry_shaders(&shader_dir);
+    for name in shader_files.iter() {
+        preprocessor.process(name)?;
+    }
+    embed_shaders(&shader_dir)?;
+
     let mut need_generate_shader = false;
     for (key, value) in std::env::vars() {
         if key == "PREPROCESS_SHADER" && value == "true" {
@@ -18,52 +33,45 @@ fn main() -> Result<(), Box<dyn Error>> {
         return Ok(());
     }
 
-    let shader_files = vec![
-        "field_setting",
-        "trajectory_update",
-        "present",
-        "clear_color",
-        "lbm/collide_stream",
-        "lbm/init",
-        "lbm/boundary",
-        "lbm/particle_update",
-        "lbm/curl_update",
-        "lbm/present",
-        "lbm/trajectory_present",
-        "aa_lbm/aa_init",
-        "aa_lbm/aa_collide_stream",
-    ];
-    let mut map_generator = ShaderMapGenerator::new();
-
     // 创建目录
     std::fs::create_dir_all("shader-preprocessed-wgsl")?;
-    for name in shader_files {
-        let _ = regenerate_shader(name, &mut map_generator);
+    for name in shader_files.iter() {
+        regenerate_shader(name, &preprocessor)?;
     }
 
-    let _ = map_generator.generate_code();
+    Ok(())
+}
 
+// 生成按相对路径查找 shader-wgsl 下源文件的 include_str! 表
+// 运行时从源文件一次性处理 include 与条件编译，错误信息对应源文件的行号
+fn embed_shaders(shader_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    let mut registry = String::from(
+        "// 由 build.rs 生成\npub fn embedded_shader(path: &str) -> Option<&'static str> {\n    match path {\n",
+    );
+    for file in wgsl_files(shader_dir) {
+        registry += &format!(
+            "        {:?} => Some(include_str!({:?})),\n",
+            file,
+            shader_dir.join(&file).to_string_lossy()
+        );
+    }
+    registry += "        _ => None,\n    }\n}\n";
+    std::fs::write(out_dir.join("shader_registry.rs"), registry)?;
     Ok(())
 }
 
+// 展开 include 后的 shader 写到 shader-preprocessed-wgsl，便于查看
 fn regenerate_shader(
-    shader_name: &str, map_generator: &mut ShaderMapGenerator,
+    shader_name: &str, preprocessor: &ShaderPreprocessor,
 ) -> Result<(), Box<dyn Error>> {
     let base_dir = env!("CARGO_MANIFEST_DIR");
-    let path = PathBuf::from(&base_dir).join("shader-wgsl").join(format!("{}.wgsl", shader_name));
     let mut out_path = "shader-preprocessed-wgsl/".to_string();
     out_path += &format!("{}.wgsl", shader_name.replace("/", "_"));
 
-    let code = match read_to_string(&path) {
-        Ok(code) => code,
-        Err(e) => {
-            panic!("Unable to read {:?}: {:?}", path, e)
-        }
-    };
-
-    let mut shader_source = String::new();
-    parse_shader_source(&code, &mut shader_source, &base_dir);
-    map_generator.insert(shader_name.replace("/", "_"), shader_source.clone());
+    // 错误信息为源文件的路径与行号
+    let shader = preprocessor.process(shader_name)?;
+    let shader_source = remove_comment_lines(&shader.code);
 
     let mut f = std::fs::File::create(&std::path::Path::new(&base_dir).join(&out_path))?;
     f.write_all(shader_source.as_bytes())?;
@@ -71,43 +79,14 @@ fn regenerate_shader(
     Ok(())
 }
 
-fn parse_shader_source(source: &str, output: &mut String, base_path: &str) {
-    let include: &str = "#include ";
+// 移除注释
+fn remove_comment_lines(source: &str) -> String {
+    let mut output = String::new();
     for line in source.lines() {
-        if line.starts_with(include) {
-            let imports = line[include.len()..].split(',');
-            // For each import, get the source, and recurse.
-            for import in imports {
-                if let Some(include) = get_shader_funcs(import, base_path) {
-                    parse_shader_source(&include, output, base_path);
-                } else {
-                    println!("shader parse error -------");
-                    println!("can't find shader functions: {}", import);
-                    println!("--------------------------");
-                }
-            }
-        } else {
-            // 移除注释
-            let need_delete = match line.find("//") {
-                Some(_) => {
-                    let segments: Vec<&str> = line.split("//").collect();
-                    segments.len() > 1 && segments.first().unwrap().trim().is_empty()
-                }
-                None => false,
-            };
-            if !need_delete {
-                output.push_str(line);
-                output.push_str("\n");
-            }
+        if !line.trim_start().starts_with("//") {
+            output.push_str(line);
+            output.push_str("\n");
         }
     }
-}
-
-fn get_shader_funcs(key: &str, base_path: &str) -> Option<String> {
-    let path = PathBuf::from(base_path).join("shader-wgsl").join(key.replace('"', ""));
-    let shader = match read_to_string(&path) {
-        Ok(code) => code,
-        Err(e) => panic!("Unable to read {:?}: {:?}", path, e),
-    };
-    Some(shader)
+    output
 }
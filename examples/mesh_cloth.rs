@@ -0,0 +1,78 @@
+use app_surface::{AppSurface, SurfaceFrame};
+use nature::{MeshClothCanvas, ObjMesh};
+
+// cargo run --example mesh_cloth [path/to/mesh.obj]
+// 不指定 OBJ 文件时使用一块方形网格，固定网格最上方的顶点
+fn main() {
+    use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+    use winit::{event_loop::ControlFlow, event_loop::EventLoop};
+
+    env_logger::init();
+    let mesh = match std::env::args().nth(1) {
+        Some(path) => ObjMesh::from_path(&path).unwrap(),
+        None => ObjMesh::parse(&grid_obj(24)).unwrap(),
+    };
+    let top = mesh.positions.iter().map(|p| p[1]).fold(f32::MIN, f32::max);
+    let pinned: Vec<u32> = (0..mesh.positions.len() as u32)
+        .filter(|i| top - mesh.positions[*i as usize][1] < 1.0e-4)
+        .collect();
+
+    let events_loop = EventLoop::new();
+    let size = winit::dpi::Size::Logical(winit::dpi::LogicalSize { width: 800.0, height: 800.0 });
+    let window = winit::window::WindowBuilder::new()
+        .with_inner_size(size)
+        .with_title("mesh cloth")
+        .build(&events_loop)
+        .unwrap();
+    let mut surface_view = MeshClothCanvas::new(AppSurface::new(window), &mesh, &pinned);
+
+    events_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+        match event {
+            Event::MainEventsCleared => surface_view.app_view.view.request_redraw(),
+            Event::WindowEvent { event: WindowEvent::Resized(_size), .. } => {
+                surface_view.resize_surface();
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                state: ElementState::Pressed,
+                                ..
+                            },
+                        ..
+                    }
+                    | WindowEvent::CloseRequested,
+                ..
+            } => {
+                *control_flow = ControlFlow::Exit;
+            }
+            Event::RedrawRequested(_) => {
+                surface_view.enter_frame();
+            }
+            _ => (),
+        }
+    });
+}
+
+// 竖直的 n x n 方形网格，每个格子由两个三角形组成
+fn grid_obj(n: u32) -> String {
+    let mut obj = String::new();
+    for h in 0..=n {
+        for w in 0..=n {
+            let (u, v) = (w as f32 / n as f32, h as f32 / n as f32);
+            obj += &format!("v {} {} 0\nvt {} {}\n", u - 0.5, 0.5 - v, u, 1.0 - v);
+        }
+    }
+    for h in 0..n {
+        for w in 0..n {
+            // OBJ 索引从 1 开始
+            let i = h * (n + 1) + w + 1;
+            let (right, below) = (i + 1, i + n + 1);
+            obj += &format!("f {0}/{0} {1}/{1} {2}/{2} {3}/{3}\n", i, below, below + 1, right);
+        }
+    }
+    obj
+}
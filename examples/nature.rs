@@ -1,5 +1,8 @@
-use app_surface::{math::Position, AppSurface, SurfaceFrame, Touch};
-use nature::Canvas;
+use app_surface::{math::Position, AppSurface, SurfaceFrame, TouchPhase};
+use nature::{
+    CombinateCanvas, EditTool, FieldAnimationType, FieldType, InputScript, ParticleColorType,
+    SettingObj,
+};
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::{Duration, Instant};
 
@@ -25,11 +28,23 @@ fn main() {
 
     let v = AppSurface::new(window);
 
-    // let mut surface_view = CombinateCanvas::new(v);
-    let mut surface_view = Canvas::new(v);
+    let setting = SettingObj::new(
+        FieldType::Fluid,
+        FieldAnimationType::Poiseuille,
+        ParticleColorType::MovementAngle,
+        20000,
+        60.0,
+    );
+    let mut surface_view = CombinateCanvas::new(v, setting);
 
+    // R 开始/结束输入录制，P 回放录制的脚本
+    let script_path = std::path::PathBuf::from("input_script.txt");
     let mut last_update_inst = Instant::now();
     let mut last_touch_point: Position = Position::zero();
+    let mut left_bt_pressed = false;
+    // 1~6 切换编辑工具：外力、障碍物、橡皮擦、墙、入口、出口；[ ] 调整笔刷的像素半径
+    // Z 撤销，Y 重做
+    let mut brush_radius = 4.0_f32;
     events_loop.run(move |event, _, control_flow| {
         *control_flow = if cfg!(feature = "metal-auto-capture") {
             ControlFlow::Exit
@@ -68,12 +83,47 @@ fn main() {
                 WindowEvent::KeyboardInput {
                     input:
                         KeyboardInput {
-                            virtual_keycode: Some(VirtualKeyCode::W),
+                            virtual_keycode: Some(keycode),
                             state: ElementState::Pressed,
                             ..
                         },
                     ..
-                } => {}
+                } => match keycode {
+                    VirtualKeyCode::R => match surface_view.stop_input_recording() {
+                        Some(script) => match script.save(&script_path) {
+                            Ok(_) => println!("input script saved: {:?}", script_path),
+                            Err(e) => println!("failed to save input script: {}", e),
+                        },
+                        None => surface_view.start_input_recording(),
+                    },
+                    VirtualKeyCode::P => {
+                        if surface_view.is_replaying() {
+                            surface_view.stop_replay();
+                        } else {
+                            match InputScript::load(&script_path) {
+                                Ok(script) => surface_view.start_replay(script, false),
+                                Err(e) => println!("failed to load input script: {}", e),
+                            }
+                        }
+                    }
+                    VirtualKeyCode::Key1 => surface_view.set_edit_tool(EditTool::Force),
+                    VirtualKeyCode::Key2 => surface_view.set_edit_tool(EditTool::Obstacle),
+                    VirtualKeyCode::Key3 => surface_view.set_edit_tool(EditTool::Eraser),
+                    VirtualKeyCode::Key4 => surface_view.set_edit_tool(EditTool::Wall),
+                    VirtualKeyCode::Key5 => surface_view.set_edit_tool(EditTool::Inlet),
+                    VirtualKeyCode::Key6 => surface_view.set_edit_tool(EditTool::Outlet),
+                    VirtualKeyCode::Z => surface_view.undo(),
+                    VirtualKeyCode::Y => surface_view.redo(),
+                    VirtualKeyCode::LBracket | VirtualKeyCode::RBracket => {
+                        brush_radius = if keycode == VirtualKeyCode::LBracket {
+                            (brush_radius * 0.5).max(1.0)
+                        } else {
+                            (brush_radius * 2.0).min(64.0)
+                        };
+                        surface_view.set_brush(brush_radius, 0.5);
+                    }
+                    _ => (),
+                },
                 WindowEvent::MouseWheel { delta, .. } => match delta {
                     MouseScrollDelta::LineDelta(_x, y) => {
                         println!("{:?}, {}", _x, y);
@@ -83,27 +133,33 @@ fn main() {
                 WindowEvent::MouseInput { device_id: _, state, button, .. } => {
                     match button {
                         MouseButton::Left => {
-                            let point = Touch::touch_end(Position::new(0.0, 0.0));
-
-                            if state == ElementState::Pressed {
-                                // surface_view.touch_start(point);
+                            left_bt_pressed = state == ElementState::Pressed;
+                            let phase = if left_bt_pressed {
+                                TouchPhase::Started
                             } else {
-                                surface_view.touch(point);
-                            }
+                                TouchPhase::Ended
+                            };
+                            surface_view.pointer_event(0, phase, last_touch_point);
                         }
                         _ => (),
                     };
                 }
                 WindowEvent::Touch(touch) => {
-                    println!("{:?}", touch);
+                    let phase = match touch.phase {
+                        winit::event::TouchPhase::Started => TouchPhase::Started,
+                        winit::event::TouchPhase::Moved => TouchPhase::Moved,
+                        winit::event::TouchPhase::Ended => TouchPhase::Ended,
+                        winit::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
+                    };
+                    let pos = Position::new(touch.location.x as f32, touch.location.y as f32);
+                    // 鼠标使用 id 0
+                    surface_view.pointer_event(touch.id + 1, phase, pos);
                 }
                 WindowEvent::CursorMoved { position, .. } => {
-                    // if left_bt_pressed {
-
-                    // }
                     last_touch_point = Position::new(position.x as f32, position.y as f32);
-                    let point = Touch::touch_move(last_touch_point);
-                    surface_view.touch(point);
+                    if left_bt_pressed {
+                        surface_view.pointer_event(0, TouchPhase::Moved, last_touch_point);
+                    }
                 }
                 _ => {}
             },
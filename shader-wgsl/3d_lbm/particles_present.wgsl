@@ -9,6 +9,8 @@ struct TrajectoryUniform {
 @group(0) @binding(0) var<uniform> params: TrajectoryUniform;
 @group(0) @binding(1) var macro_info: texture_3d<f32>;
 @group(0) @binding(2) var tex_sampler: sampler;
+// 双指缩放、旋转的视图变换
+@group(0) @binding(3) var<uniform> camera: mat4x4<f32>;
 
 struct VertexOutput {
     @location(0) uv: vec3<f32>;
@@ -21,10 +23,12 @@ fn vs_compose(
     @location(1) particle_pos_initial: vec4<f32>,
     @location(2) position: vec2<f32>,
 ) -> VertexOutput {
-    let pos = vec4<f32>(particle_pos.xy + position * params.screen_factor, particle_pos.z, particle_pos.w);
+    let view_pos = camera * vec4<f32>(particle_pos.xyz, 1.0);
+    let pos = vec4<f32>(view_pos.xy + position * params.screen_factor, view_pos.z, particle_pos.w);
     var result: VertexOutput;
     result.position = pos;
-    result.uv = (pos.xyz + 1.0) / 2.0;
+    // 按粒子在流场中的位置采样，与视图变换无关
+    result.uv = (particle_pos.xyz + 1.0) / 2.0;
     return result;
 }
 
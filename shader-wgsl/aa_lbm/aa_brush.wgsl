@@ -0,0 +1,101 @@
+#include "lbm/struct/lbm_uniform.wgsl"
+#include "lbm/struct/lattice_info.wgsl"
+#include "struct/field.wgsl"
+
+struct BrushUniform {
+  count: i32,
+  _pading0: i32,
+  _pading1: i32,
+  _pading2: i32,
+};
+
+struct BrushCommand {
+  // 线段的起止点（格子坐标），圆形时两点相同
+  p0: vec2<f32>,
+  p1: vec2<f32>,
+  force: vec2<f32>,
+  radius: f32,
+  // 0: 半径内强度一致，1: 从中心到边缘衰减到 0
+  falloff: f32,
+  // 写入的材质，1: 擦除为流体，6: 外力
+  material: i32,
+  block_iter: i32,
+  _pading0: i32,
+  _pading1: i32,
+};
+
+struct BrushBuffer {
+  data: array<BrushCommand>,
+};
+
+struct StoreFloat {
+  data: array<f32>,
+};
+
+@group(0) @binding(0) var<uniform> fluid: LbmUniform;
+@group(0) @binding(1) var<uniform> field: FieldUniform;
+@group(0) @binding(2) var<uniform> brush: BrushUniform;
+@group(0) @binding(3) var<storage, read_write> commands: BrushBuffer;
+@group(0) @binding(4) var<storage, read_write> lattice_info: StoreInfo;
+@group(0) @binding(5) var<storage, read_write> aa_cell: StoreFloat;
+
+fn isSolidCell(material: i32) -> bool { return material == 2 || material == 4; }
+
+fn distance_to_segment(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+  let ab = b - a;
+  let len_sq = dot(ab, ab);
+  var t = 0.0;
+  if (len_sq > 0.0) {
+    t = clamp(dot(p - a, ab) / len_sq, 0.0, 1.0);
+  }
+  return length(p - (a + ab * t));
+}
+
+// 一帧内所有的笔刷命令一次执行，后面的命令覆盖前面的
+@compute @workgroup_size(64, 4)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let uv = vec2<i32>(global_invocation_id.xy);
+  // 最外圈的格子保持不变
+  if (uv.x < 1 || uv.y < 1 || uv.x >= field.lattice_size.x - 2 || uv.y >= field.lattice_size.y - 2) {
+    return;
+  }
+  let field_index = uv.x + uv.y * field.lattice_size.x;
+  let origin_material = lattice_info.data[field_index].material;
+  var info: LatticeInfo = lattice_info.data[field_index];
+  let center = vec2<f32>(uv) + 0.5;
+  var is_changed = false;
+  for (var i: i32 = 0; i < brush.count; i = i + 1) {
+    let cmd = commands.data[i];
+    let dis = distance_to_segment(center, cmd.p0, cmd.p1);
+    if (dis > cmd.radius) {
+      continue;
+    }
+    if (cmd.material == 6) {
+      // 外力只作用于流体格子
+      if (info.material == 1 || info.material == 6) {
+        let strength = 1.0 - cmd.falloff * smoothstep(0.0, cmd.radius, dis);
+        info = LatticeInfo(6, cmd.block_iter, cmd.force.x * strength, cmd.force.y * strength);
+        is_changed = true;
+      }
+    } else if (info.material != 7) {
+      // 障碍物、墙、入口、出口与擦除，ghost 格子保持不变
+      info = LatticeInfo(cmd.material, cmd.block_iter, cmd.force.x, cmd.force.y);
+      is_changed = true;
+    }
+  }
+  if (!is_changed) {
+    return;
+  }
+  lattice_info.data[field_index] = info;
+
+  if (isSolidCell(origin_material) && !isSolidCell(info.material)) {
+    // 新腾出的格子重置为静止的平衡态分布
+    for (var i: i32 = 0; i < 9; i = i + 1) {
+      aa_cell.data[field_index + i * fluid.soa_offset] = fluid.e_w_max[i].z;
+    }
+  } else if (!isSolidCell(origin_material) && isSolidCell(info.material)) {
+    for (var i: i32 = 0; i < 9; i = i + 1) {
+      aa_cell.data[field_index + i * fluid.soa_offset] = 0.0;
+    }
+  }
+}
@@ -0,0 +1,45 @@
+#include "lbm/struct/lbm_uniform.wgsl"
+#include "lbm/struct/lattice_info.wgsl"
+#include "struct/field.wgsl"
+
+struct StoreFloat {
+  data: array<f32>,
+};
+
+@group(0) @binding(0) var<uniform> fluid: LbmUniform;
+@group(0) @binding(1) var<uniform> field: FieldUniform;
+// CPU 端的材质数据，撤销/重做时整体上传
+@group(0) @binding(2) var<storage, read_write> target_info: StoreInfo;
+@group(0) @binding(3) var<storage, read_write> lattice_info: StoreInfo;
+@group(0) @binding(4) var<storage, read_write> aa_cell: StoreFloat;
+
+fn isSolidCell(material: i32) -> bool { return material == 2 || material == 4; }
+
+@compute @workgroup_size(64, 4)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let uv = vec2<i32>(global_invocation_id.xy);
+  if (uv.x >= field.lattice_size.x || uv.y >= field.lattice_size.y) {
+    return;
+  }
+  let field_index = uv.x + uv.y * field.lattice_size.x;
+  let info: LatticeInfo = lattice_info.data[field_index];
+  let target_cell: LatticeInfo = target_info.data[field_index];
+  if (info.material == target_cell.material && info.block_iter == target_cell.block_iter && info.vx == target_cell.vx && info.vy == target_cell.vy) {
+    return;
+  }
+  // 外力只在 GPU 上存在并会自行衰减，CPU 端对应的是流体，保持不变
+  if (info.material == 6 && target_cell.material == 1) {
+    return;
+  }
+  lattice_info.data[field_index] = target_cell;
+
+  if (isSolidCell(info.material) && !isSolidCell(target_cell.material)) {
+    for (var i: i32 = 0; i < 9; i = i + 1) {
+      aa_cell.data[field_index + i * fluid.soa_offset] = fluid.e_w_max[i].z;
+    }
+  } else if (!isSolidCell(info.material) && isSolidCell(target_cell.material)) {
+    for (var i: i32 = 0; i < 9; i = i + 1) {
+      aa_cell.data[field_index + i * fluid.soa_offset] = 0.0;
+    }
+  }
+}
@@ -8,7 +8,7 @@ fn field_index(uv: vec2<i32>) -> i32 {
 }
 
 fn get_velocity(p: vec2<i32>) -> vec2<f32> {
-    #insert_code_segment
+    #slot(code_segment)
 }
 
 fn get_velocity0(p: vec2<i32>) -> vec2<f32> {
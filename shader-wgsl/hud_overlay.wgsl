@@ -0,0 +1,60 @@
+#include "bufferless.vs.wgsl"
+
+struct HudUniform {
+  // 像素坐标：x, y, width, height
+  rect: vec4<f32>,
+  cols: i32,
+  rows: i32,
+  // 一个字体像素占的屏幕像素数
+  scale: f32,
+  bar_width: f32,
+};
+
+struct GlyphBuf {
+  data: array<u32>,
+};
+
+struct BarBuf {
+  data: array<f32>,
+};
+
+@group(0) @binding(0) var<uniform> hud: HudUniform;
+// 每个字符格子的 3x5 字形位图
+@group(0) @binding(1) var<storage, read_write> glyphs: GlyphBuf;
+// 每行耗时占一帧预算的比例
+@group(0) @binding(2) var<storage, read_write> bars: BarBuf;
+
+// 每个字符占 4x6 个字体像素：3x5 的字形加 1 像素的间隔
+@fragment
+fn fs_main(vertex: VertexOutput) -> @location(0) vec4<f32> {
+  let local = vertex.position.xy - hud.rect.xy;
+  if (local.x < 0.0 || local.y < 0.0 || local.x >= hud.rect.z || local.y >= hud.rect.w) {
+    discard;
+  }
+  let font_px = vec2<i32>(floor(local / hud.scale));
+  let row = min(font_px.y / 6, hud.rows - 1);
+  var color = vec4<f32>(0.0, 0.0, 0.0, 0.55);
+
+  let text_width = f32(hud.cols * 4) * hud.scale;
+  if (local.x < text_width) {
+    let col = font_px.x / 4;
+    let gx = font_px.x % 4;
+    let gy = font_px.y % 6;
+    if (gx < 3 && gy < 5) {
+      let bitmap = glyphs.data[row * hud.cols + col];
+      if (((bitmap >> u32(gy * 3 + gx)) & 1u) == 1u) {
+        color = vec4<f32>(0.95, 0.95, 0.95, 1.0);
+      }
+    }
+  } else {
+    let ratio = bars.data[row];
+    let bar_x = (local.x - text_width - hud.scale * 2.0) / hud.bar_width;
+    let gy = font_px.y % 6;
+    if (bar_x >= 0.0 && bar_x < min(ratio, 1.0) && gy >= 1 && gy < 4) {
+      // 越接近一帧的预算越红
+      let rgb = mix(vec3<f32>(0.3, 0.85, 0.4), vec3<f32>(0.95, 0.3, 0.25), clamp(ratio, 0.0, 1.0));
+      color = vec4<f32>(rgb, 1.0);
+    }
+  }
+  return color;
+}
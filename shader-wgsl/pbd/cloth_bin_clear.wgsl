@@ -0,0 +1,17 @@
+#include "pbd/struct/cloth_collision.wgsl"
+
+struct BinCountBuffer {
+  data: array<atomic<i32>>,
+};
+
+@group(0) @binding(0) var<uniform> bin: BinUniform;
+@group(0) @binding(1) var<storage, read_write> bin_counts: BinCountBuffer;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let index = i32(global_invocation_id.x);
+  if (index >= bin.bin_num.w) {
+    return;
+  }
+  atomicStore(&bin_counts.data[index], 0);
+}
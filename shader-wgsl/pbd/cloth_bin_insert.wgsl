@@ -0,0 +1,31 @@
+#include "pbd/struct/particle.wgsl"
+#include "pbd/struct/cloth_collision.wgsl"
+
+struct BinCountBuffer {
+  data: array<atomic<i32>>,
+};
+
+struct BinBuffer {
+  data: array<i32>,
+};
+
+@group(0) @binding(0) var<uniform> bin: BinUniform;
+@group(0) @binding(1) var<storage, read_write> particles: ParticlesBuffer;
+@group(0) @binding(2) var<storage, read_write> bin_counts: BinCountBuffer;
+@group(0) @binding(3) var<storage, read_write> bins: BinBuffer;
+
+// 把粒子放入空间 hash 容器
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particles.data);
+  let field_index = global_invocation_id.x;
+  if (field_index >= total) {
+    return;
+  }
+  let hash = bin_hash(bin_coord(particles.data[field_index].pos.xyz, bin), bin);
+  let slot = atomicAdd(&bin_counts.data[hash], 1);
+  // 容器已满时丢弃，只会漏掉少量碰撞
+  if (slot < bin.max_bin_count) {
+    bins.data[hash * bin.max_bin_count + slot] = i32(field_index);
+  }
+}
@@ -0,0 +1,80 @@
+#include "pbd/struct/particle.wgsl"
+#include "pbd/struct/cloth_uniform.wgsl"
+#include "pbd/struct/cloth_collision.wgsl"
+
+@group(0) @binding(0) var<uniform> cloth: ClothUniform;
+@group(0) @binding(1) var<uniform> collision: CollisionUniform;
+@group(0) @binding(2) var<storage, read_write> particles: ParticlesBuffer;
+@group(0) @binding(3) var<storage, read_write> colliders: CollidersBuffer;
+
+let EPSILON: f32 = 0.0000001;
+
+// 与碰撞体表面的有向距离，xyz 为表面法线
+fn signed_distance(pos: vec3<f32>, c: Collider) -> vec4<f32> {
+  if (c.ty == COLLIDER_SPHERE) {
+    let v = pos - c.p0.xyz;
+    let len = length(v);
+    return vec4<f32>(v / max(len, EPSILON), len - c.radius);
+  } else if (c.ty == COLLIDER_CAPSULE) {
+    let ab = c.p1.xyz - c.p0.xyz;
+    let t = clamp(dot(pos - c.p0.xyz, ab) / max(dot(ab, ab), EPSILON), 0.0, 1.0);
+    let v = pos - (c.p0.xyz + ab * t);
+    let len = length(v);
+    return vec4<f32>(v / max(len, EPSILON), len - c.radius);
+  } else if (c.ty == COLLIDER_BOX) {
+    let local = pos - c.p0.xyz;
+    let q = abs(local) - c.p1.xyz;
+    let outside = max(q, vec3<f32>(0.0));
+    let outside_len = length(outside);
+    if (outside_len > 0.0) {
+      return vec4<f32>(outside * sign(local) / outside_len, outside_len);
+    }
+    // 在盒子内部时，从最近的面推出
+    var n = vec3<f32>(sign(local.x), 0.0, 0.0);
+    var d = q.x;
+    if (q.y > d) {
+      n = vec3<f32>(0.0, sign(local.y), 0.0);
+      d = q.y;
+    }
+    if (q.z > d) {
+      n = vec3<f32>(0.0, 0.0, sign(local.z));
+      d = q.z;
+    }
+    return vec4<f32>(n, d);
+  }
+  // 平面
+  let n = normalize(c.p1.xyz);
+  return vec4<f32>(n, dot(pos - c.p0.xyz, n));
+}
+
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particles.data);
+  let field_index = global_invocation_id.x;
+  if (field_index >= total) {
+    return;
+  }
+  var particle = particles.data[field_index];
+  if (particle.uv_mass.z < 0.001) {
+    return;
+  }
+  var pos = particle.pos.xyz;
+  var is_collided = false;
+  for (var i = 0; i < collision.collider_count; i = i + 1) {
+    let c = colliders.data[i];
+    let sd = signed_distance(pos, c);
+    let penetration = collision.thickness - sd.w;
+    if (penetration > 0.0) {
+      pos = pos + sd.xyz * penetration;
+      // 摩擦：削减本帧的切向位移
+      let dx = pos - particle.old_pos.xyz;
+      let tangent = dx - sd.xyz * dot(dx, sd.xyz);
+      pos = pos - tangent * c.friction;
+      is_collided = true;
+    }
+  }
+  if (is_collided) {
+    particle.pos = vec4<f32>(pos, particle.pos.w);
+    particles.data[field_index] = particle;
+  }
+}
@@ -0,0 +1,65 @@
+#include "pbd/cloth_fluid_layout.wgsl"
+#include "lbm/struct/lattice_info.wgsl"
+#include "func/point_in_triangle.wgsl"
+
+struct Triangle {
+  p0: i32,
+  p1: i32,
+  p2: i32,
+};
+
+struct TrianglesBuffer {
+  data: array<Triangle>,
+};
+
+@group(0) @binding(4) var<storage, read_write> triangles: TrianglesBuffer;
+@group(0) @binding(5) var<storage, read_write> lattice_info: StoreInfo;
+
+// 一个三角形最多覆盖的格子边长，避免退化的三角形占用过多线程时间
+let MAX_COVER: i32 = 16;
+
+// 布料 -> 流体：三角形覆盖的格子成为随布料运动的外力格子
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let triangle_index = i32(global_invocation_id.x);
+  if (triangle_index >= cloth.triangle_num) {
+    return;
+  }
+  let tri = triangles.data[triangle_index];
+  let particle0 = particles.data[tri.p0];
+  let particle1 = particles.data[tri.p1];
+  let particle2 = particles.data[tri.p2];
+
+  let a = vec3<f32>(lattice_coord(particle0.pos.xyz), 0.0);
+  let b = vec3<f32>(lattice_coord(particle1.pos.xyz), 0.0);
+  let c = vec3<f32>(lattice_coord(particle2.pos.xyz), 0.0);
+
+  // 三角形的平均速度换算为格子速度
+  let velocity = (particle_velocity(particle0) + particle_velocity(particle1) + particle_velocity(particle2)) / 3.0;
+  var lattice_velocity = vec2<f32>(velocity.x, -velocity.y) / coupling.velocity_scale;
+  let speed = length(lattice_velocity);
+  if (speed > coupling.max_boundary_speed) {
+    lattice_velocity = lattice_velocity * (coupling.max_boundary_speed / speed);
+  }
+
+  let min_uv = max(vec2<i32>(floor(min(a.xy, min(b.xy, c.xy)))), vec2<i32>(1));
+  let max_uv = min(vec2<i32>(ceil(max(a.xy, max(b.xy, c.xy)))), min(field.lattice_size.xy - 2, min_uv + MAX_COVER));
+  for (var y = min_uv.y; y <= max_uv.y; y = y + 1) {
+    for (var x = min_uv.x; x <= max_uv.x; x = x + 1) {
+      let p = vec3<f32>(f32(x) + 0.5, f32(y) + 0.5, 0.0);
+      if (!is_point_in_triangle(p, a, b, c)) {
+        continue;
+      }
+      let field_index = x + y * field.lattice_size.x;
+      var info: LatticeInfo = lattice_info.data[field_index];
+      // 只改写流体格子，不覆盖边界、入口、出口与障碍物
+      if (info.material == 1 || info.material == 6) {
+        info.material = 6;
+        info.block_iter = coupling.boundary_iter;
+        info.vx = lattice_velocity.x;
+        info.vy = lattice_velocity.y;
+        lattice_info.data[field_index] = info;
+      }
+    }
+  }
+}
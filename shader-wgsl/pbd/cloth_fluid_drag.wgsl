@@ -0,0 +1,44 @@
+#include "pbd/cloth_fluid_layout.wgsl"
+
+@group(0) @binding(4) var macro_tex: texture_2d<f32>;
+
+// 流体 -> 布料：按粒子所在格子的流速施加空气阻力
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particles.data);
+  let field_index = global_invocation_id.x;
+  if (field_index >= total) {
+    return;
+  }
+  var particle: Particle = particles.data[field_index];
+  if (!is_movable_particle(particle)) {
+    return;
+  }
+
+  var wind = vec3<f32>(0.0);
+  let uv = vec2<i32>(lattice_coord(particle.pos.xyz));
+  if (is_inner_lattice(uv)) {
+    // 格子坐标的 y 轴朝下
+    let macro_info = textureLoad(macro_tex, uv, 0);
+    wind = vec3<f32>(macro_info.x, -macro_info.y, 0.0) * coupling.velocity_scale;
+  }
+
+  // 与 cloth_display 相同的法线计算方式
+  let p0 = particles.data[particle.connect[0]].pos.xyz - particle.pos.xyz;
+  let p1 = particles.data[particle.connect[1]].pos.xyz - particle.pos.xyz;
+  let p2 = particles.data[particle.connect[2]].pos.xyz - particle.pos.xyz;
+  let p3 = particles.data[particle.connect[3]].pos.xyz - particle.pos.xyz;
+  let normal = cross(p1, p0) + cross(p3, p2);
+
+  var drag = vec3<f32>(0.0);
+  let normal_len = length(normal);
+  if (normal_len > EPSILON) {
+    let n = normal / normal_len;
+    let relative_velocity = wind - particle_velocity(particle);
+    // 只保留法线方向的压力分量，切向摩擦可忽略
+    drag = coupling.drag_coefficient * dot(relative_velocity, n) * n;
+  }
+  // 重力由预测位置时的 cloth.gravity 施加
+  particle.accelerate = vec4<f32>(drag * particle.uv_mass.z, 0.0);
+  particles.data[field_index].accelerate = particle.accelerate;
+}
@@ -0,0 +1,34 @@
+#include "pbd/struct/particle.wgsl"
+#include "pbd/struct/cloth_uniform.wgsl"
+#include "pbd/struct/fluid_coupling.wgsl"
+#include "struct/field.wgsl"
+
+@group(0) @binding(0) var<uniform> cloth: ClothUniform;
+@group(0) @binding(1) var<uniform> coupling: FluidCouplingUniform;
+@group(0) @binding(2) var<uniform> field: FieldUniform;
+@group(0) @binding(3) var<storage, read_write> particles: ParticlesBuffer;
+
+let EPSILON: f32 = 0.0000001;
+
+fn is_movable_particle(particle: Particle) -> bool {
+  if (particle.uv_mass.z < 0.001) {
+    return false;
+  }
+  return true;
+}
+
+// 将布料空间的坐标投影到流体的格子坐标
+fn lattice_coord(pos: vec3<f32>) -> vec2<f32> {
+  let clip = coupling.mvp * vec4<f32>(pos, 1.0);
+  let ndc = clip.xy / clip.w;
+  let pixel = vec2<f32>((ndc.x + 1.0) * 0.5, (1.0 - ndc.y) * 0.5) * vec2<f32>(field.canvas_size.xy);
+  return pixel / field.lattice_pixel_size.xy;
+}
+
+fn is_inner_lattice(uv: vec2<i32>) -> bool {
+  return uv.x > 0 && uv.y > 0 && uv.x < field.lattice_size.x - 1 && uv.y < field.lattice_size.y - 1;
+}
+
+fn particle_velocity(particle: Particle) -> vec3<f32> {
+  return (particle.pos.xyz - particle.old_pos.xyz) / cloth.dt;
+}
@@ -0,0 +1,58 @@
+#include "pbd/struct/particle.wgsl"
+#include "pbd/struct/cloth_uniform.wgsl"
+#include "pbd/struct/cloth_interaction.wgsl"
+
+@group(0) @binding(0) var<uniform> cloth: ClothUniform;
+@group(0) @binding(1) var<uniform> ui: InteractionUniform;
+@group(0) @binding(2) var<storage, read_write> particles: ParticlesBuffer;
+@group(0) @binding(3) var<storage, read_write> state: InteractionState;
+
+// 鼠标约束：只需一个线程
+// 抓取期间粒子质量为无穷大，由触点直接驱动，松开后恢复原质量
+@compute @workgroup_size(1)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  if (ui.action == ACTION_PICK || ui.action == ACTION_TOGGLE_PIN) {
+    // 先放开之前抓取的粒子
+    if (state.particle_index >= 0) {
+      particles.data[state.particle_index].uv_mass.z = state.saved_invert_mass;
+      state.particle_index = -1;
+    }
+    let key = atomicLoad(&state.pick_key);
+    if (key == NONE_PICKED) {
+      state.particle_index = -1;
+      return;
+    }
+    let index = i32(key & ((1u << ui.index_bits) - 1u));
+    var particle = particles.data[index];
+    if (ui.action == ACTION_TOGGLE_PIN) {
+      if (particle.uv_mass.z < 0.001) {
+        particle.uv_mass.z = ui.default_invert_mass;
+      } else {
+        particle.uv_mass.z = 0.0;
+      }
+      particles.data[index] = particle;
+      state.particle_index = -1;
+      return;
+    }
+    state.particle_index = index;
+    state.saved_invert_mass = particle.uv_mass.z;
+    state.ray_t = dot(particle.pos.xyz - ui.ray_origin.xyz, ui.ray_dir.xyz);
+    particle.uv_mass.z = 0.0;
+    particles.data[index] = particle;
+    return;
+  }
+
+  let index = state.particle_index;
+  if (index < 0) {
+    return;
+  }
+  var particle = particles.data[index];
+  if (ui.action == ACTION_DRAG) {
+    let target_pos = ui.ray_origin.xyz + ui.ray_dir.xyz * state.ray_t;
+    particle.pos = vec4<f32>(mix(particle.pos.xyz, target_pos, ui.drag_stiffness), particle.pos.w);
+  } else if (ui.action == ACTION_RELEASE) {
+    particle.uv_mass.z = state.saved_invert_mass;
+    state.particle_index = -1;
+  }
+  particles.data[index] = particle;
+}
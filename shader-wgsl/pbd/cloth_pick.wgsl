@@ -0,0 +1,31 @@
+#include "pbd/struct/particle.wgsl"
+#include "pbd/struct/cloth_uniform.wgsl"
+#include "pbd/struct/cloth_interaction.wgsl"
+
+@group(0) @binding(0) var<uniform> cloth: ClothUniform;
+@group(0) @binding(1) var<uniform> ui: InteractionUniform;
+@group(0) @binding(2) var<storage, read_write> particles: ParticlesBuffer;
+@group(0) @binding(3) var<storage, read_write> state: InteractionState;
+
+// 找出离触点射线最近的粒子
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particles.data);
+  let field_index = global_invocation_id.x;
+  if (field_index >= total) {
+    return;
+  }
+  let particle = particles.data[field_index];
+  let to_particle = particle.pos.xyz - ui.ray_origin.xyz;
+  let t = dot(to_particle, ui.ray_dir.xyz);
+  if (t < 0.0) {
+    return;
+  }
+  let dis = length(to_particle - ui.ray_dir.xyz * t);
+  if (dis > ui.pick_radius) {
+    return;
+  }
+  let max_distance = f32((1u << (32u - ui.index_bits)) - 1u);
+  let key = (u32(dis / ui.pick_radius * max_distance) << ui.index_bits) | field_index;
+  atomicMin(&state.pick_key, key);
+}
@@ -8,7 +8,6 @@
 // @group(1) @binding(0) var<uniform> dy_uniform: DynamicUniform;
 
 
-let force: vec4<f32>  = vec4<f32>(0.0, 0.0, 0.0, 0.0);
 let ball_pos: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
 
 @compute @workgroup_size(32)
@@ -23,7 +22,8 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     // if (field_index > 32u) {
       let temp_pos = particle.pos;
       // 预估新的位置
-      particle.pos = particle.pos + (particle.pos - particle.old_pos) + (particle.accelerate + force) * cloth.dt * cloth.dt;
+      let velocity = (particle.pos - particle.old_pos) * (1.0 - cloth.damping);
+      particle.pos = particle.pos + velocity + (particle.accelerate + cloth.gravity) * cloth.dt * cloth.dt;
       particle.old_pos = temp_pos;
       particles.data[field_index] = particle;
 
@@ -0,0 +1,73 @@
+#include "pbd/struct/particle.wgsl"
+#include "pbd/struct/cloth_collision.wgsl"
+
+struct BinCountBuffer {
+  data: array<i32>,
+};
+
+struct BinBuffer {
+  data: array<i32>,
+};
+
+struct Vec4Buffer {
+  data: array<vec4<f32>>,
+};
+
+@group(0) @binding(0) var<uniform> bin: BinUniform;
+@group(0) @binding(1) var<uniform> collision: CollisionUniform;
+@group(0) @binding(2) var<storage, read_write> particles: ParticlesBuffer;
+@group(0) @binding(3) var<storage, read_write> rest_pos: Vec4Buffer;
+@group(0) @binding(4) var<storage, read_write> bin_counts: BinCountBuffer;
+@group(0) @binding(5) var<storage, read_write> bins: BinBuffer;
+@group(0) @binding(6) var<storage, read_write> corrections: Vec4Buffer;
+
+let EPSILON: f32 = 0.0000001;
+
+// 粒子间的自碰撞
+// 只读取粒子位置，修正量写入 corrections，由 cloth_self_collision_apply 统一施加，
+// 这样同一次 dispatch 里不会读到其它线程刚修改过的位置
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particles.data);
+  let field_index = global_invocation_id.x;
+  if (field_index >= total) {
+    return;
+  }
+  let particle = particles.data[field_index];
+  if (collision.self_collision == 0 || particle.uv_mass.z < 0.001) {
+    corrections.data[field_index] = vec4<f32>(0.0);
+    return;
+  }
+  let index = i32(field_index);
+  let pos = particle.pos.xyz;
+  let rest = rest_pos.data[field_index].xyz;
+  var correction = vec3<f32>(0.0);
+  let coord = bin_coord(pos, bin);
+  for (var z = -1; z <= 1; z = z + 1) {
+    for (var y = -1; y <= 1; y = y + 1) {
+      for (var x = -1; x <= 1; x = x + 1) {
+        let neighbor = coord + vec3<i32>(x, y, z);
+        if (any(neighbor < vec3<i32>(0)) || any(neighbor > bin.bin_max_index.xyz)) {
+          continue;
+        }
+        let hash = bin_hash(neighbor, bin);
+        let count = min(bin_counts.data[hash], bin.max_bin_count);
+        for (var i = 0; i < count; i = i + 1) {
+          let other = bins.data[hash * bin.max_bin_count + i];
+          if (other == index) {
+            continue;
+          }
+          let v = pos - particles.data[other].pos.xyz;
+          let dis = length(v);
+          // 初始状态下就比厚度更近的粒子（网格上相邻、折叠后相邻的层），
+          // 只保证不比初始距离更近，否则折叠的布料一开始就会被撑开
+          let min_dis = min(collision.thickness, length(rest - rest_pos.data[other].xyz));
+          if (dis < min_dis) {
+            correction = correction + v / max(dis, EPSILON) * (min_dis - dis) * 0.5;
+          }
+        }
+      }
+    }
+  }
+  corrections.data[field_index] = vec4<f32>(correction, 0.0);
+}
@@ -0,0 +1,20 @@
+#include "pbd/struct/particle.wgsl"
+
+struct Vec4Buffer {
+  data: array<vec4<f32>>,
+};
+
+@group(0) @binding(0) var<storage, read_write> particles: ParticlesBuffer;
+@group(0) @binding(1) var<storage, read_write> corrections: Vec4Buffer;
+
+// 施加 cloth_self_collision 算出的修正量
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  let total = arrayLength(&particles.data);
+  let field_index = global_invocation_id.x;
+  if (field_index >= total) {
+    return;
+  }
+  let correction = corrections.data[field_index].xyz;
+  particles.data[field_index].pos = particles.data[field_index].pos + vec4<f32>(correction, 0.0);
+}
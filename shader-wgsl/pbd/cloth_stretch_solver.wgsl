@@ -33,6 +33,10 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     // storage buffer item 里的字段如果有数组，那 item 只能使用 var 绑定，否则后面读取 item 字段时会报错：
     // The expression [73] may only be indexed by a constant
     var constraint = constraints.data[constraint_index];
+    // 已被撕裂的约束
+    if (constraint.rest_length < 0.0) {
+      continue;
+    }
     if (invert_mass0 < 0.0) {
       particle0_index = constraint.particle0;
       particle = particles.data[particle0_index];
@@ -49,10 +53,21 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     let dis = length(p0_minus_p1.xyz);
     // Cj(x)
     let distance = dis - constraint.rest_length;
+    if (cloth.tear_threshold > 0.0 && distance > constraint.rest_length * cloth.tear_threshold) {
+      // 撕裂处的三角形与弯曲约束在读回约束后由 CPU 端移除
+      constraint.rest_length = -1.0;
+      constraints.data[constraint_index] = constraint;
+      continue;
+    }
 
+    // 对角线上的是剪切约束
+    var compliance = cloth.compliance;
+    if (constraint.particle1 - constraint.particle0 == cloth.num_x + 1) {
+      compliance = cloth.shear_compliance;
+    }
     var correction_vector: vec4<f32>;
     // eq.18
-    let dlambda = -(distance + cloth.compliance * constraint.lambda) / (sum_mass + cloth.compliance);
+    let dlambda = -(distance + compliance * constraint.lambda) / (sum_mass + compliance);
     // eq.17
     correction_vector = dlambda * p0_minus_p1 / (dis + EPSILON);
 
@@ -0,0 +1,52 @@
+struct Collider {
+  // 0: sphere, 1: capsule, 2: box, 3: plane
+  ty: i32,
+  friction: f32,
+  radius: f32,
+  padding: f32,
+  // sphere: 球心，capsule: 端点 a，box: 中心，plane: 平面上的一点
+  p0: vec4<f32>,
+  // capsule: 端点 b，box: 半边长，plane: 法线
+  p1: vec4<f32>,
+};
+
+struct CollidersBuffer {
+  data: array<Collider>,
+};
+
+struct CollisionUniform {
+  collider_count: i32,
+  // 粒子与碰撞体表面，粒子与粒子之间保持的最小距离
+  thickness: f32,
+  self_collision: i32,
+  padding: f32,
+};
+
+struct BinUniform {
+  // xyz: 各轴向上的格子数，w: hash 容器数
+  bin_num: vec4<i32>,
+  // 格子各轴向上最大的索引数
+  bin_max_index: vec4<i32>,
+  bin_size: vec4<f32>,
+  // 转换到 【0～n] 坐标空间需要的偏移
+  pos_offset: vec4<f32>,
+  // 每个容器最多存放的粒子数
+  max_bin_count: i32,
+  padding0: f32,
+  padding1: f32,
+  padding2: f32,
+};
+
+let COLLIDER_SPHERE: i32 = 0;
+let COLLIDER_CAPSULE: i32 = 1;
+let COLLIDER_BOX: i32 = 2;
+
+fn bin_coord(pos: vec3<f32>, bin: BinUniform) -> vec3<i32> {
+  let coord = vec3<i32>(floor((pos + bin.pos_offset.xyz) / bin.bin_size.xyz));
+  return clamp(coord, vec3<i32>(0), bin.bin_max_index.xyz);
+}
+
+fn bin_hash(coord: vec3<i32>, bin: BinUniform) -> i32 {
+  let h = (u32(coord.x) * 73856093u) ^ (u32(coord.y) * 19349663u) ^ (u32(coord.z) * 83492791u);
+  return i32(h % u32(bin.bin_num.w));
+}
@@ -0,0 +1,31 @@
+struct InteractionUniform {
+  // 屏幕触点在布料空间的射线
+  ray_origin: vec4<f32>,
+  ray_dir: vec4<f32>,
+  // 0: idle, 1: pick, 2: drag, 3: release, 4: toggle pin
+  action: i32,
+  pick_radius: f32,
+  drag_stiffness: f32,
+  // 取消固定后恢复的质量倒数
+  default_invert_mass: f32,
+  // pick_key 低位存放粒子索引的位数
+  index_bits: u32,
+  padding0: u32,
+  padding1: u32,
+  padding2: u32,
+};
+
+struct InteractionState {
+  // 高位为射线距离，低 index_bits 位为粒子索引
+  pick_key: atomic<u32>,
+  particle_index: i32,
+  // 被抓取粒子在射线上的距离
+  ray_t: f32,
+  saved_invert_mass: f32,
+};
+
+let ACTION_PICK: i32 = 1;
+let ACTION_DRAG: i32 = 2;
+let ACTION_RELEASE: i32 = 3;
+let ACTION_TOGGLE_PIN: i32 = 4;
+let NONE_PICKED: u32 = 0xffffffffu;
@@ -7,4 +7,14 @@ struct ClothUniform {
    triangle_num: i32,
    compliance: f32,
    dt: f32,
+   // 拉伸超过 rest_length * (1 + tear_threshold) 的约束会被撕裂，<= 0 时不撕裂
+   tear_threshold: f32,
+   bend_compliance: f32,
+   shear_compliance: f32,
+   gravity: vec4<f32>,
+   // 速度阻尼
+   damping: f32,
+   padding0: f32,
+   padding1: f32,
+   padding2: f32,
 };
\ No newline at end of file
@@ -0,0 +1,9 @@
+struct FluidCouplingUniform {
+  mvp: mat4x4<f32>,
+  // 格子速度 -> 布料空间速度的缩放
+  velocity_scale: f32,
+  drag_coefficient: f32,
+  // 布料覆盖的格子保持为外力格子的 lbm 迭代次数
+  boundary_iter: i32,
+  max_boundary_speed: f32,
+};
@@ -34,7 +34,7 @@ struct DynamicUniform {
     max_num_x: i32,
     // 当前 mesh coloring 分组的数据长度
     group_len: i32,
-    // 迭代計數的倒數
+    // 迭代計數的倒數，XPBD 求解时不再使用
     invert_iter: f32,
 };
 @group(1) @binding(0) var<uniform> dy_uniform: DynamicUniform;
@@ -47,6 +47,8 @@ fn is_movable_particle(particle: Particle) -> bool {
 }
 
 
+// 三角形弯曲约束 C = |v - c| - h0，c 为三个粒子的中心
+// XPBD：柔度 cloth.bend_compliance 已经除以了 dt^2
 @compute @workgroup_size(32, 1)
 fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {  
     var field_index = i32(global_invocation_id.x);
@@ -63,39 +65,40 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
         }
 
         let bending: BendingConstraint = constraints.data[constraint_index];
-        // 弯曲约束 C = acos(d) -ϕ0, d = n1.n2
+        // 跨过撕裂处的约束
+        if (bending.v < 0) {
+            continue;
+        }
         var v: Particle = particles.data[bending.v];
         var b0: Particle = particles.data[bending.b0];
         var b1: Particle = particles.data[bending.b1];
 
         // eq. 3
         let c: vec3<f32> = (b0.pos.xyz + b1.pos.xyz + v.pos.xyz) * 0.33333333;
-        // eq. 8
-        let w = b0.uv_mass.z + b1.uv_mass.z + 2.0 * v.uv_mass.z;
         let v_minus_c = v.pos.xyz - c;
         let v_minus_c_len = length(v_minus_c);
-        // 公式 6
-        let k = 1.0 - pow(1.0 - 0.01, dy_uniform.invert_iter);
-        // float k = 0.0;
-        // 不等式約束方程 5
-        // 弯曲度大于静态值才执行位置修正（论文里的表述反了）
-        let c_triangle = v_minus_c_len - (k + bending.h0);
-        if (c_triangle <= 0.0) {
-            return;
+        let c_triangle = v_minus_c_len - bending.h0;
+        if (v_minus_c_len < 0.0000001 || c_triangle <= 0.0) {
+            continue;
+        }
+        // ∇v C = 2/3 n，∇b0 C = ∇b1 C = -1/3 n
+        let n = v_minus_c / v_minus_c_len;
+        let sum_w = (4.0 * v.uv_mass.z + b0.uv_mass.z + b1.uv_mass.z) / 9.0;
+        if (sum_w < 0.0000001) {
+            continue;
         }
-        // 方程 9a, 9b, 9c
-        let f = v_minus_c * (1.0 - (k + bending.h0) / v_minus_c_len);
+        let dlambda = -c_triangle / (sum_w + cloth.bend_compliance);
 
         if (is_movable_particle(v)) {
-            v.pos = vec4<f32>(v.pos.xyz + (-4.0 * v.uv_mass.z) / w * f, 0.0);
+            v.pos = vec4<f32>(v.pos.xyz + v.uv_mass.z * dlambda * 0.6666667 * n, v.pos.w);
             particles.data[bending.v] = v;
         }
         if (is_movable_particle(b0)) {
-            b0.pos = vec4<f32>(b0.pos.xyz + (2.0 * b0.uv_mass.z) / w * f, 0.0);
+            b0.pos = vec4<f32>(b0.pos.xyz - b0.uv_mass.z * dlambda * 0.3333333 * n, b0.pos.w);
             particles.data[bending.b0] = b0;
         }
         if (is_movable_particle(b1)) {
-            b1.pos = vec4<f32>(b1.pos.xyz + (2.0 * b1.uv_mass.z) / w * f, 0.0);
+            b1.pos = vec4<f32>(b1.pos.xyz - b1.uv_mass.z * dlambda * 0.3333333 * n, b1.pos.w);
             particles.data[bending.b1] = b1;
         }
     }
@@ -0,0 +1,93 @@
+#include "pbd/struct/particle.wgsl"
+#include "pbd/struct/cloth_uniform.wgsl"
+#include "pbd/struct/dynamic_uniform.wgsl"
+
+// p0, p1 为两个三角形的公共边，p2, p3 为各自的对角顶点
+struct DihedralConstraint {
+   p0: i32,
+   p1: i32,
+   p2: i32,
+   p3: i32,
+   rest_angle: f32,
+   lambda: f32,
+   padding0: f32,
+   padding1: f32,
+};
+
+struct DihedralConstraintBuf {
+    data: array<DihedralConstraint>,
+};
+
+struct ReorderBuf {
+    data: array<i32>,
+};
+
+@group(0) @binding(0) var<uniform> cloth: ClothUniform;
+@group(0) @binding(1) var<storage, read_write> particles: ParticlesBuffer;
+@group(0) @binding(2) var<storage, read_write> constraints: DihedralConstraintBuf;
+@group(0) @binding(3) var<storage, read_write> reorder_constraints: ReorderBuf;
+
+@group(1) @binding(0) var<uniform> dy_uniform: DynamicUniform;
+
+let EPSILON: f32 = 0.0000001;
+
+// Position Based Dynamics, Appendix A: Bending Constraint Projection
+@compute @workgroup_size(32)
+fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+  var field_index = i32(global_invocation_id.x);
+  if (field_index >= dy_uniform.group_len) {
+    return;
+  }
+  let constraint_index = reorder_constraints.data[field_index + dy_uniform.offset];
+  let c = constraints.data[constraint_index];
+  var particle0 = particles.data[c.p0];
+  var particle1 = particles.data[c.p1];
+  var particle2 = particles.data[c.p2];
+  var particle3 = particles.data[c.p3];
+  let w0 = particle0.uv_mass.z;
+  let w1 = particle1.uv_mass.z;
+  let w2 = particle2.uv_mass.z;
+  let w3 = particle3.uv_mass.z;
+
+  // 以 p0 为原点
+  let x1 = particle1.pos.xyz - particle0.pos.xyz;
+  let x2 = particle2.pos.xyz - particle0.pos.xyz;
+  let x3 = particle3.pos.xyz - particle0.pos.xyz;
+  let c12 = cross(x1, x2);
+  let c13 = cross(x1, x3);
+  let len12 = length(c12);
+  let len13 = length(c13);
+  if (len12 < EPSILON || len13 < EPSILON) {
+    return;
+  }
+  let n1 = c12 / len12;
+  let n2 = c13 / len13;
+  let d = clamp(dot(n1, n2), -1.0, 1.0);
+  let sin2 = 1.0 - d * d;
+  if (sin2 < EPSILON) {
+    return;
+  }
+  let q2 = (cross(x1, n2) + cross(n1, x1) * d) / len12;
+  let q3 = (cross(x1, n1) + cross(n2, x1) * d) / len13;
+  let q1 = -(cross(x2, n2) + cross(n1, x2) * d) / len12 - (cross(x3, n1) + cross(n2, x3) * d) / len13;
+  let q0 = -q1 - q2 - q3;
+
+  // 梯度 ∇C = -q / sqrt(1 - d^2)
+  let sum_grad = (w0 * dot(q0, q0) + w1 * dot(q1, q1) + w2 * dot(q2, q2) + w3 * dot(q3, q3)) / sin2;
+  let denominator = sum_grad + cloth.bend_compliance;
+  if (denominator < EPSILON) {
+    return;
+  }
+  // 每个子步只迭代一次，lambda 始终从 0 开始
+  let dlambda = -(acos(d) - c.rest_angle) / denominator;
+
+  let s = -dlambda / sqrt(sin2);
+  particle0.pos = particle0.pos + vec4<f32>(w0 * s * q0, 0.0);
+  particle1.pos = particle1.pos + vec4<f32>(w1 * s * q1, 0.0);
+  particle2.pos = particle2.pos + vec4<f32>(w2 * s * q2, 0.0);
+  particle3.pos = particle3.pos + vec4<f32>(w3 * s * q3, 0.0);
+  particles.data[c.p0] = particle0;
+  particles.data[c.p1] = particle1;
+  particles.data[c.p2] = particle2;
+  particles.data[c.p3] = particle3;
+}
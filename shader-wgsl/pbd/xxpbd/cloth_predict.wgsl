@@ -8,7 +8,6 @@ struct DynamicUniform {
 @group(1) @binding(0) var<uniform> dy_uniform: DynamicUniform;
 
 
-let force: vec4<f32>  = vec4<f32>(0.0, -29.98, 0.0, 0.0);
 let ball_pos: vec4<f32> = vec4<f32>(0.0, 0.0, 0.0, 0.0);
 
 @compute @workgroup_size(32, 1, 1)
@@ -29,7 +28,9 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     //   particle.pos = particle.pos + (particle.pos - particle.old_pos) + (particle.accelerate + force) * cloth.dt * cloth.dt;
     // Xn+1 = Xn + dt * Vn + dt^2 * M^-1 * F(Xn)
         // particle.pos = particle.pos + cloth.dt * particle.accelerate + force * particle.uv_mass.z * cloth.dt * cloth.dt;
-        particle.pos = particle.pos + (particle.pos - particle.old_pos) + force * particle.uv_mass.z * cloth.dt * cloth.dt ;
+        // 这里的 gravity 是力，按 invert_mass 换算成加速度
+        let velocity = (particle.pos - particle.old_pos) * (1.0 - cloth.damping);
+        particle.pos = particle.pos + velocity + cloth.gravity * particle.uv_mass.z * cloth.dt * cloth.dt;
 
       particle.old_pos = temp_pos;
 
@@ -45,9 +45,14 @@ fn cs_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
         // Cj(x)
         let distance = dis - constraint.rest_length;
 
+        // 对角线上的是剪切约束
+        var compliance = cloth.compliance;
+        if (constraint.particle1 - constraint.particle0 == cloth.num_x + 1) {
+            compliance = cloth.shear_compliance;
+        }
         var correction_vector: vec4<f32>;
         // eq.18
-        let dlambda = -distance / (sum_mass + cloth.compliance);
+        let dlambda = -distance / (sum_mass + compliance);
         // eq.17
         correction_vector = dlambda * p0_minus_p1 / (dis + EPSILON);
 
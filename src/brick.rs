@@ -49,4 +49,12 @@ impl Brick {
 
         Self { display_node }
     }
+
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) =
+            crate::util::shader::reload_shader(device, changed, "procedural/brick")
+        {
+            self.display_node.reload(device, &shader);
+        }
+    }
 }
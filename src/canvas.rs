@@ -1,4 +1,7 @@
-use crate::Diffraction;
+use crate::util::render_graph::{RenderGraph, FRAME};
+use crate::util::{resource_pool, FrameRecorder, GpuProfiler};
+use crate::{Diffraction, SimClock, SIM_STEPS_PER_SECOND};
+use std::path::PathBuf;
 
 use app_surface::{
     math::{Position, Rect, Size},
@@ -12,11 +15,18 @@ pub struct Canvas {
     nature_node: Diffraction,
     floor_node: crate::Floor,
     brick: crate::Brick,
+    // 目前只绘制 brick
+    graph: RenderGraph<crate::Brick>,
+    profiler: GpuProfiler,
+    recorder: Option<FrameRecorder>,
+    clock: SimClock,
 }
 
 #[allow(dead_code)]
 impl Canvas {
     pub fn new(app_view: AppSurface) -> Self {
+        // 池里的资源属于之前的 device
+        resource_pool::clear();
         let dc_origin =
             Position::new(app_view.config.width as f32 / 2.0, app_view.config.height as f32 / 2.0);
         let depth_tex = crate::util::load_texture::empty(
@@ -41,12 +51,90 @@ impl Canvas {
 
         let brick = crate::Brick::new(&app_view);
 
-        Self { app_view, dc_origin, depth_tex, d3_noise, nature_node, floor_node, brick }
+        let mut graph = RenderGraph::new();
+        graph.add_render_pass(
+            "brick",
+            &[],
+            FRAME,
+            wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            |brick, rpass| brick.display_node.draw_rpass(rpass),
+        );
+        let surface_size = (app_view.config.width, app_view.config.height);
+        if let Err(e) = graph.compile(&app_view.device, &app_view.queue, surface_size) {
+            panic!("canvas render graph: {}", e);
+        }
+        let profiler = GpuProfiler::new(&app_view.device, &app_view.queue);
+
+        Self {
+            app_view,
+            dc_origin,
+            depth_tex,
+            d3_noise,
+            nature_node,
+            floor_node,
+            brick,
+            graph,
+            profiler,
+            recorder: None,
+            clock: SimClock::new(SIM_STEPS_PER_SECOND),
+        }
+    }
+
+    // 暂停后仍会重绘，只是不再推进模拟
+    pub fn pause(&mut self) {
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    // 暂停状态下推进 n 个模拟步
+    pub fn step(&mut self, n: u32) {
+        self.clock.step(n);
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.clock.set_time_scale(scale);
+    }
+
+    // 开启/关闭各个 pass 的耗时统计
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler.set_enabled(enabled);
+    }
+
+    // 各个 pass 的 GPU 耗时滚动平均（毫秒）
+    pub fn profiler_averages(&self) -> Vec<(&'static str, f32)> {
+        self.profiler.averages()
+    }
+
+    // 开始录制 PNG 图片序列，每绘制一帧保存一张
+    // 录制期间按真实时间绘制，图片序列的播放帧率在合成视频时决定
+    pub fn start_recording(&mut self, output_dir: PathBuf) -> Result<(), String> {
+        self.stop_recording();
+        self.recorder = Some(FrameRecorder::new(&mut self.app_view, output_dir)?);
+        Ok(())
+    }
+
+    // 停止录制，返回录制的帧数
+    pub fn stop_recording(&mut self) -> u32 {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(&mut self.app_view),
+            None => 0,
+        }
     }
 }
 
 impl SurfaceFrame for Canvas {
     fn resize_surface(&mut self) {
+        if self.recorder.is_some() {
+            let count = self.stop_recording();
+            crate::console_log!("frame recording stopped by resize after {} frames", count);
+        }
         self.app_view.resize_surface();
     }
     fn touch(&mut self, touch: Touch) {
@@ -66,42 +154,37 @@ impl SurfaceFrame for Canvas {
 
     fn enter_frame(&mut self) {
         // self.nature_node.enter_frame(&mut self.app_view);
+        // floor_node 与 nature_node 需要深度缓冲 depth_tex，目前没有绘制
 
+        let changed = crate::util::changed_shaders();
+        if !changed.is_empty() {
+            crate::util::resource_pool::evict_shaders(&changed);
+            self.brick.reload_shaders(&self.app_view.device, &changed);
+        }
+        // 目前绘制的节点都是静态的，没有需要按模拟步推进的状态
+        let _steps = self.clock.tick();
+        self.profiler.begin_frame();
         let (frame, frame_view) = self.app_view.get_current_frame_view();
-        let color_attachments = [Some(wgpu::RenderPassColorAttachment {
-            view: &frame_view,
-            resolve_target: None,
-            ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: true },
-        })];
-        let render_pass_descriptor = wgpu::RenderPassDescriptor {
-            label: None,
-            color_attachments: &color_attachments,
-            // depth_stencil_attachment: None,
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_tex.tex_view,
-                depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(1.0), store: false }),
-                stencil_ops: None,
-            }),
-        };
-
         let mut encoder =
             self.app_view.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("diffraction encoder"),
             });
-        {
-            // let mut rpass = encoder.begin_render_pass(&render_pass_descriptor);
-            // self.floor_node.draw_render_pass(&mut rpass);
-            // self.nature_node.draw_render_pass(&mut rpass);
-        }
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &color_attachments,
-                depth_stencil_attachment: None,
-            });
-            self.brick.display_node.draw_rpass(&mut rpass);
+        self.graph.execute(&mut encoder, &self.brick, &frame_view, &mut self.profiler);
+        if self.profiler.is_enabled() {
+            self.profiler.resolve(&mut encoder);
         }
         self.app_view.queue.submit(Some(encoder.finish()));
+        if self.profiler.is_enabled() {
+            self.profiler.end_frame(&self.app_view.device);
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) =
+                recorder.capture(&self.app_view.device, &self.app_view.queue, &frame.texture)
+            {
+                crate::console_log!("frame recording stopped: {}", e);
+                self.stop_recording();
+            }
+        }
         frame.present();
     }
 }
@@ -1,80 +1,431 @@
-use crate::util::BufferObj;
-use crate::{setting_obj::SettingObj, D3FluidPlayer, FieldPlayer, FieldType, FluidPlayer, Player};
+use crate::edit_history::{EditCommand, EditHistory, SettingValue};
+use crate::input_script::{ScriptRecorder, ScriptReplayer};
+use crate::util::resource_pool::{self, PooledBuffer};
+use crate::util::{FrameRecorder, GpuProfiler, HudOverlay};
+use crate::{
+    setting_obj::SettingObj, D3FluidPlayer, EditTool, FieldPlayer, FieldType, FlagPlayer,
+    FluidPlayer, InputEvent, InputScript, Player, SimClock, SIM_STEPS_PER_SECOND,
+};
 use app_surface::{
     math::{Position, Size},
     AppSurface, SurfaceFrame, Touch, TouchPhase,
 };
+use std::path::PathBuf;
 
 pub struct CombinateCanvas {
     pub app_view: AppSurface,
     canvas_size: Size<u32>,
-    canvas_buf: BufferObj,
+    canvas_buf: PooledBuffer,
     setting: SettingObj,
     player: Box<dyn Player>,
+    // 所有 player 共用的模拟时钟，重建 player 时沿用暂停状态与时间缩放
+    clock: SimClock,
+    recorder: Option<FrameRecorder>,
+    profiler: GpuProfiler,
+    // 性能统计的屏幕叠加层，显示时才创建
+    hud: Option<HudOverlay>,
+    input_recorder: Option<ScriptRecorder>,
+    // 回放期间忽略实时输入
+    input_replayer: Option<ScriptReplayer>,
+    // 重建 player 时沿用
+    brush: Option<(f32, f32)>,
+    edit_tool: EditTool,
+    // 障碍物等材质编辑与参数修改的撤销/重做记录，只对当前的场景有效
+    history: EditHistory,
 }
 
 impl CombinateCanvas {
     pub fn new(app_view: AppSurface, setting: SettingObj) -> Self {
+        // 池里的资源属于之前的 device
+        resource_pool::clear();
         let canvas_size: Size<u32> = (&app_view.config).into();
         let mut setting = setting;
         setting.update_canvas_size(&app_view.device, &app_view.queue, canvas_size);
-        let canvas_buf = crate::util::BufferObj::create_empty_storage_buffer(
-            &app_view.device,
-            (canvas_size.width * canvas_size.height * 12) as u64,
-            false,
-            Some("canvas_buf"),
-        );
+        let canvas_buf = Self::create_canvas_buf(&app_view, canvas_size);
         let player = Self::create_player(&app_view, canvas_size, &canvas_buf, &setting);
         if let Some(callback) = app_view.callback_to_app {
             callback(0);
         }
-        CombinateCanvas { app_view, canvas_size, canvas_buf, setting, player }
+        let profiler = GpuProfiler::new(&app_view.device, &app_view.queue);
+        CombinateCanvas {
+            app_view,
+            canvas_size,
+            canvas_buf,
+            setting,
+            player,
+            clock: SimClock::new(SIM_STEPS_PER_SECOND),
+            recorder: None,
+            profiler,
+            hud: None,
+            input_recorder: None,
+            input_replayer: None,
+            brush: None,
+            edit_tool: EditTool::Force,
+            history: EditHistory::default(),
+        }
     }
 
     pub fn update_field_type(
         &mut self, field_ty: FieldType, animation_ty: crate::FieldAnimationType,
     ) {
-        if self.setting.update_field_type(&self.app_view.queue, field_ty) {
-            self.setting.animation_type = animation_ty;
-            self.recreate_player();
-        }
+        self.input(InputEvent::FieldType(field_ty, animation_ty));
     }
 
     pub fn update_fluid_viscosity(&mut self, nu: f32) {
-        if self.setting.field_type == FieldType::Fluid && self.setting.fluid_viscosity != nu {
-            self.setting.fluid_viscosity = nu;
-            self.player.update_uniforms(&self.app_view.queue, &self.setting);
-        }
+        self.input(InputEvent::FluidViscosity(nu));
     }
 
     pub fn update_particles_count(&mut self, count: i32) {
-        self.setting.update_particles_count(&self.app_view.device, &self.app_view.queue, count);
+        self.input(InputEvent::ParticlesCount(count));
     }
 
     pub fn update_particle_color(&mut self, color_type: crate::ParticleColorType) {
-        self.setting.update_particle_color(&self.app_view.device, &self.app_view.queue, color_type);
+        self.input(InputEvent::ParticleColor(color_type));
     }
 
     pub fn update_particle_point_size(&mut self, point_size: i32) {
-        self.setting.update_particle_point_size(&self.app_view.queue, point_size);
+        self.input(InputEvent::ParticlePointSize(point_size));
     }
 
     pub fn update_animation_type(&mut self, ty: crate::FieldAnimationType) {
-        self.setting.animation_type = ty;
-        self.recreate_player();
+        self.input(InputEvent::AnimationType(ty));
     }
 
     pub fn recreate_player(&mut self) {
+        // shader 与 pipeline 取自 resource_pool 的缓存，旧 player 回收的纹理与 buffer 尺寸相同时直接复用
+        self.player.recycle_resources();
         self.player =
             Self::create_player(&self.app_view, self.canvas_size, &self.canvas_buf, &self.setting);
+        resource_pool::trim();
+        // 创建 player 的耗时不计入模拟
+        self.clock.reset();
+        if let Some((radius, falloff)) = self.brush {
+            self.player.set_brush(radius, falloff);
+        }
+        self.player.set_edit_tool(self.edit_tool);
+        // 材质编辑属于旧的场景
+        self.history.clear();
+    }
+
+    // 暂停后仍会重绘，只是不再推进模拟
+    pub fn pause(&mut self) {
+        self.clock.pause();
+        self.player.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+        self.player.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    // 暂停状态下推进 n 个模拟步
+    pub fn step(&mut self, n: u32) {
+        self.clock.step(n);
+        self.player.step(n);
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.clock.set_time_scale(scale);
+        self.player.set_time_scale(scale);
+    }
+
+    // 开始录制 PNG 图片序列，录制期间模拟按 fps 固定推进
+    pub fn start_recording(&mut self, output_dir: PathBuf, fps: u32) -> Result<(), String> {
+        self.stop_recording();
+        let recorder = FrameRecorder::new(&mut self.app_view, output_dir)?.with_fps(fps);
+        self.recorder = Some(recorder);
+        Ok(())
+    }
+
+    // 停止录制，返回录制的帧数
+    pub fn stop_recording(&mut self) -> u32 {
+        let count = match self.recorder.take() {
+            Some(recorder) => recorder.finish(&mut self.app_view),
+            None => 0,
+        };
+        self.clock.reset();
+        count
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    // 开启/关闭各个 pass 的耗时统计
+    // device 由 AppSurface::new 创建，它不请求 wgpu::Features::TIMESTAMP_QUERY，所以目前只统计 CPU 帧时间；
+    // 要统计 GPU 耗时，需要 app-surface 在适配器支持时请求该特性
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if enabled && !self.profiler.is_timestamp_supported() {
+            crate::console_log!(
+                "TIMESTAMP_QUERY is not enabled on this device, only CPU frame time is profiled"
+            );
+        }
+        self.profiler.set_enabled(enabled);
+    }
+
+    // 设备是否启用了时间戳查询，否则只有 CPU 帧时间
+    pub fn is_gpu_timing_supported(&self) -> bool {
+        self.profiler.is_timestamp_supported()
+    }
+
+    // 显示性能统计叠加层，会同时开启耗时统计
+    pub fn set_hud_visible(&mut self, visible: bool) {
+        if visible {
+            if self.hud.is_none() {
+                let scale = (2.0 * self.app_view.scale_factor).round().max(1.0);
+                self.hud = Some(HudOverlay::new(
+                    &self.app_view.device,
+                    self.app_view.config.format,
+                    scale,
+                ));
+            }
+            self.profiler.set_enabled(true);
+        } else {
+            self.hud = None;
+        }
+    }
+
+    pub fn is_hud_visible(&self) -> bool {
+        self.hud.is_some()
+    }
+
+    // CPU 帧时间的滚动平均（毫秒）
+    pub fn profiler_frame_time(&self) -> f32 {
+        self.profiler.frame_time()
+    }
+
+    // 各个 pass 的 GPU 耗时滚动平均（毫秒）
+    pub fn profiler_averages(&self) -> Vec<(&'static str, f32)> {
+        self.profiler.averages()
+    }
+
+    // 外力笔刷的像素半径与边缘衰减 [0, 1]
+    pub fn set_brush(&mut self, radius: f32, falloff: f32) {
+        self.input(InputEvent::Brush(radius, falloff));
+    }
+
+    // 流体场景的编辑工具：外力、障碍物、橡皮擦、墙、入口与出口
+    pub fn set_edit_tool(&mut self, tool: EditTool) {
+        self.input(InputEvent::EditTool(tool));
+    }
+
+    pub fn edit_tool(&self) -> EditTool {
+        self.edit_tool
+    }
+
+    // 开始录制输入脚本，当前的场景与参数作为脚本开头的事件
+    pub fn start_input_recording(&mut self) {
+        self.input_replayer = None;
+        let mut recorder = ScriptRecorder::new();
+        recorder
+            .record(InputEvent::FieldType(self.setting.field_type, self.setting.animation_type));
+        // 重建 player，回放时从同样的初始状态开始
+        recorder.record(InputEvent::AnimationType(self.setting.animation_type));
+        recorder.record(InputEvent::FluidViscosity(self.setting.fluid_viscosity));
+        recorder.record(InputEvent::ParticlesCount(self.setting.particles_count));
+        recorder.record(InputEvent::ParticleColor(self.setting.color_ty));
+        recorder
+            .record(InputEvent::ParticlePointSize(self.setting.particles_uniform_data.point_size));
+        if let Some((radius, falloff)) = self.brush {
+            recorder.record(InputEvent::Brush(radius, falloff));
+        }
+        recorder.record(InputEvent::EditTool(self.edit_tool));
+        self.input_recorder = Some(recorder);
+        self.recreate_player();
+    }
+
+    pub fn stop_input_recording(&mut self) -> Option<InputScript> {
+        self.input_recorder.take().map(|recorder| recorder.finish())
+    }
+
+    // 回放输入脚本，事件在记录时的模拟步数处触发
+    // 与录制时一样从重建的 player 开始，脚本开头的场景事件只在场景不同时才会重建
+    pub fn start_replay(&mut self, script: InputScript, looping: bool) {
+        self.input_recorder = None;
+        self.input_replayer = Some(ScriptReplayer::new(script, looping));
+        self.recreate_player();
+    }
+
+    pub fn stop_replay(&mut self) {
+        self.input_replayer = None;
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.input_replayer.is_some()
     }
 
     pub fn reset(&mut self) {
-        self.player.reset(&self.app_view.device, &self.app_view.queue);
+        self.input(InputEvent::Reset);
+    }
+
+    // 撤销最近一次障碍物、墙、入口、出口、擦除、外力笔画或参数修改
+    // 外力笔画的撤销是沿原笔画反向施加外力，已经扩散的动量不会精确复原
+    pub fn undo(&mut self) {
+        self.input(InputEvent::Undo);
+    }
+
+    pub fn redo(&mut self) {
+        self.input(InputEvent::Redo);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
     }
 
     pub fn on_click(&mut self, pos: Position) {
-        self.player.on_click(&self.app_view.device, &self.app_view.queue, pos);
+        self.input(InputEvent::Click(pos));
+    }
+
+    // 多点触摸：平台层按触摸点的 id 分别传入
+    pub fn pointer_event(&mut self, id: u64, phase: TouchPhase, pos: Position) {
+        let event = match phase {
+            TouchPhase::Started => InputEvent::TouchBegin(id, pos),
+            TouchPhase::Moved => InputEvent::TouchMove(id, pos),
+            TouchPhase::Ended | TouchPhase::Cancelled => InputEvent::TouchEnd(id, pos),
+        };
+        self.input(event);
+    }
+
+    // 实时输入：录制时写入脚本，回放时忽略
+    fn input(&mut self, event: InputEvent) {
+        if self.input_replayer.is_some() {
+            return;
+        }
+        if let Some(recorder) = self.input_recorder.as_mut() {
+            recorder.record(event);
+        }
+        self.apply_input(event);
+    }
+
+    // 执行输入并写入撤销记录
+    fn apply_input(&mut self, event: InputEvent) {
+        let before = self.setting_value(&event);
+        self.apply_event(event);
+        match event {
+            InputEvent::TouchBegin(..)
+            | InputEvent::TouchMove(..)
+            | InputEvent::TouchEnd(..)
+            | InputEvent::Click(_) => {
+                // 一笔结束后才会取到
+                if let Some(edit) = self.player.take_lattice_edit() {
+                    self.history.record(EditCommand::Lattice(edit));
+                }
+            }
+            _ => {
+                if let (Some(before), Some(after)) = (before, self.setting_value(&event)) {
+                    if before != after {
+                        self.history.record(EditCommand::Setting { before, after });
+                    }
+                }
+            }
+        }
+    }
+
+    // 参数事件对应的当前值
+    fn setting_value(&self, event: &InputEvent) -> Option<SettingValue> {
+        Some(match event {
+            InputEvent::FluidViscosity(_) => {
+                SettingValue::FluidViscosity(self.setting.fluid_viscosity)
+            }
+            InputEvent::ParticlesCount(_) => {
+                SettingValue::ParticlesCount(self.setting.particles_count)
+            }
+            InputEvent::ParticleColor(_) => SettingValue::ParticleColor(self.setting.color_ty),
+            InputEvent::ParticlePointSize(_) => {
+                SettingValue::ParticlePointSize(self.setting.particles_uniform_data.point_size)
+            }
+            _ => return None,
+        })
+    }
+
+    fn apply_command(&mut self, command: &EditCommand, is_undo: bool) {
+        match command {
+            EditCommand::Lattice(edit) => self.player.apply_lattice_edit(edit, is_undo),
+            EditCommand::Setting { before, after } => {
+                let value = if is_undo { *before } else { *after };
+                self.apply_event(value.to_input());
+            }
+        }
+    }
+
+    fn apply_event(&mut self, event: InputEvent) {
+        let (device, queue) = (&self.app_view.device, &self.app_view.queue);
+        match event {
+            InputEvent::TouchBegin(id, pos) => self.player.touch_begin(device, queue, id, pos),
+            InputEvent::TouchMove(id, pos) => self.player.touch_move(device, queue, id, pos),
+            InputEvent::TouchEnd(id, pos) => self.player.touch_end(device, queue, id, pos),
+            InputEvent::Click(pos) => self.player.on_click(device, queue, pos),
+            InputEvent::FluidViscosity(nu) => {
+                let is_lbm = self.setting.field_type == FieldType::Fluid
+                    || self.setting.field_type == FieldType::ClothInFluid;
+                if is_lbm && self.setting.fluid_viscosity != nu {
+                    self.setting.fluid_viscosity = nu;
+                    self.player.update_uniforms(queue, &self.setting);
+                }
+            }
+            InputEvent::ParticlesCount(count) => {
+                self.setting.update_particles_count(device, queue, count);
+            }
+            InputEvent::ParticleColor(color_type) => {
+                self.setting.update_particle_color(device, queue, color_type);
+            }
+            InputEvent::ParticlePointSize(point_size) => {
+                self.setting.update_particle_point_size(queue, point_size);
+            }
+            InputEvent::AnimationType(ty) => {
+                if self.setting.update_animation_type(ty) {
+                    self.recreate_player();
+                }
+            }
+            InputEvent::FieldType(field_ty, animation_ty) => {
+                if self.setting.update_field_type(queue, field_ty) {
+                    self.setting.update_animation_type(animation_ty);
+                    self.recreate_player();
+                }
+            }
+            InputEvent::Reset => {
+                self.player.reset(device, queue);
+                self.history.clear();
+            }
+            InputEvent::Brush(radius, falloff) => {
+                self.brush = Some((radius, falloff));
+                self.player.set_brush(radius, falloff);
+            }
+            InputEvent::EditTool(tool) => {
+                self.edit_tool = tool;
+                self.player.set_edit_tool(tool);
+            }
+            InputEvent::Undo => {
+                if let Some(command) = self.history.pop_undo() {
+                    self.apply_command(&command, true);
+                    self.history.push_redo(command);
+                }
+            }
+            InputEvent::Redo => {
+                if let Some(command) = self.history.pop_redo() {
+                    self.apply_command(&command, false);
+                    self.history.push_undo(command);
+                }
+            }
+        }
+    }
+
+    // resize 回到之前的尺寸时复用回收的 buffer，并清空上次残留的轨迹
+    fn create_canvas_buf(app_view: &AppSurface, canvas_size: Size<u32>) -> PooledBuffer {
+        PooledBuffer::empty_storage(
+            &app_view.device,
+            &app_view.queue,
+            (canvas_size.width * canvas_size.height * 12) as wgpu::BufferAddress,
+            Some("canvas_buf"),
+        )
     }
 
     fn create_player<'a>(
@@ -93,6 +444,9 @@ impl CombinateCanvas {
             FieldType::Fluid => {
                 Box::new(FluidPlayer::new(&app_view, canvas_size, canvas_buf, setting))
             }
+            FieldType::ClothInFluid => {
+                Box::new(FlagPlayer::new(&app_view, canvas_size, canvas_buf, setting))
+            }
             _ => Box::new(D3FluidPlayer::new(
                 &app_view.device,
                 &app_view.queue,
@@ -106,40 +460,99 @@ impl CombinateCanvas {
 }
 
 impl SurfaceFrame for CombinateCanvas {
+    // 单点触摸与鼠标输入统一使用 id 0
     fn touch(&mut self, touch: Touch) {
-        match touch.phase {
-            TouchPhase::Started => {
-                self.player.touch_begin(&self.app_view.device, &self.app_view.queue)
-            }
-            TouchPhase::Moved => {
-                self.player.touch_move(&self.app_view.device, &self.app_view.queue, touch.position);
-            }
-            TouchPhase::Ended | TouchPhase::Cancelled => {
-                self.player.touch_end(&self.app_view.device, &self.app_view.queue);
-            }
-        }
+        self.pointer_event(0, touch.phase, touch.position);
     }
 
     fn resize_surface(&mut self) {
+        if self.recorder.is_some() {
+            let count = self.stop_recording();
+            crate::console_log!("frame recording stopped by resize after {} frames", count);
+        }
         self.app_view.resize_surface();
-        self.canvas_size = (&self.app_view.config).into();
-        self.canvas_buf = crate::util::BufferObj::create_empty_storage_buffer(
-            &self.app_view.device,
-            (self.canvas_size.width * self.canvas_size.height * 12) as u64,
-            false,
-            Some("canvas_buf"),
-        );
+        let canvas_size: Size<u32> = (&self.app_view.config).into();
+        if canvas_size.width != self.canvas_size.width
+            || canvas_size.height != self.canvas_size.height
+        {
+            self.canvas_size = canvas_size;
+            let canvas_buf = Self::create_canvas_buf(&self.app_view, canvas_size);
+            std::mem::replace(&mut self.canvas_buf, canvas_buf).recycle();
+        }
         self.recreate_player();
     }
 
     fn enter_frame(&mut self) {
+        let changed = crate::util::changed_shaders();
+        if !changed.is_empty() {
+            // 缓存中其它场景用到的旧 shader 也要作废
+            resource_pool::evict_shaders(&changed);
+            self.player.reload_shaders(&self.app_view.device, &changed);
+        }
+        let is_rewound = match self.input_replayer.as_mut() {
+            Some(replayer) if replayer.is_finished() && replayer.looping => {
+                replayer.rewind();
+                true
+            }
+            _ => false,
+        };
+        if is_rewound {
+            // 每一轮都从同样的初始状态开始
+            self.recreate_player();
+        }
+        if let Some(replayer) = self.input_replayer.as_mut() {
+            for event in replayer.take_due() {
+                self.apply_input(event);
+            }
+        }
+        let clock = &mut self.clock;
+        let mut steps = match self.recorder.as_mut() {
+            Some(recorder) if !clock.is_paused() => {
+                recorder.next_sim_steps(SIM_STEPS_PER_SECOND, clock.time_scale())
+            }
+            _ => clock.tick(),
+        };
+        if let Some(replayer) = self.input_replayer.as_mut() {
+            steps = replayer.clamp_steps(steps);
+            replayer.advance(steps);
+            if replayer.is_finished() && !replayer.looping {
+                self.input_replayer = None;
+            }
+        }
+        if let Some(recorder) = self.input_recorder.as_mut() {
+            recorder.advance(steps);
+        }
+        self.profiler.begin_frame();
         let (frame, frame_view) = self.app_view.get_current_frame_view();
         self.player.enter_frame(
             &self.app_view.device,
             &self.app_view.queue,
             &frame_view,
             &mut self.setting,
+            steps,
+            &mut self.profiler,
         );
+        if self.profiler.is_enabled() {
+            let mut encoder =
+                self.app_view.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("profiler encoder"),
+                });
+            self.profiler.resolve(&mut encoder);
+            if let Some(hud) = self.hud.as_mut() {
+                hud.update(&self.app_view.queue, &self.profiler);
+                hud.draw(&mut encoder, &frame_view);
+            }
+            self.app_view.queue.submit(Some(encoder.finish()));
+            self.profiler.end_frame(&self.app_view.device);
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) =
+                recorder.capture(&self.app_view.device, &self.app_view.queue, &frame.texture)
+            {
+                crate::console_log!("frame recording stopped: {}", e);
+                self.stop_recording();
+            }
+        }
         frame.present();
     }
 }
@@ -0,0 +1,159 @@
+use crate::fluid::LatticeEdit;
+use crate::{InputEvent, ParticleColorType};
+
+// 最多保留的撤销步数
+const MAX_HISTORY: usize = 100;
+
+// 可撤销的参数
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum SettingValue {
+    FluidViscosity(f32),
+    ParticlesCount(i32),
+    ParticleColor(ParticleColorType),
+    ParticlePointSize(i32),
+}
+
+impl SettingValue {
+    pub fn to_input(self) -> InputEvent {
+        match self {
+            SettingValue::FluidViscosity(nu) => InputEvent::FluidViscosity(nu),
+            SettingValue::ParticlesCount(count) => InputEvent::ParticlesCount(count),
+            SettingValue::ParticleColor(ty) => InputEvent::ParticleColor(ty),
+            SettingValue::ParticlePointSize(size) => InputEvent::ParticlePointSize(size),
+        }
+    }
+}
+
+// 一次可撤销的编辑
+pub(crate) enum EditCommand {
+    // 障碍物、墙、入口、出口与擦除改变的格子，以及外力笔画的印记
+    Lattice(LatticeEdit),
+    Setting { before: SettingValue, after: SettingValue },
+}
+
+#[derive(Default)]
+pub(crate) struct EditHistory {
+    undo_stack: Vec<EditCommand>,
+    redo_stack: Vec<EditCommand>,
+}
+
+impl EditHistory {
+    // 新的编辑会清空重做记录
+    pub fn record(&mut self, command: EditCommand) {
+        self.redo_stack.clear();
+        self.push_undo(command);
+    }
+
+    pub fn pop_undo(&mut self) -> Option<EditCommand> {
+        self.undo_stack.pop()
+    }
+
+    pub fn pop_redo(&mut self) -> Option<EditCommand> {
+        self.redo_stack.pop()
+    }
+
+    // 撤销后的命令放入重做记录
+    pub fn push_redo(&mut self, command: EditCommand) {
+        self.redo_stack.push(command);
+    }
+
+    // 重做后的命令放回撤销记录
+    pub fn push_undo(&mut self, command: EditCommand) {
+        if self.undo_stack.len() == MAX_HISTORY {
+            self.undo_stack.remove(0);
+        }
+        self.undo_stack.push(command);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viscosity_change(before: f32, after: f32) -> EditCommand {
+        EditCommand::Setting {
+            before: SettingValue::FluidViscosity(before),
+            after: SettingValue::FluidViscosity(after),
+        }
+    }
+
+    fn after_viscosity(command: &EditCommand) -> f32 {
+        match command {
+            EditCommand::Setting { after: SettingValue::FluidViscosity(nu), .. } => *nu,
+            _ => panic!("unexpected command"),
+        }
+    }
+
+    #[test]
+    fn undo_then_redo() {
+        let mut history = EditHistory::default();
+        assert!(!history.can_undo() && !history.can_redo());
+        history.record(viscosity_change(0.02, 0.05));
+        history.record(viscosity_change(0.05, 0.1));
+
+        let command = history.pop_undo().unwrap();
+        assert_eq!(after_viscosity(&command), 0.1);
+        history.push_redo(command);
+        assert!(history.can_undo() && history.can_redo());
+
+        let command = history.pop_redo().unwrap();
+        assert_eq!(after_viscosity(&command), 0.1);
+        history.push_undo(command);
+        assert!(!history.can_redo());
+        assert_eq!(after_viscosity(&history.pop_undo().unwrap()), 0.1);
+        assert_eq!(after_viscosity(&history.pop_undo().unwrap()), 0.05);
+        assert!(history.pop_undo().is_none());
+    }
+
+    #[test]
+    fn record_clears_redo() {
+        let mut history = EditHistory::default();
+        history.record(viscosity_change(0.02, 0.05));
+        let command = history.pop_undo().unwrap();
+        history.push_redo(command);
+        history.record(viscosity_change(0.02, 0.2));
+        assert!(!history.can_redo());
+        assert_eq!(after_viscosity(&history.pop_undo().unwrap()), 0.2);
+    }
+
+    #[test]
+    fn drops_oldest_beyond_max_history() {
+        let mut history = EditHistory::default();
+        for i in 0..MAX_HISTORY + 5 {
+            history.record(viscosity_change(i as f32, i as f32 + 1.0));
+        }
+        let mut count = 0;
+        let mut oldest = 0.0;
+        while let Some(command) = history.pop_undo() {
+            oldest = after_viscosity(&command);
+            count += 1;
+        }
+        assert_eq!(count, MAX_HISTORY);
+        // 最早的 5 次编辑被丢弃
+        assert_eq!(oldest, 6.0);
+    }
+
+    #[test]
+    fn clear_drops_both_stacks() {
+        let mut history = EditHistory::default();
+        history.record(viscosity_change(0.02, 0.05));
+        history.record(viscosity_change(0.05, 0.1));
+        let command = history.pop_undo().unwrap();
+        history.push_redo(command);
+        history.clear();
+        assert!(!history.can_undo() && !history.can_redo());
+    }
+}
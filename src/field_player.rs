@@ -1,26 +1,35 @@
-use crate::util::node::{BufferlessFullscreenNode, ComputeNode};
-use crate::util::BufferObj;
+use crate::util::node::{Bindings, BufferlessFullscreenNode, ComputeNode};
+use crate::util::render_graph::{RenderGraph, FRAME};
+use crate::util::shader::{reload_shader, reload_shader_variant};
+use crate::util::{BufferObj, GpuProfiler, ShaderReflection, UniformBuffer};
 use crate::{setting_obj::SettingObj, FieldUniform, Player};
 use app_surface::math::Size;
+use std::rc::Rc;
 use wgpu::{CommandEncoderDescriptor, Device, Queue};
 
 use crate::{create_shader_module, insert_code_then_create};
 
 pub struct FieldPlayer {
     canvas_size: Size<u32>,
-    field_uniform_data: FieldUniform,
-    field_uniform: BufferObj,
+    field_uniform: UniformBuffer<FieldUniform>,
     field_buf: BufferObj,
-    trajectory_update_shader: wgpu::ShaderModule,
+    // field_setting 中插入的速度场代码，热重载时使用
+    code_segment: &'static str,
+    trajectory_update_shader: Rc<wgpu::ShaderModule>,
     field_setting_node: ComputeNode,
     particles_update_node: ComputeNode,
     render_node: BufferlessFullscreenNode,
+    graph: RenderGraph<FieldPlayer>,
     frame_num: usize,
+    // 不足一个参考帧的模拟步数
+    pending_steps: u32,
+    // 本帧需要推进的参考帧数
+    frames: u32,
 }
 
 impl FieldPlayer {
     pub fn new(
-        device: &wgpu::Device, _queue: &wgpu::Queue, canvas_format: wgpu::TextureFormat,
+        device: &wgpu::Device, queue: &wgpu::Queue, canvas_format: wgpu::TextureFormat,
         canvas_size: Size<u32>, canvas_buf: &BufferObj, setting: &SettingObj,
     ) -> Self {
         let pixel_distance = 4;
@@ -41,8 +50,7 @@ impl FieldPlayer {
             speed_ty: 0,
             _padding: [0.0; 3],
         };
-        let field_uniform =
-            BufferObj::create_uniform_buffer(device, &field_uniform_data, Some("field_uniform"));
+        let field_uniform = UniformBuffer::new(device, field_uniform_data, Some("field_uniform"));
         let field_buf = BufferObj::create_empty_storage_buffer(
             device,
             (field_size.width * field_size.height * 16) as u64,
@@ -55,47 +63,76 @@ impl FieldPlayer {
         let setting_shader =
             insert_code_then_create(device, "field_setting", Some(code_segment), None);
 
-        let field_setting_node = ComputeNode::new(
+        let field_setting_node = ComputeNode::with_bindings(
             device,
             field_threadgroup,
-            vec![&field_uniform],
-            vec![&field_buf],
-            vec![],
+            &ShaderReflection::new("field_setting", Some(code_segment), &[]),
+            Bindings::new().uniform("field", &field_uniform).buffer("fb", &field_buf),
             &setting_shader,
         );
 
+        let particles_uniform = setting.particles_uniform.as_ref().unwrap();
         let trajectory_update_shader = create_shader_module(device, "trajectory_update", None);
-        let particles_update_node = ComputeNode::new(
+        let particles_update_node = ComputeNode::with_bindings(
             device,
             setting.particles_threadgroup,
-            vec![&field_uniform, &setting.particles_uniform.as_ref().unwrap()],
-            vec![&field_buf, &setting.particles_buf.as_ref().unwrap(), canvas_buf],
-            vec![],
+            &ShaderReflection::new("trajectory_update", None, &[]),
+            Bindings::new()
+                .uniform("field", &field_uniform)
+                .uniform("particle_uniform", particles_uniform)
+                .buffer("fb", &field_buf)
+                .buffer("pb", setting.particles_buf.as_ref().unwrap())
+                .buffer("canvas", canvas_buf),
             &trajectory_update_shader,
         );
 
         let render_shader = create_shader_module(device, "present", None);
-        let render_node = BufferlessFullscreenNode::new(
+        let render_node = BufferlessFullscreenNode::with_bindings(
             device,
             canvas_format,
-            vec![&field_uniform, &setting.particles_uniform.as_ref().unwrap()],
-            vec![canvas_buf],
-            vec![],
-            vec![],
+            &ShaderReflection::new("present", None, &[]),
+            Bindings::new()
+                .uniform("field", &field_uniform)
+                .uniform("particle_uniform", particles_uniform)
+                .buffer("canvas", canvas_buf),
             &render_shader,
             None,
             false,
         );
+        let mut graph = RenderGraph::new();
+        graph.import(&["field", "particles", "canvas"]);
+        // 粒子按参考帧推进
+        graph
+            .add_compute_pass("particles", &["field"], &["particles", "canvas"], |player, cpass| {
+                for _ in 0..player.frames {
+                    player.particles_update_node.dispatch(cpass);
+                }
+            })
+            .run_if(|player| player.frames > 0);
+        graph.add_render_pass(
+            "present",
+            &["canvas"],
+            FRAME,
+            wgpu::LoadOp::Clear(wgpu::Color { r: 0.1, g: 0.15, b: 0.17, a: 1.0 }),
+            |player, rpass| player.render_node.draw_rpass(rpass),
+        );
+        if let Err(e) = graph.compile(device, queue, (canvas_size.width, canvas_size.height)) {
+            panic!("field render graph: {}", e);
+        }
+
         let instance = FieldPlayer {
             canvas_size,
-            field_uniform_data,
             field_uniform,
             field_buf,
+            code_segment,
             trajectory_update_shader,
             field_setting_node,
             particles_update_node,
             render_node,
+            graph,
             frame_num: 0,
+            pending_steps: 0,
+            frames: 0,
         };
         instance
     }
@@ -106,6 +143,30 @@ impl FieldPlayer {
 }
 
 impl Player for FieldPlayer {
+    fn recycle_resources(&mut self) {
+        self.graph.recycle();
+    }
+
+    // 暂停时丢弃不足一帧的步数，单步从参考帧的起点开始计数
+    fn pause(&mut self) {
+        self.pending_steps = 0;
+    }
+
+    fn reload_shaders(&mut self, device: &Device, changed: &[String]) {
+        if let Some(shader) =
+            reload_shader_variant(device, changed, "field_setting", Some(self.code_segment), &[])
+        {
+            self.field_setting_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "trajectory_update") {
+            self.particles_update_node.reload(device, &shader);
+            self.trajectory_update_shader = shader;
+        }
+        if let Some(shader) = reload_shader(device, changed, "present") {
+            self.render_node.reload(device, &shader);
+        }
+    }
+
     fn reset(&mut self, device: &Device, queue: &Queue) {
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
             label: Some("update_field encoder"),
@@ -116,21 +177,17 @@ impl Player for FieldPlayer {
 
     fn enter_frame(
         &mut self, device: &Device, queue: &Queue, frame_view: &wgpu::TextureView,
-        _setting: &mut crate::SettingObj,
+        _setting: &mut crate::SettingObj, steps: u32, profiler: &mut GpuProfiler,
     ) {
         //  On latast wgpu(2021/06/05), must reset twice to get correct result
         if self.frame_num <= 1 {
             self.reset(device, queue);
         }
+        self.frames = crate::sim_clock::steps_to_frames(&mut self.pending_steps, steps);
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
             label: Some("field player encoder"),
         });
-        self.particles_update_node.compute(&mut encoder);
-        self.render_node.draw(
-            frame_view,
-            &mut encoder,
-            wgpu::LoadOp::Clear(wgpu::Color { r: 0.1, g: 0.15, b: 0.17, a: 1.0 }),
-        );
+        self.graph.execute(&mut encoder, self, frame_view, profiler);
         queue.submit(Some(encoder.finish()));
         self.frame_num += 1;
     }
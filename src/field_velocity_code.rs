@@ -35,6 +35,11 @@ pub fn get_velocity_code_segment(ty: FieldAnimationType) -> &'static str {
     return v + c * 0.2;
     "#
         }
-        _ => "",
+        // 流体等类型不使用 field_setting，返回静止的场，保证每种类型生成的 shader 都合法
+        _ => {
+            r#"
+    return vec2<f32>(0.0);
+    "#
+        }
     }
 }
@@ -1,6 +1,13 @@
-use super::{init_lattice_material, is_sd_sphere, LatticeInfo, LatticeType, OBSTACLE_RADIUS};
-use crate::util::{node::ComputeNode, AnyTexture, BufferObj};
+use super::{
+    init_lattice_material, lbm_fluid_ty, BrushCommand, BrushUniform, LatticeEdit, LatticeInfo,
+    LatticeType, OBSTACLE_RADIUS,
+};
+use crate::util::node::{Bindings, ComputeNode};
+use crate::util::resource_pool::{PooledBuffer, PooledTexture};
+use crate::util::shader::reload_shader;
+use crate::util::{BufferObj, ShaderReflection, UniformBuffer};
 use app_surface::math::{Position, Size};
+use std::collections::HashMap;
 
 use crate::{
     create_shader_module, fluid::LbmUniform, setting_obj::SettingObj, FieldAnimationType,
@@ -13,16 +20,36 @@ pub struct AAD2Q9Node {
     pub lattice: wgpu::Extent3d,
     pub lattice_pixel_size: u32,
     animation_ty: FieldAnimationType,
-    pub lbm_uniform_buf: BufferObj,
-    pub fluid_uniform_buf: BufferObj,
-    pub macro_tex: AnyTexture,
+    pub lbm_uniform_buf: UniformBuffer<LbmUniform>,
+    pub fluid_uniform_buf: UniformBuffer<FieldUniform>,
+    // 按格子数分配的资源，重建 player 时回收到 resource_pool
+    pub macro_tex: PooledTexture,
     pub lattice_info_data: Vec<LatticeInfo>,
-    pub info_buf: BufferObj,
+    pub info_buf: PooledBuffer,
+    lattice_buf: PooledBuffer,
     collide_stream_node: ComputeNode,
     pub dispatch_group_count: (u32, u32, u32),
     pub reset_node: ComputeNode,
+    // 一帧内的外力笔画与障碍物先缓存，每帧上传一次，由 brush_node 写入 info_buf
+    brush_commands: Vec<BrushCommand>,
+    brush_uniform_buf: BufferObj,
+    brush_buf: BufferObj,
+    brush_node: ComputeNode,
+    // 外力笔刷的半径（格子数）与边缘衰减，半径 0.5 时只写入线段经过的一个格子宽
+    pub brush_radius: f32,
+    pub brush_falloff: f32,
+    // 当前这一笔改变的格子与写入的外力印记，用于撤销/重做
+    edit_cells: HashMap<usize, (LatticeInfo, LatticeInfo)>,
+    edit_forces: Vec<BrushCommand>,
+    // 撤销/重做后把 CPU 端的材质整体上传，由 restore_node 对比写入 info_buf
+    restore_buf: PooledBuffer,
+    restore_node: ComputeNode,
+    is_restore_pending: bool,
 }
 
+// 单帧最多的笔刷命令数
+const MAX_BRUSH_COMMANDS: usize = 128;
+
 impl AAD2Q9Node {
     pub fn new(
         app_view: &app_surface::AppSurface, canvas_size: Size<u32>, setting: &SettingObj,
@@ -46,7 +73,7 @@ impl AAD2Q9Node {
         let tau = 3.0 * setting.fluid_viscosity + 0.5;
         // let tau = 3.0 * viscocity + 0.5;
 
-        let fluid_ty = if setting.animation_type == FieldAnimationType::Poiseuille { 0 } else { 1 };
+        let fluid_ty = lbm_fluid_ty(setting.animation_type);
         let lbm_uniform_data =
             LbmUniform::new(tau, fluid_ty, (lattice.width * lattice.height) as i32);
 
@@ -62,13 +89,9 @@ impl AAD2Q9Node {
             speed_ty: 1,
             _padding: [0.0; 3],
         };
-        let lbm_uniform_buf =
-            BufferObj::create_uniform_buffer(device, &lbm_uniform_data, Some("uniform_buf0"));
-        let fluid_uniform_buf = BufferObj::create_uniform_buffer(
-            device,
-            &field_uniform_data,
-            Some("fluid_uniform_buf"),
-        );
+        let lbm_uniform_buf = UniformBuffer::new(device, lbm_uniform_data, Some("uniform_buf0"));
+        let fluid_uniform_buf =
+            UniformBuffer::new(device, field_uniform_data, Some("fluid_uniform_buf"));
         let scalar_lattice_size = (lattice.width * lattice.height * 4) as wgpu::BufferAddress;
         // let macro_buf = BufferObj::create_empty_storage_buffer(
         //     device,
@@ -78,27 +101,27 @@ impl AAD2Q9Node {
         // );
         let macro_tex_format = TextureFormat::Rgba16Float;
         let macro_tex_access = wgpu::StorageTextureAccess::WriteOnly;
-        let macro_tex = crate::util::load_texture::empty(
+        let macro_tex = PooledTexture::new(
             device,
+            queue,
             macro_tex_format,
             wgpu::Extent3d {
                 width: lattice.width,
                 height: lattice.height,
                 depth_or_array_layers: 1,
             },
-            None,
-            Some(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING),
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
             Some("macro_tex"),
         );
 
         let lattice_info_data = init_lattice_material(lattice, setting.animation_type);
         let info_buf =
-            BufferObj::create_storage_buffer(device, &lattice_info_data, Some("info_buffer"));
+            PooledBuffer::storage(device, queue, &lattice_info_data, Some("info_buffer"));
 
-        let aa_buffer = BufferObj::create_empty_storage_buffer(
+        let lattice_buf = PooledBuffer::empty_storage(
             device,
+            queue,
             scalar_lattice_size * 9,
-            false,
             Some("lattice_buf"),
         );
         let collide_stream_shader =
@@ -143,10 +166,10 @@ impl AAD2Q9Node {
         let collide_stream_node = ComputeNode::new_with_dynamic_uniforms(
             device,
             dispatch_group_count,
-            vec![&lbm_uniform_buf, &fluid_uniform_buf],
+            vec![&lbm_uniform_buf.buffer, &fluid_uniform_buf.buffer],
             vec![&dynamic_buf],
-            vec![&aa_buffer, &info_buf],
-            vec![(&macro_tex, Some(macro_tex_access))],
+            vec![&*lattice_buf, &*info_buf],
+            vec![(&*macro_tex, Some(macro_tex_access))],
             &collide_stream_shader,
         );
 
@@ -154,12 +177,55 @@ impl AAD2Q9Node {
         let reset_node = ComputeNode::new(
             device,
             dispatch_group_count,
-            vec![&lbm_uniform_buf, &fluid_uniform_buf],
-            vec![&aa_buffer, &info_buf],
-            vec![(&macro_tex, Some(macro_tex_access))],
+            vec![&lbm_uniform_buf.buffer, &fluid_uniform_buf.buffer],
+            vec![&*lattice_buf, &*info_buf],
+            vec![(&*macro_tex, Some(macro_tex_access))],
             &init_shader,
         );
 
+        let brush_uniform_buf = BufferObj::create_uniform_buffer(
+            device,
+            &BrushUniform { count: 0, _pading0: 0, _pading1: 0, _pading2: 0 },
+            Some("brush_uniform_buf"),
+        );
+        let brush_buf = BufferObj::create_empty_storage_buffer(
+            device,
+            (MAX_BRUSH_COMMANDS * std::mem::size_of::<BrushCommand>()) as wgpu::BufferAddress,
+            false,
+            Some("brush_buf"),
+        );
+        let brush_shader = create_shader_module(device, "aa_lbm/aa_brush", Some("brush_shader"));
+        let brush_node = ComputeNode::with_bindings(
+            device,
+            dispatch_group_count,
+            &ShaderReflection::new("aa_lbm/aa_brush", None, &[]),
+            Bindings::new()
+                .uniform("fluid", &lbm_uniform_buf)
+                .uniform("field", &fluid_uniform_buf)
+                .buffer("brush", &brush_uniform_buf)
+                .buffer("commands", &brush_buf)
+                .buffer("lattice_info", &info_buf)
+                .buffer("aa_cell", &lattice_buf),
+            &brush_shader,
+        );
+
+        let restore_buf =
+            PooledBuffer::storage(device, queue, &lattice_info_data, Some("restore_buf"));
+        let restore_shader =
+            create_shader_module(device, "aa_lbm/aa_restore", Some("restore_shader"));
+        let restore_node = ComputeNode::with_bindings(
+            device,
+            dispatch_group_count,
+            &ShaderReflection::new("aa_lbm/aa_restore", None, &[]),
+            Bindings::new()
+                .uniform("fluid", &lbm_uniform_buf)
+                .uniform("field", &fluid_uniform_buf)
+                .buffer("target_info", &restore_buf)
+                .buffer("lattice_info", &info_buf)
+                .buffer("aa_cell", &lattice_buf),
+            &restore_shader,
+        );
+
         let mut instance = AAD2Q9Node {
             lattice,
             lattice_pixel_size,
@@ -169,9 +235,21 @@ impl AAD2Q9Node {
             macro_tex,
             lattice_info_data,
             info_buf,
+            lattice_buf,
             dispatch_group_count,
             collide_stream_node,
             reset_node,
+            brush_commands: vec![],
+            brush_uniform_buf,
+            brush_buf,
+            brush_node,
+            brush_radius: 0.5,
+            brush_falloff: 0.0,
+            edit_cells: HashMap::new(),
+            edit_forces: vec![],
+            restore_buf,
+            restore_node,
+            is_restore_pending: false,
         };
         // On latast wgpu(2021/06/05), must reset twice to get correct result
         // But, cannot use official demo reproduce this problem, so strange!!
@@ -180,47 +258,107 @@ impl AAD2Q9Node {
         return instance;
     }
 
+    // shader 热重载：重建用到了 changed 中 shader 的节点
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "aa_lbm/aa_collide_stream") {
+            self.collide_stream_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "aa_lbm/aa_init") {
+            self.reset_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "aa_lbm/aa_brush") {
+            self.brush_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "aa_lbm/aa_restore") {
+            self.restore_node.reload(device, &shader);
+        }
+    }
+
+    // player 被替换之前调用，之后不能再使用此节点
+    pub fn recycle_resources(&mut self) {
+        self.macro_tex.recycle();
+        self.info_buf.recycle();
+        self.lattice_buf.recycle();
+        self.restore_buf.recycle();
+    }
+
     pub fn reset(&mut self, encoder: &mut wgpu::CommandEncoder) {
         self.reset_node.compute(encoder);
     }
 
-    pub fn add_obstacle(&mut self, queue: &wgpu::Queue, x: u32, y: u32) {
-        let obstacle = LatticeInfo {
-            material: LatticeType::Obstacle as i32,
+    // x, y 为格子坐标
+    pub fn add_obstacle(&mut self, x: u32, y: u32) {
+        let center = [x as f32 + 0.5, y as f32 + 0.5];
+        self.paint(center, center, OBSTACLE_RADIUS, LatticeType::Obstacle, [0.0; 2]);
+    }
+
+    // 沿线段绘制材质：障碍物、墙、入口、出口，或者用 LatticeType::Bulk 擦除为流体
+    // p0, p1 为像素坐标，radius 为格子数，velocity 为入口的速度
+    pub fn paint_material(
+        &mut self, p0: Position, p1: Position, radius: f32, material: LatticeType,
+        velocity: [f32; 2],
+    ) {
+        let scale = 1.0 / self.lattice_pixel_size as f32;
+        self.paint(
+            [p0.x * scale, p0.y * scale],
+            [p1.x * scale, p1.y * scale],
+            radius,
+            material,
+            velocity,
+        );
+    }
+
+    fn paint(
+        &mut self, p0: [f32; 2], p1: [f32; 2], radius: f32, material: LatticeType,
+        velocity: [f32; 2],
+    ) {
+        let command = BrushCommand {
+            p0,
+            p1,
+            force: velocity,
+            radius,
+            falloff: 0.0,
+            material: material as i32,
             block_iter: -1,
-            vx: 0.0,
-            vy: 0.0,
+            _pading0: 0,
+            _pading1: 0,
         };
-        let center = Position::new(x as f32 + 0.5, y as f32 + 0.5);
-        let mut info: Vec<LatticeInfo> = vec![];
-
-        let min_y = y - OBSTACLE_RADIUS as u32;
-        let max_y = min_y + OBSTACLE_RADIUS as u32 * 2;
-        for y in min_y..max_y {
-            for x in 0..self.lattice.width {
-                let index = (self.lattice.width * y) + x;
-                if is_sd_sphere(
-                    &Position::new(x as f32 + 0.5, y as f32 + 0.5).minus(&center),
-                    OBSTACLE_RADIUS,
-                ) {
-                    self.lattice_info_data[index as usize] = obstacle;
-                    info.push(obstacle);
-                } else {
-                    let origin_info = self.lattice_info_data[index as usize];
-                    info.push(origin_info);
+        // 同步 CPU 端的材质数据，与 aa_brush.wgsl 的规则一致
+        let (w, h) = (self.lattice.width as i32, self.lattice.height as i32);
+        let min_x = ((p0[0].min(p1[0]) - radius).floor() as i32).max(1);
+        let max_x = ((p0[0].max(p1[0]) + radius).ceil() as i32).min(w - 3);
+        let min_y = ((p0[1].min(p1[1]) - radius).floor() as i32).max(1);
+        let max_y = ((p0[1].max(p1[1]) + radius).ceil() as i32).min(h - 3);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let center = [x as f32 + 0.5, y as f32 + 0.5];
+                let index = (y * w + x) as usize;
+                if distance_to_segment(center, p0, p1) <= radius
+                    && self.lattice_info_data[index].material != LatticeType::Ghost as i32
+                {
+                    let before = self.lattice_info_data[index];
+                    let after = LatticeInfo {
+                        material: command.material,
+                        block_iter: command.block_iter,
+                        vx: velocity[0],
+                        vy: velocity[1],
+                    };
+                    self.edit_cells.entry(index).or_insert((before, after)).1 = after;
+                    self.lattice_info_data[index] = after;
                 }
             }
         }
-
-        let offset = (self.lattice.width * min_y) as u64 * 16;
-        queue.write_buffer(&self.info_buf.buffer, offset, info.as_bytes());
+        self.push_brush(command);
     }
 
     pub fn reset_lattice_info(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        if self.animation_ty == FieldAnimationType::Poiseuille {
-            self.lattice_info_data = init_lattice_material(self.lattice, self.animation_ty);
-            queue.write_buffer(&self.info_buf.buffer, 0, self.lattice_info_data.as_bytes());
-        }
+        self.brush_commands.clear();
+        self.edit_cells.clear();
+        self.edit_forces.clear();
+        self.is_restore_pending = false;
+        // 所有场景都恢复为初始的材质，清除绘制的障碍物与墙
+        self.lattice_info_data = init_lattice_material(self.lattice, self.animation_ty);
+        queue.write_buffer(&self.info_buf.buffer, 0, self.lattice_info_data.as_bytes());
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("fluid reset encoder"),
         });
@@ -228,36 +366,114 @@ impl AAD2Q9Node {
         queue.submit(Some(encoder.finish()));
     }
 
-    pub fn add_external_force(&mut self, queue: &wgpu::Queue, pos: Position, pre_pos: Position) {
+    // 取出自上次调用以来的材质修改，没有实际改变时返回 None
+    pub fn take_edit(&mut self) -> Option<LatticeEdit> {
+        let mut cells: Vec<(usize, LatticeInfo, LatticeInfo)> = self
+            .edit_cells
+            .drain()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(index, (before, after))| (index, before, after))
+            .collect();
+        let forces = std::mem::take(&mut self.edit_forces);
+        if cells.is_empty() && forces.is_empty() {
+            return None;
+        }
+        cells.sort_by_key(|cell| cell.0);
+        Some(LatticeEdit { cells, forces })
+    }
+
+    // 撤销时恢复修改前的材质，重做时恢复修改后的材质
+    // 外力注入的动量已经随流体扩散，无法精确收回：撤销时沿原笔画反向施加同样的外力，重做时再次施加
+    pub fn apply_edit(&mut self, edit: &LatticeEdit, is_undo: bool) {
+        for (index, before, after) in edit.cells.iter() {
+            self.lattice_info_data[*index] = if is_undo { *before } else { *after };
+        }
+        if !edit.cells.is_empty() {
+            self.is_restore_pending = true;
+        }
+        for command in edit.forces.iter() {
+            let mut command = *command;
+            if is_undo {
+                command.force = [-command.force[0], -command.force[1]];
+            }
+            self.push_brush(command);
+        }
+    }
+
+    // 上传撤销/重做后的材质，返回是否需要执行 dispatch_restore
+    pub fn upload_restore(&mut self, queue: &wgpu::Queue) -> bool {
+        if !self.is_restore_pending {
+            return false;
+        }
+        queue.write_buffer(&self.restore_buf.buffer, 0, self.lattice_info_data.as_bytes());
+        self.is_restore_pending = false;
+        true
+    }
+
+    pub fn dispatch_restore<'c, 'b: 'c>(&'b self, cpass: &mut wgpu::ComputePass<'c>) {
+        self.restore_node.dispatch(cpass);
+    }
+
+    // pos, pre_pos 为像素坐标
+    pub fn add_external_force(&mut self, pos: Position, pre_pos: Position) {
         let dis = pos.distance(&pre_pos);
         let mut force = 0.1 * (dis / 20.0);
         if force > 0.12 {
             force = 0.12;
         }
         let ridian = pos.slope_ridian(&pre_pos);
-        let vx: f32 = force * ridian.cos();
-        let vy = force * ridian.sin();
-        let info: Vec<LatticeInfo> = vec![LatticeInfo {
+        let scale = 1.0 / self.lattice_pixel_size as f32;
+        let command = BrushCommand {
+            p0: [pre_pos.x * scale, pre_pos.y * scale],
+            p1: [pos.x * scale, pos.y * scale],
+            force: [force * ridian.cos(), force * ridian.sin()],
+            radius: self.brush_radius,
+            falloff: self.brush_falloff,
             material: LatticeType::ExternalForce as i32,
             block_iter: 30,
-            vx,
-            vy,
-        }];
-        let c = (dis / (self.lattice_pixel_size - 1) as f32).ceil();
-        let step = dis / c;
-        for i in 0..c as i32 {
-            let p = pre_pos.new_by_slope_n_dis(ridian, step * i as f32).round();
-            let x = p.x as u32 / self.lattice_pixel_size;
-            let y = p.y as u32 / self.lattice_pixel_size;
-            if x < 1 || x >= self.lattice.width - 2 || y < 1 || y >= self.lattice.height - 2 {
-                continue;
-            }
-            let offset = (self.lattice.width * y + x) as u64 * 16;
-            queue.write_buffer(&self.info_buf.buffer, offset, info.as_bytes());
+            _pading0: 0,
+            _pading1: 0,
+        };
+        self.edit_forces.push(command);
+        self.push_brush(command);
+    }
+
+    fn push_brush(&mut self, command: BrushCommand) {
+        self.brush_commands.push(command);
+    }
+
+    // 上传本帧缓存的笔刷命令，返回是否需要执行 dispatch_brush
+    // 一次最多上传 MAX_BRUSH_COMMANDS 个，其余的按顺序留到下一帧，不丢弃，CPU 端的材质已经同步过了
+    pub fn upload_brush(&mut self, queue: &wgpu::Queue) -> bool {
+        if self.brush_commands.is_empty() {
+            return false;
         }
+        let count = self.brush_commands.len().min(MAX_BRUSH_COMMANDS);
+        let uniform = BrushUniform { count: count as i32, _pading0: 0, _pading1: 0, _pading2: 0 };
+        queue.write_buffer(&self.brush_uniform_buf.buffer, 0, uniform.as_bytes());
+        let commands: Vec<BrushCommand> = self.brush_commands.drain(..count).collect();
+        queue.write_buffer(&self.brush_buf.buffer, 0, commands.as_bytes());
+        true
+    }
+
+    pub fn dispatch_brush<'c, 'b: 'c>(&'b self, cpass: &mut wgpu::ComputePass<'c>) {
+        self.brush_node.dispatch(cpass);
     }
 
     pub fn dispatch<'c, 'b: 'c>(&'b self, cpass: &mut wgpu::ComputePass<'c>, _swap_index: usize) {
         self.collide_stream_node.dispatch_by_offsets(cpass, Some(vec![vec![0], vec![256]]));
     }
 }
+
+fn distance_to_segment(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let ap = [p[0] - a[0], p[1] - a[1]];
+    let len_sq = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if len_sq > 0.0 {
+        ((ap[0] * ab[0] + ap[1] * ab[1]) / len_sq).max(0.0).min(1.0)
+    } else {
+        0.0
+    };
+    let d = [ap[0] - ab[0] * t, ap[1] - ab[1] * t];
+    (d[0] * d[0] + d[1] * d[1]).sqrt()
+}
@@ -3,7 +3,7 @@ use std::{borrow::BorrowMut, u32};
 use super::{init_lattice_material, is_sd_sphere, LatticeInfo, LatticeType, OBSTACLE_RADIUS};
 use crate::util::{
     node::{BindingGroupSetting, ComputeNode},
-    AnyTexture, BufferObj,
+    AnyTexture, BufferObj, UniformBuffer,
 };
 use app_surface::math::{Position, Size};
 
@@ -18,8 +18,8 @@ pub struct D2Q9Node {
     pub lattice: wgpu::Extent3d,
     pub lattice_pixel_size: u32,
     animation_ty: FieldAnimationType,
-    pub lbm_uniform_buf: BufferObj,
-    pub fluid_uniform_buf: BufferObj,
+    pub lbm_uniform_buf: UniformBuffer<LbmUniform>,
+    pub fluid_uniform_buf: UniformBuffer<FieldUniform>,
     pub macro_tex: AnyTexture,
     pub lattice_info_data: Vec<LatticeInfo>,
     pub info_buf: BufferObj,
@@ -70,13 +70,9 @@ impl D2Q9Node {
             speed_ty: 1,
             _padding: [0.0; 3],
         };
-        let lbm_uniform_buf =
-            BufferObj::create_uniform_buffer(device, &lbm_uniform_data, Some("uniform_buf0"));
-        let fluid_uniform_buf = BufferObj::create_uniform_buffer(
-            device,
-            &field_uniform_data,
-            Some("fluid_uniform_buf"),
-        );
+        let lbm_uniform_buf = UniformBuffer::new(device, lbm_uniform_data, Some("uniform_buf0"));
+        let fluid_uniform_buf =
+            UniformBuffer::new(device, field_uniform_data, Some("fluid_uniform_buf"));
         let scalar_lattice_size = (lattice.width * lattice.height * 4) as wgpu::BufferAddress;
         // let macro_buf = BufferObj::create_empty_storage_buffer(
         //     device,
@@ -128,7 +124,7 @@ impl D2Q9Node {
                 vec![&collide_stream_buffers[i], &collide_stream_buffers[(i + 1) % 2], &info_buf];
             let setting_node = BindingGroupSetting::new(
                 device,
-                vec![&lbm_uniform_buf, &fluid_uniform_buf],
+                vec![&lbm_uniform_buf.buffer, &fluid_uniform_buf.buffer],
                 buffers.clone(),
                 vec![(&macro_tex, Some(macro_tex_access))],
                 vec![],
@@ -164,7 +160,7 @@ impl D2Q9Node {
         let reset_node = ComputeNode::new(
             device,
             dispatch_group_count,
-            vec![&lbm_uniform_buf, &fluid_uniform_buf],
+            vec![&lbm_uniform_buf.buffer, &fluid_uniform_buf.buffer],
             vec![&collide_stream_buffers[0], &collide_stream_buffers[1], &info_buf],
             vec![(&macro_tex, Some(macro_tex_access))],
             &init_shader,
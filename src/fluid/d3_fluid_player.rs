@@ -1,21 +1,28 @@
 use super::{D3Q15Node, OBSTACLE_RADIUS};
-use crate::util::BufferObj;
+use crate::gesture::{PinchRotate, PointerTracker};
+use crate::util::{BufferObj, GpuProfiler};
 use crate::{fluid::LbmUniform, setting_obj::SettingObj, FieldAnimationType, Player};
 use app_surface::math::{Position, Size};
+use nalgebra_glm as glm;
 use wgpu::{CommandEncoderDescriptor, Device, Queue};
-use zerocopy::AsBytes;
 // 通用的流體模擬，產生外部依賴的流體量
 pub struct D3FluidPlayer {
     animation_ty: FieldAnimationType,
     canvas_size: Size<u32>,
     lattice: wgpu::Extent3d,
     lattice_pixel_size: u32,
-    pre_pos: Position,
+    pointers: PointerTracker,
+    // 双指手势控制视图的缩放与旋转，单指产生外力
+    pinch: PinchRotate,
+    zoom: f32,
+    roll: f32,
     fluid_compute_node: D3Q15Node,
     // particle_update_node: ComputeNode,
     // render_node: BufferlessFullscreenNode,
     particles_render: super::D3ParticleRenderNode,
     depth_tex: crate::util::AnyTexture,
+    // 不足一个参考帧的模拟步数
+    pending_steps: u32,
 }
 
 impl D3FluidPlayer {
@@ -84,16 +91,31 @@ impl D3FluidPlayer {
             canvas_size,
             lattice,
             lattice_pixel_size: fluid_compute_node.lattice_pixel_size,
-            pre_pos: Position::new(0.0, 0.0),
+            pointers: PointerTracker::default(),
+            pinch: PinchRotate::default(),
+            zoom: 1.0,
+            roll: 0.0,
             fluid_compute_node,
             // particle_update_node,
             // render_node,
             particles_render,
             depth_tex,
+            pending_steps: 0,
         }
     }
 }
 
+impl D3FluidPlayer {
+    // 在屏幕空间里绕视图中心缩放、旋转，按宽高比修正避免旋转时变形
+    fn update_camera(&self, queue: &Queue) {
+        let aspect = self.canvas_size.width as f32 / self.canvas_size.height as f32;
+        let mut matrix = glm::scale(&glm::Mat4::identity(), &glm::vec3(1.0 / aspect, 1.0, 1.0));
+        matrix = glm::rotate_z(&matrix, self.roll);
+        matrix = glm::scale(&matrix, &glm::vec3(aspect * self.zoom, self.zoom, 1.0));
+        self.particles_render.update_camera(queue, matrix);
+    }
+}
+
 impl Player for D3FluidPlayer {
     fn on_click(
         &mut self, _device: &wgpu::Device, queue: &wgpu::Queue, pos: app_surface::math::Position,
@@ -114,48 +136,72 @@ impl Player for D3FluidPlayer {
         self.fluid_compute_node.add_obstacle(queue, x, y);
     }
 
-    fn touch_begin(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) {
-        self.pre_pos = Position::new(0.0, 0.0);
+    fn touch_begin(
+        &mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, id: u64, pos: Position,
+    ) {
+        self.pointers.begin(id, pos);
+        self.pinch.reset();
     }
 
-    fn touch_move(&mut self, _device: &Device, queue: &Queue, pos: app_surface::math::Position) {
+    fn touch_move(&mut self, _device: &Device, queue: &Queue, id: u64, pos: Position) {
         if pos.x <= 0.0 || pos.y <= 0.0 {
-            self.pre_pos = Position::zero();
+            self.pointers.end(id);
             return;
         }
-        let dis = pos.distance(&self.pre_pos);
-        if (self.pre_pos.x == 0.0 && self.pre_pos.y == 0.0) || dis > 300.0 {
-            self.pre_pos = pos;
+        let pre_pos = self.pointers.move_to(id, pos);
+        if self.pointers.len() >= 2 {
+            let (a, b) = self.pointers.first_two().unwrap();
+            if let Some((scale, rotation)) = self.pinch.update(a, b) {
+                self.zoom = (self.zoom * scale).max(0.5).min(4.0);
+                self.roll += rotation;
+                self.update_camera(queue);
+            }
             return;
         }
+        if let Some(pre_pos) = pre_pos {
+            if pos.distance(&pre_pos) <= 300.0 {
+                self.fluid_compute_node.add_external_force(queue, pos, pre_pos);
+            }
+        }
+    }
+
+    fn touch_end(&mut self, _device: &Device, _queue: &Queue, id: u64, _pos: Position) {
+        self.pointers.end(id);
+        self.pinch.reset();
+    }
 
-        self.fluid_compute_node.add_external_force(queue, pos, self.pre_pos);
+    // 暂停时丢弃不足一帧的步数，单步从参考帧的起点开始计数
+    fn pause(&mut self) {
+        self.pending_steps = 0;
+    }
 
-        self.pre_pos = pos;
+    fn reload_shaders(&mut self, device: &Device, changed: &[String]) {
+        self.fluid_compute_node.reload_shaders(device, changed);
+        self.particles_render.reload_shaders(device, changed);
     }
 
     fn update_uniforms(&mut self, queue: &Queue, setting: &crate::SettingObj) {
         // 通过外部参数来重置流体粒子碰撞松解时间 tau = (3.0 * x + 0.5), x：[0~1] 趋大，松解时间趋快
         let tau = 3.0 * setting.fluid_viscosity + 0.5;
         let fluid_ty = if setting.animation_type == FieldAnimationType::Poiseuille { 0 } else { 1 };
-        let uniform_data =
-            LbmUniform::new(tau, fluid_ty, (self.lattice.width * self.lattice.height) as i32);
-        queue.write_buffer(
-            &self.fluid_compute_node.lbm_uniform_buf.buffer,
-            0,
-            uniform_data.as_bytes(),
-        );
+        let soa_offset = (self.lattice.width * self.lattice.height) as i32;
+        self.fluid_compute_node
+            .lbm_uniform_buf
+            .update(queue, |uniform| *uniform = LbmUniform::new(tau, fluid_ty, soa_offset));
     }
 
     fn reset(&mut self, device: &Device, queue: &Queue) {
         self.fluid_compute_node.reset_lattice_info(device, queue);
 
-        self.pre_pos = Position::new(0.0, 0.0);
+        self.pointers = PointerTracker::default();
+        self.zoom = 1.0;
+        self.roll = 0.0;
+        self.update_camera(queue);
     }
 
     fn enter_frame(
         &mut self, device: &Device, queue: &Queue, frame_view: &wgpu::TextureView,
-        _setting: &mut crate::SettingObj,
+        _setting: &mut crate::SettingObj, steps: u32, profiler: &mut GpuProfiler,
     ) {
         // 频繁调用 queue.write_buffer 导致内存线性增涨(2021/06/14)
         // setting.particles_uniform_data.is_only_update_pos = 1;
@@ -181,7 +227,12 @@ impl Player for D3FluidPlayer {
         //     &setting.particles_buf.as_ref().unwrap(),
         //     setting.particles_count,
         // );
-        self.particles_render.update_particles(&mut encoder, frame_view);
+        let scope = profiler.begin_scope(&mut encoder, "particles");
+        for _ in 0..crate::sim_clock::steps_to_frames(&mut self.pending_steps, steps) {
+            self.particles_render.update_particles(&mut encoder, frame_view);
+        }
+        profiler.end_scope(&mut encoder, scope);
+        let scope = profiler.begin_scope(&mut encoder, "present");
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
@@ -204,6 +255,7 @@ impl Player for D3FluidPlayer {
             });
             self.particles_render.draw_rpass(&mut rpass);
         }
+        profiler.end_scope(&mut encoder, scope);
         queue.submit(Some(encoder.finish()));
         // self.fluid_compute_node.reset_lattice_info(device, queue);
     }
@@ -1,5 +1,6 @@
-use crate::util::BufferObj;
+use crate::util::{shader::reload_shader, BufferObj, MVPUniform};
 use app_surface::math::Size;
+use nalgebra_glm as glm;
 use wgpu::util::DeviceExt;
 use zerocopy::AsBytes;
 
@@ -8,11 +9,18 @@ use crate::{create_shader_module, TrajectoryUniform};
 pub struct D3ParticleRenderNode {
     update_bind_group: wgpu::BindGroup,
     update_pipeline: wgpu::RenderPipeline,
+    update_pipeline_layout: wgpu::PipelineLayout,
 
     bind_group: wgpu::BindGroup,
     compose_pipeline: wgpu::RenderPipeline,
+    compose_pipeline_layout: wgpu::PipelineLayout,
+    // 热重载时用来重建 pipeline
+    canvas_format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
     vertices_buf: wgpu::Buffer,
     points_buf: wgpu::Buffer,
+    // 双指缩放、旋转的视图变换
+    camera_buf: BufferObj,
 
     particles_buf: BufferObj,
     frame_index: u32,
@@ -73,13 +81,34 @@ impl D3ParticleRenderNode {
             wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(&sampler) },
         ];
 
+        let camera_buf = BufferObj::create_uniform_buffer(
+            device,
+            &MVPUniform { mvp_matrix: glm::Mat4::identity().into() },
+            Some("particle render camera_buf"),
+        );
+        let mut compose_layouts = layouts.clone();
+        compose_layouts.push(wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(0),
+            },
+            count: None,
+        });
+        let mut compose_entries = entries.clone();
+        compose_entries.push(wgpu::BindGroupEntry {
+            binding: 3,
+            resource: camera_buf.buffer.as_entire_binding(),
+        });
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &layouts,
+            entries: &compose_layouts,
             label: None,
         });
         let bind_group: wgpu::BindGroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &bind_group_layout,
-            entries: &entries,
+            entries: &compose_entries,
             label: None,
         });
 
@@ -118,7 +147,7 @@ impl D3ParticleRenderNode {
             &compose_shader,
             ("vs_compose", "fs_compose"),
             false,
-            Some(depth_tex),
+            Some(depth_tex.format),
         );
 
         let particles_data = crate::init_3d_particles(wgpu::Extent3d {
@@ -181,21 +210,72 @@ impl D3ParticleRenderNode {
             &update_shader,
             ("vs_update", "fs_update"),
             true,
-            Some(depth_tex),
+            Some(depth_tex.format),
         );
 
         D3ParticleRenderNode {
             update_bind_group,
             update_pipeline,
+            update_pipeline_layout,
             bind_group,
             compose_pipeline,
+            compose_pipeline_layout: render_pipeline_layout,
+            canvas_format,
+            depth_format: depth_tex.format,
             vertices_buf,
             points_buf,
+            camera_buf,
             particles_buf,
             frame_index: 0,
         }
     }
 
+    // shader 热重载：出错时保留旧的 pipeline
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "3d_lbm/particles_present") {
+            let pipeline = crate::util::shader::try_create(device, || {
+                generate_pipeline(
+                    device,
+                    vec![Some(self.canvas_format.into())],
+                    &self.compose_pipeline_layout,
+                    &shader,
+                    ("vs_compose", "fs_compose"),
+                    false,
+                    Some(self.depth_format),
+                )
+            });
+            match pipeline {
+                Ok(pipeline) => self.compose_pipeline = pipeline,
+                Err(e) => crate::console_log!("render pipeline reload error: {}", e),
+            }
+        }
+        if let Some(shader) = reload_shader(device, changed, "3d_lbm/particles_update") {
+            let pipeline = crate::util::shader::try_create(device, || {
+                generate_pipeline(
+                    device,
+                    vec![Some(self.canvas_format.into())],
+                    &self.update_pipeline_layout,
+                    &shader,
+                    ("vs_update", "fs_update"),
+                    true,
+                    Some(self.depth_format),
+                )
+            });
+            match pipeline {
+                Ok(pipeline) => self.update_pipeline = pipeline,
+                Err(e) => crate::console_log!("render pipeline reload error: {}", e),
+            }
+        }
+    }
+
+    pub fn update_camera(&self, queue: &wgpu::Queue, matrix: glm::TMat4<f32>) {
+        queue.write_buffer(
+            &self.camera_buf.buffer,
+            0,
+            MVPUniform { mvp_matrix: matrix.into() }.as_bytes(),
+        );
+    }
+
     pub fn update_particles(
         &self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView,
     ) {
@@ -232,7 +312,7 @@ fn generate_pipeline(
     device: &wgpu::Device, targets: Vec<Option<wgpu::ColorTargetState>>,
     pipeline_layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule,
     entry_points: (&'static str, &'static str), is_update: bool,
-    depth_tex: Option<&crate::util::AnyTexture>,
+    depth_format: Option<wgpu::TextureFormat>,
 ) -> wgpu::RenderPipeline {
     let attributes = wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4];
     let buffers: Vec<wgpu::VertexBufferLayout> = if is_update {
@@ -282,9 +362,9 @@ fn generate_pipeline(
         depth_stencil: if is_update {
             None
         } else {
-            if let Some(depth_tex) = depth_tex {
+            if let Some(format) = depth_format {
                 Some(wgpu::DepthStencilState {
-                    format: depth_tex.format,
+                    format,
                     depth_write_enabled: false,
                     depth_compare: wgpu::CompareFunction::LessEqual,
                     stencil: wgpu::StencilState::default(),
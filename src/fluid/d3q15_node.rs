@@ -1,7 +1,8 @@
 use super::{init_lattice_material, is_sd_sphere, LatticeInfo, LatticeType, OBSTACLE_RADIUS};
 use crate::util::{
     node::{BindingGroupSetting, ComputeNode},
-    AnyTexture, BufferObj,
+    shader::reload_shader,
+    AnyTexture, BufferObj, UniformBuffer,
 };
 use app_surface::math::{Position, Size};
 
@@ -16,12 +17,13 @@ pub struct D3Q15Node {
     pub lattice: wgpu::Extent3d,
     pub lattice_pixel_size: u32,
     animation_ty: FieldAnimationType,
-    pub lbm_uniform_buf: BufferObj,
-    pub fluid_uniform_buf: BufferObj,
+    pub lbm_uniform_buf: UniformBuffer<LbmUniform>,
+    pub fluid_uniform_buf: UniformBuffer<FieldUniform>,
     pub macro_tex: AnyTexture,
     pub lattice_info_data: Vec<LatticeInfo>,
     pub info_buf: BufferObj,
     setting_nodes: Vec<BindingGroupSetting>,
+    pipeline_layouts: Vec<wgpu::PipelineLayout>,
     collide_stream_pipelines: Vec<wgpu::ComputePipeline>,
     boundary_pipelines: Vec<wgpu::ComputePipeline>,
     pub dispatch_group_count: (u32, u32, u32),
@@ -69,13 +71,9 @@ impl D3Q15Node {
             speed_ty: 1,
             _padding: [0.0; 3],
         };
-        let lbm_uniform_buf =
-            BufferObj::create_uniform_buffer(device, &lbm_uniform_data, Some("uniform_buf0"));
-        let fluid_uniform_buf = BufferObj::create_uniform_buffer(
-            device,
-            &field_uniform_data,
-            Some("fluid_uniform_buf"),
-        );
+        let lbm_uniform_buf = UniformBuffer::new(device, lbm_uniform_data, Some("uniform_buf0"));
+        let fluid_uniform_buf =
+            UniformBuffer::new(device, field_uniform_data, Some("fluid_uniform_buf"));
         let scalar_lattice_size =
             (lattice.width * lattice.height * lattice.depth_or_array_layers * 4)
                 as wgpu::BufferAddress;
@@ -110,6 +108,7 @@ impl D3Q15Node {
 
         let visibilitys: Vec<wgpu::ShaderStages> = [wgpu::ShaderStages::COMPUTE; 10].to_vec();
         let mut setting_nodes = Vec::<BindingGroupSetting>::with_capacity(2);
+        let mut pipeline_layouts = Vec::<wgpu::PipelineLayout>::with_capacity(2);
         let mut collide_stream_pipelines = Vec::<wgpu::ComputePipeline>::with_capacity(2);
         let mut boundary_pipelines = Vec::<wgpu::ComputePipeline>::with_capacity(2);
 
@@ -118,7 +117,7 @@ impl D3Q15Node {
                 vec![&collide_stream_buffers[i], &collide_stream_buffers[(i + 1) % 2], &info_buf];
             let setting_node = BindingGroupSetting::new(
                 device,
-                vec![&lbm_uniform_buf, &fluid_uniform_buf],
+                vec![&lbm_uniform_buf.buffer, &fluid_uniform_buf.buffer],
                 buffers.clone(),
                 vec![(&macro_tex, Some(macro_tex_access))],
                 vec![],
@@ -129,21 +128,20 @@ impl D3Q15Node {
                 bind_group_layouts: &[&setting_node.bind_group_layout],
                 push_constant_ranges: &[],
             });
-            let collide_stream_pipeline =
-                device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                    label: Some("collid_stream pipeline"),
-                    layout: Some(&pipeline_layout),
-                    module: &collide_stream_shader,
-                    entry_point: "main",
-                });
-            let boundary_pipeline =
-                device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-                    label: Some("boundary_pipeline pipeline"),
-                    layout: Some(&pipeline_layout),
-                    module: &boundary_shader,
-                    entry_point: "main",
-                });
+            let collide_stream_pipeline = create_pipeline(
+                device,
+                &pipeline_layout,
+                &collide_stream_shader,
+                "collid_stream pipeline",
+            );
+            let boundary_pipeline = create_pipeline(
+                device,
+                &pipeline_layout,
+                &boundary_shader,
+                "boundary_pipeline pipeline",
+            );
             setting_nodes.push(setting_node);
+            pipeline_layouts.push(pipeline_layout);
             collide_stream_pipelines.push(collide_stream_pipeline);
             boundary_pipelines.push(boundary_pipeline);
         }
@@ -153,7 +151,7 @@ impl D3Q15Node {
         let reset_node = ComputeNode::new(
             device,
             dispatch_group_count,
-            vec![&lbm_uniform_buf, &fluid_uniform_buf],
+            vec![&lbm_uniform_buf.buffer, &fluid_uniform_buf.buffer],
             vec![&collide_stream_buffers[0], &collide_stream_buffers[1], &info_buf],
             vec![(&macro_tex, Some(macro_tex_access))],
             &init_shader,
@@ -169,6 +167,7 @@ impl D3Q15Node {
             lattice_info_data,
             info_buf,
             setting_nodes,
+            pipeline_layouts,
             dispatch_group_count,
             collide_stream_pipelines,
             boundary_pipelines,
@@ -188,6 +187,45 @@ impl D3Q15Node {
         return instance;
     }
 
+    // shader 热重载：bind group 不变，只重建 pipeline，出错时保留旧的
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "3d_lbm/collide_stream") {
+            if let Some(pipelines) =
+                self.try_create_pipelines(device, &shader, "collid_stream pipeline")
+            {
+                self.collide_stream_pipelines = pipelines;
+            }
+        }
+        if let Some(shader) = reload_shader(device, changed, "3d_lbm/boundary") {
+            if let Some(pipelines) =
+                self.try_create_pipelines(device, &shader, "boundary_pipeline pipeline")
+            {
+                self.boundary_pipelines = pipelines;
+            }
+        }
+        if let Some(shader) = reload_shader(device, changed, "3d_lbm/init") {
+            self.reset_node.reload(device, &shader);
+        }
+    }
+
+    fn try_create_pipelines(
+        &self, device: &wgpu::Device, shader: &wgpu::ShaderModule, label: &str,
+    ) -> Option<Vec<wgpu::ComputePipeline>> {
+        let pipelines = crate::util::shader::try_create(device, || {
+            self.pipeline_layouts
+                .iter()
+                .map(|layout| create_pipeline(device, layout, shader, label))
+                .collect::<Vec<_>>()
+        });
+        match pipelines {
+            Ok(pipelines) => Some(pipelines),
+            Err(e) => {
+                crate::console_log!("compute pipeline reload error: {}", e);
+                None
+            }
+        }
+    }
+
     pub fn reset(&mut self, encoder: &mut wgpu::CommandEncoder) {
         self.reset_node.compute(encoder);
     }
@@ -269,3 +307,14 @@ impl D3Q15Node {
         cpass.dispatch_workgroups(self.dispatch_group_count.0, self.dispatch_group_count.1, 1);
     }
 }
+
+fn create_pipeline(
+    device: &wgpu::Device, layout: &wgpu::PipelineLayout, shader: &wgpu::ShaderModule, label: &str,
+) -> wgpu::ComputePipeline {
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        module: shader,
+        entry_point: "main",
+    })
+}
@@ -1,15 +1,17 @@
-use super::{AAD2Q9Node, OBSTACLE_RADIUS};
+use super::{lbm_fluid_ty, AAD2Q9Node, LatticeEdit, LatticeType, INLET_SPEED, OBSTACLE_RADIUS};
 use crate::util::{
-    node::{BufferlessFullscreenNode, ComputeNode},
-    BufferObj,
+    node::{Bindings, BufferlessFullscreenNode, ComputeNode},
+    render_graph::{RenderGraph, TextureDesc, FRAME},
+    BufferObj, GpuProfiler, ShaderReflection,
 };
 use app_surface::math::{Position, Size};
 
-use crate::{fluid::LbmUniform, setting_obj::SettingObj, FieldAnimationType, Player};
+use crate::gesture::PointerTracker;
+use crate::{fluid::LbmUniform, setting_obj::SettingObj, EditTool, FieldAnimationType, Player};
 use wgpu::{CommandEncoderDescriptor, Device, Queue, TextureFormat};
-use zerocopy::AsBytes;
 
 use crate::create_shader_module;
+use crate::util::shader::reload_shader;
 
 // 通用的流體模擬，產生外部依賴的流體量
 pub struct FluidPlayer {
@@ -17,14 +19,24 @@ pub struct FluidPlayer {
     canvas_size: Size<u32>,
     lattice: wgpu::Extent3d,
     lattice_pixel_size: u32,
-    pre_pos: Position,
-    fluid_compute_node: AAD2Q9Node,
+    // 每个手指独立产生外力笔画
+    pointers: PointerTracker,
+    tool: EditTool,
+    // 桌面端鼠标按下的位置：鼠标只有 on_click 与 touch_end，没有 touch_begin
+    press_pos: Option<Position>,
+    pub fluid_compute_node: AAD2Q9Node,
     // collide scheme
     use_aa_pattern: bool,
     curl_cal_node: ComputeNode,
     particle_update_node: ComputeNode,
     render_node: BufferlessFullscreenNode,
     particle_render: BufferlessFullscreenNode,
+    // 每帧的 pass 及其执行顺序
+    graph: RenderGraph<FluidPlayer>,
+    // 本帧的模拟步数与待执行的笔刷、撤销/重做，由 enter_frame 写入，graph 中的 pass 读取
+    frame_steps: u32,
+    has_brush: bool,
+    has_restore: bool,
 }
 
 impl FluidPlayer {
@@ -32,53 +44,89 @@ impl FluidPlayer {
         app_view: &app_surface::AppSurface, canvas_size: Size<u32>, canvas_buf: &BufferObj,
         setting: &SettingObj,
     ) -> Self {
-        let device = &app_view.device;
+        let (device, queue) = (&app_view.device, &app_view.queue);
         let use_aa_pattern = true;
         let fluid_compute_node = AAD2Q9Node::new(app_view, canvas_size, setting);
         let lattice = fluid_compute_node.lattice;
 
-        let curl_shader =
-            create_shader_module(device, "lbm/curl_update", Some("curl_update_shader"));
         // iOS cannot create R32Float texture, R16Float cannot use to storage texture
         // Need enable TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES feature
         let curl_texture_format = TextureFormat::Rgba16Float;
         // let curl_texture_format = TextureFormat::R16Float;
-        let curl_tex = crate::util::load_texture::empty(
-            device,
-            curl_texture_format,
-            wgpu::Extent3d {
-                width: lattice.width,
-                height: lattice.height,
-                depth_or_array_layers: 1,
+        let mut graph = RenderGraph::new();
+        graph.import(&["lattice", "lattice_info", "macro_tex"]).add_texture(
+            "curl_tex",
+            TextureDesc {
+                format: curl_texture_format,
+                // 与格子数一致
+                scale: 1.0 / fluid_compute_node.lattice_pixel_size as f32,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
             },
-            None,
-            Some(wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING),
-            Some("curl_tex"),
         );
-        let curl_cal_node = ComputeNode::new(
+        graph
+            .add_compute_pass("brush", &[], &["lattice", "lattice_info"], |player, cpass| {
+                player.dispatch_brush(cpass)
+            })
+            .run_if(|player| player.has_brush || player.has_restore);
+        // AA 模式下碰撞与迁移在同一个 kernel 里完成，没有单独的迁移 pass 可以计时
+        graph
+            .add_compute_pass(
+                "lbm collide/stream",
+                &[],
+                &["lattice", "lattice_info", "macro_tex"],
+                |player, cpass| player.dispatch_lbm(cpass),
+            )
+            .repeat(|player| player.frame_steps);
+        // 每个模拟步之后都更新一次旋度
+        graph
+            .add_compute_pass(
+                "curl",
+                &["lattice_info", "macro_tex"],
+                &["curl_tex"],
+                |player, cpass| player.curl_cal_node.dispatch(cpass),
+            )
+            .repeat(|player| player.frame_steps);
+        graph.add_render_pass(
+            "present",
+            &["macro_tex", "curl_tex"],
+            FRAME,
+            wgpu::LoadOp::Clear(wgpu::Color { r: 0.2, g: 0.2, b: 0.25, a: 1.0 }),
+            |player, rpass| player.render_node.draw_rpass(rpass),
+        );
+        if let Err(e) = graph.compile(device, queue, (canvas_size.width, canvas_size.height)) {
+            panic!("fluid render graph: {}", e);
+        }
+        let curl_tex = graph.texture("curl_tex");
+
+        let particles_uniform = setting.particles_uniform.as_ref().unwrap();
+        let curl_shader =
+            create_shader_module(device, "lbm/curl_update", Some("curl_update_shader"));
+        let curl_cal_node = ComputeNode::with_bindings(
             device,
             fluid_compute_node.dispatch_group_count,
-            vec![&fluid_compute_node.lbm_uniform_buf, &fluid_compute_node.fluid_uniform_buf],
-            vec![&fluid_compute_node.info_buf],
-            vec![
-                (&fluid_compute_node.macro_tex, None),
-                (&curl_tex, Some(wgpu::StorageTextureAccess::WriteOnly)),
-            ],
+            &ShaderReflection::new("lbm/curl_update", None, &[]),
+            Bindings::new()
+                .uniform("fluid", &fluid_compute_node.lbm_uniform_buf)
+                .uniform("field", &fluid_compute_node.fluid_uniform_buf)
+                .buffer("lattice_info", &fluid_compute_node.info_buf)
+                .texture("fb", &fluid_compute_node.macro_tex)
+                .texture("curl_info", curl_tex),
             &curl_shader,
         );
 
         let render_shader = create_shader_module(device, "lbm/present", Some("lbm present shader"));
         let sampler = crate::util::load_texture::bilinear_sampler(device);
-        let render_node = BufferlessFullscreenNode::new(
+        let render_node = BufferlessFullscreenNode::with_bindings(
             device,
             app_view.config.format,
-            vec![
-                &fluid_compute_node.fluid_uniform_buf,
-                &setting.particles_uniform.as_ref().unwrap(),
-            ],
-            vec![&canvas_buf],
-            vec![&fluid_compute_node.macro_tex, &curl_tex],
-            vec![&sampler],
+            &ShaderReflection::new("lbm/present", None, &[]),
+            Bindings::new()
+                .uniform("field", &fluid_compute_node.fluid_uniform_buf)
+                .uniform("particle_uniform", particles_uniform)
+                .buffer("canvas", canvas_buf)
+                .texture("macro_info", &fluid_compute_node.macro_tex)
+                .texture("cur_info", curl_tex)
+                .sampler("tex_sampler", &sampler),
             &render_shader,
             None,
             false,
@@ -86,30 +134,29 @@ impl FluidPlayer {
 
         let update_shader =
             create_shader_module(device, "lbm/particle_update", Some("particle_update_shader"));
-        let particle_update_node = ComputeNode::new(
+        let particle_update_node = ComputeNode::with_bindings(
             device,
             setting.particles_threadgroup,
-            vec![
-                &fluid_compute_node.lbm_uniform_buf,
-                &fluid_compute_node.fluid_uniform_buf,
-                &setting.particles_uniform.as_ref().unwrap(),
-            ],
-            vec![&setting.particles_buf.as_ref().unwrap(), &canvas_buf],
-            vec![(&fluid_compute_node.macro_tex, None)],
+            &ShaderReflection::new("lbm/particle_update", None, &[]),
+            Bindings::new()
+                .uniform("fluid", &fluid_compute_node.lbm_uniform_buf)
+                .uniform("field", &fluid_compute_node.fluid_uniform_buf)
+                .uniform("particle_uniform", particles_uniform)
+                .buffer("pb", setting.particles_buf.as_ref().unwrap())
+                .buffer("canvas", canvas_buf)
+                .texture("fb", &fluid_compute_node.macro_tex),
             &update_shader,
         );
 
         let particle_shader = create_shader_module(device, "present", None);
-        let particle_render = BufferlessFullscreenNode::new(
+        let particle_render = BufferlessFullscreenNode::with_bindings(
             device,
             app_view.config.format,
-            vec![
-                &fluid_compute_node.fluid_uniform_buf,
-                &setting.particles_uniform.as_ref().unwrap(),
-            ],
-            vec![&canvas_buf],
-            vec![],
-            vec![],
+            &ShaderReflection::new("present", None, &[]),
+            Bindings::new()
+                .uniform("field", &fluid_compute_node.fluid_uniform_buf)
+                .uniform("particle_uniform", particles_uniform)
+                .buffer("canvas", canvas_buf),
             &particle_shader,
             None,
             false,
@@ -121,124 +168,241 @@ impl FluidPlayer {
             lattice,
             use_aa_pattern,
             lattice_pixel_size: fluid_compute_node.lattice_pixel_size,
-            pre_pos: Position::new(0.0, 0.0),
+            pointers: PointerTracker::default(),
+            tool: EditTool::Force,
+            press_pos: None,
             fluid_compute_node,
             curl_cal_node,
             particle_update_node,
             render_node,
             particle_render,
+            graph,
+            frame_steps: 0,
+            has_brush: false,
+            has_restore: false,
+        }
+    }
+
+    // 本帧的外力笔画与撤销/重做
+    fn dispatch_brush<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        if self.has_brush {
+            self.fluid_compute_node.dispatch_brush(cpass);
+        }
+        // 撤销/重做在笔刷之后，以 CPU 端的材质为准
+        if self.has_restore {
+            self.fluid_compute_node.dispatch_restore(cpass);
+        }
+    }
+
+    // 一次 lbm 迭代
+    fn dispatch_lbm<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        self.fluid_compute_node.dispatch(cpass, 0);
+        if !self.use_aa_pattern {
+            self.fluid_compute_node.dispatch(cpass, 1);
         }
     }
 }
 
 impl Player for FluidPlayer {
+    fn recycle_resources(&mut self) {
+        self.graph.recycle();
+        self.fluid_compute_node.recycle_resources();
+    }
+
     fn on_click(
-        &mut self, _device: &wgpu::Device, queue: &wgpu::Queue, pos: app_surface::math::Position,
+        &mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, pos: app_surface::math::Position,
     ) {
         if pos.x <= 0.0 || pos.y <= 0.0 {
             return;
         }
-        let x = pos.x as u32 / self.lattice_pixel_size;
-        let y = pos.y as u32 / self.lattice_pixel_size;
-        let half_size = OBSTACLE_RADIUS as u32;
-        if x < half_size
-            || x >= self.lattice.width - (half_size + 2)
-            || y < half_size
-            || y >= self.lattice.height - (half_size + 2)
-        {
-            return;
+        self.press_pos = Some(pos);
+        match self.tool {
+            EditTool::Force | EditTool::Obstacle => {
+                let x = pos.x as u32 / self.lattice_pixel_size;
+                let y = pos.y as u32 / self.lattice_pixel_size;
+                let half_size = OBSTACLE_RADIUS as u32;
+                if x < half_size
+                    || x >= self.lattice.width - (half_size + 2)
+                    || y < half_size
+                    || y >= self.lattice.height - (half_size + 2)
+                {
+                    return;
+                }
+                self.fluid_compute_node.add_obstacle(x, y);
+            }
+            EditTool::Eraser => {
+                self.fluid_compute_node.paint_material(
+                    pos,
+                    pos,
+                    OBSTACLE_RADIUS,
+                    LatticeType::Bulk,
+                    [0.0; 2],
+                );
+            }
+            // 线段在抬起时绘制
+            _ => {}
         }
-        self.fluid_compute_node.add_obstacle(queue, x, y);
     }
 
-    fn touch_begin(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) {
-        self.pre_pos = Position::new(0.0, 0.0);
+    fn touch_begin(
+        &mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, id: u64, pos: Position,
+    ) {
+        self.pointers.begin(id, pos);
     }
 
-    fn touch_move(&mut self, _device: &Device, queue: &Queue, pos: app_surface::math::Position) {
+    fn touch_move(&mut self, _device: &Device, _queue: &Queue, id: u64, pos: Position) {
         if pos.x <= 0.0 || pos.y <= 0.0 {
-            self.pre_pos = Position::zero();
+            self.pointers.end(id);
             return;
         }
-        let dis = pos.distance(&self.pre_pos);
-        if (self.pre_pos.x == 0.0 && self.pre_pos.y == 0.0) || dis > 300.0 {
-            self.pre_pos = pos;
-            return;
+        let pre_pos = match self.pointers.move_to(id, pos) {
+            Some(pre_pos) => pre_pos,
+            None => return,
+        };
+        match self.tool {
+            EditTool::Force => {
+                if pos.distance(&pre_pos) <= 300.0 {
+                    self.fluid_compute_node.add_external_force(pos, pre_pos);
+                }
+            }
+            EditTool::Obstacle | EditTool::Eraser => {
+                // 只在按下之后绘制，忽略桌面端鼠标的悬停移动
+                let is_pressed = self.press_pos.is_some()
+                    || self.pointers.get(id).map_or(false, |p| p.start.is_some());
+                if is_pressed {
+                    let material = if self.tool == EditTool::Obstacle {
+                        LatticeType::Obstacle
+                    } else {
+                        LatticeType::Bulk
+                    };
+                    self.fluid_compute_node.paint_material(
+                        pre_pos,
+                        pos,
+                        OBSTACLE_RADIUS,
+                        material,
+                        [0.0; 2],
+                    );
+                }
+            }
+            _ => {}
         }
+    }
 
-        self.fluid_compute_node.add_external_force(queue, pos, self.pre_pos);
+    fn touch_end(&mut self, device: &Device, queue: &Queue, id: u64, pos: Position) {
+        let pointer = self.pointers.end(id);
+        match self.tool {
+            EditTool::Wall | EditTool::Inlet | EditTool::Outlet => {
+                // 从按下的位置到最后一次移动的位置
+                let start = pointer.as_ref().and_then(|p| p.start).or(self.press_pos);
+                if let (Some(start), Some(pointer)) = (start, pointer) {
+                    let (material, velocity) = match self.tool {
+                        EditTool::Wall => (LatticeType::Obstacle, [0.0; 2]),
+                        EditTool::Inlet => {
+                            (LatticeType::Inlet, inlet_velocity(start, pointer.pre_pos))
+                        }
+                        _ => (LatticeType::Outlet, [0.0; 2]),
+                    };
+                    self.fluid_compute_node.paint_material(
+                        start,
+                        pointer.pre_pos,
+                        1.0,
+                        material,
+                        velocity,
+                    );
+                }
+            }
+            _ => {
+                // 轻点放置障碍物或擦除，多个手指可以同时操作
+                if let Some(pointer) = pointer {
+                    if pointer.is_tap {
+                        self.on_click(device, queue, pointer.start.unwrap_or(pos));
+                    }
+                }
+            }
+        }
+        self.press_pos = None;
+    }
+
+    fn set_edit_tool(&mut self, tool: EditTool) {
+        self.tool = tool;
+        self.press_pos = None;
+    }
+
+    fn take_lattice_edit(&mut self) -> Option<LatticeEdit> {
+        // 一笔从按下到抬起作为一次编辑
+        if self.press_pos.is_some() || self.pointers.is_pressed() {
+            return None;
+        }
+        self.fluid_compute_node.take_edit()
+    }
+
+    fn apply_lattice_edit(&mut self, edit: &LatticeEdit, is_undo: bool) {
+        self.fluid_compute_node.apply_edit(edit, is_undo);
+    }
+
+    fn reload_shaders(&mut self, device: &Device, changed: &[String]) {
+        self.fluid_compute_node.reload_shaders(device, changed);
+        if let Some(shader) = reload_shader(device, changed, "lbm/curl_update") {
+            self.curl_cal_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "lbm/present") {
+            self.render_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "lbm/particle_update") {
+            self.particle_update_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "present") {
+            self.particle_render.reload(device, &shader);
+        }
+    }
 
-        self.pre_pos = pos;
+    fn set_brush(&mut self, radius: f32, falloff: f32) {
+        // 半径的单位由像素换算为格子
+        self.fluid_compute_node.brush_radius = (radius / self.lattice_pixel_size as f32).max(0.5);
+        self.fluid_compute_node.brush_falloff = falloff.max(0.0).min(1.0);
     }
 
     fn update_uniforms(&mut self, queue: &Queue, setting: &crate::SettingObj) {
         // 通过外部参数来重置流体粒子碰撞松解时间 tau = (3.0 * x + 0.5), x：[0~1] 趋大，松解时间趋快
         let tau = 3.0 * setting.fluid_viscosity + 0.5;
-        let fluid_ty = if setting.animation_type == FieldAnimationType::Poiseuille { 0 } else { 1 };
-        let uniform_data =
-            LbmUniform::new(tau, fluid_ty, (self.lattice.width * self.lattice.height) as i32);
-        queue.write_buffer(
-            &self.fluid_compute_node.lbm_uniform_buf.buffer,
-            0,
-            uniform_data.as_bytes(),
-        );
+        let fluid_ty = lbm_fluid_ty(setting.animation_type);
+        let soa_offset = (self.lattice.width * self.lattice.height) as i32;
+        self.fluid_compute_node
+            .lbm_uniform_buf
+            .update(queue, |uniform| *uniform = LbmUniform::new(tau, fluid_ty, soa_offset));
     }
 
     fn reset(&mut self, device: &Device, queue: &Queue) {
         self.fluid_compute_node.reset_lattice_info(device, queue);
 
-        self.pre_pos = Position::new(0.0, 0.0);
+        self.pointers = PointerTracker::default();
+        self.press_pos = None;
     }
 
     fn enter_frame(
         &mut self, device: &Device, queue: &Queue, frame_view: &wgpu::TextureView,
-        setting: &mut crate::SettingObj,
+        setting: &mut crate::SettingObj, steps: u32, profiler: &mut GpuProfiler,
     ) {
         setting.particles_uniform_data.is_only_update_pos = 1;
         setting.update_particles_uniform(queue);
+        self.has_brush = self.fluid_compute_node.upload_brush(queue);
+        self.has_restore = self.fluid_compute_node.upload_restore(queue);
+        self.frame_steps = steps;
         let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
             label: Some("fluid player encoder"),
         });
-        {
-            let mut cpass = encoder
-                .begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("fluid solver") });
-
-            for _ in 0..6 {
-                self.fluid_compute_node.dispatch(&mut cpass, 0);
-                // self.particle_update_node.dispatch(&mut cpass);
-                self.curl_cal_node.dispatch(&mut cpass);
-
-                if !self.use_aa_pattern {
-                    self.fluid_compute_node.dispatch(&mut cpass, 1);
-                    // self.particle_update_node.dispatch(&mut cpass);
-                    self.curl_cal_node.dispatch(&mut cpass);
-                }
-            }
-        }
-        // draw macro_tex
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: frame_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.2, g: 0.2, b: 0.25, a: 1.0 }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-            self.render_node.draw_rpass(&mut rpass);
-        }
-        // draw paticles
-        {
-            // self.particle_render.draw(
-            //     frame_view,
-            //     &mut encoder,
-            //     wgpu::LoadOp::Clear(wgpu::Color { r: 0.2, g: 0.2, b: 0.25, a: 1.0 }),
-            // );
-        }
+        self.graph.execute(&mut encoder, self, frame_view, profiler);
         queue.submit(Some(encoder.finish()));
     }
 }
+
+// 入口沿线段的法线方向吹：从上往下画时向右，从左往右画时向上
+// 线段太短时没有可靠的方向，沿 +x 吹
+fn inlet_velocity(start: Position, end: Position) -> [f32; 2] {
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len < 1.0 {
+        return [INLET_SPEED, 0.0];
+    }
+    [INLET_SPEED * dy / len, -INLET_SPEED * dx / len]
+}
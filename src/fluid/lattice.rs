@@ -1,10 +1,10 @@
-use super::{is_sd_sphere, OBSTACLE_RADIUS};
+use super::{is_sd_sphere, BrushCommand, OBSTACLE_RADIUS};
 use crate::FieldAnimationType;
 use app_surface::math::Position;
 use zerocopy::{AsBytes, FromBytes};
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, AsBytes, FromBytes)]
+#[derive(Copy, Clone, Debug, PartialEq, AsBytes, FromBytes)]
 pub struct LatticeInfo {
     pub material: i32,
     //  dynamic iter value, change material ultimately
@@ -13,6 +13,13 @@ pub struct LatticeInfo {
     pub vy: f32,
 }
 
+// 一次编辑（一笔）改变的格子：索引、修改前与修改后的材质
+pub struct LatticeEdit {
+    pub(super) cells: Vec<(usize, LatticeInfo, LatticeInfo)>,
+    // 这一笔写入的外力印记
+    pub(super) forces: Vec<BrushCommand>,
+}
+
 pub enum LatticeType {
     Bulk = 1,
     Boundary = 2,
@@ -56,7 +63,7 @@ pub fn init_lattice_material(
                             vx = 0.13;
                         }
                     }
-                    FieldAnimationType::Poiseuille => {
+                    FieldAnimationType::Poiseuille | FieldAnimationType::WindTunnel => {
                         // poiseuille
                         if y == 0 || y == ny - 1 || (nz > 1 && (z == 0 || z == nz - 1)) {
                             material = LatticeType::Boundary as i32;
@@ -67,7 +74,7 @@ pub fn init_lattice_material(
                             vx = 0.12;
                         } else if x == nx - 2 {
                             material = LatticeType::Outlet as i32;
-                        } else {
+                        } else if ty == FieldAnimationType::Poiseuille {
                             // obstacle
                             let p = Position::new(x as f32, y as f32);
                             if is_sd_sphere(&p.minus(&s0), OBSTACLE_RADIUS)
@@ -1,6 +1,11 @@
 const OBSTACLE_RADIUS: f32 = 16.0;
+// 入口的流速，lattice 单位
+const INLET_SPEED: f32 = 0.12;
+// 每帧的 lbm 迭代次数
+pub const LBM_STEPS_PER_FRAME: u32 = 6;
 
 mod lattice;
+pub(crate) use lattice::LatticeEdit;
 use lattice::*;
 
 mod particle_render_node;
@@ -8,7 +13,7 @@ mod particle_render_node;
 mod d2q9_node;
 
 mod aa_d2q9_node;
-use aa_d2q9_node::AAD2Q9Node;
+pub use aa_d2q9_node::AAD2Q9Node;
 mod d3q15_node;
 use d3q15_node::D3Q15Node;
 
@@ -39,6 +44,14 @@ pub struct LbmUniform {
     pub e_w_max: [[f32; 4]; 9],
     pub inversed_direction: [[i32; 4]; 9],
 }
+crate::util::uniform_layout!(LbmUniform {
+    tau,
+    omega,
+    fluid_ty,
+    soa_offset,
+    e_w_max,
+    inversed_direction,
+});
 
 impl LbmUniform {
     pub fn new(tau: f32, fluid_ty: i32, soa_offset: i32) -> Self {
@@ -76,6 +89,40 @@ pub struct TickTock {
     _pading1: i32,
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsBytes, FromBytes)]
+pub struct BrushUniform {
+    count: i32,
+    _pading0: i32,
+    _pading1: i32,
+    _pading2: i32,
+}
+
+// 写入 LatticeInfo 的笔刷命令：线段或圆形，坐标与半径的单位都是格子
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsBytes, FromBytes)]
+pub struct BrushCommand {
+    p0: [f32; 2],
+    p1: [f32; 2],
+    force: [f32; 2],
+    radius: f32,
+    // 0: 半径内强度一致，1: 从中心到边缘衰减到 0
+    falloff: f32,
+    material: i32,
+    block_iter: i32,
+    _pading0: i32,
+    _pading1: i32,
+}
+
+// lbm 初始化分布所用的流体类型
+// 0: channel flow with a x axis inlet, 1: fluid at rest
+fn lbm_fluid_ty(ty: crate::FieldAnimationType) -> i32 {
+    match ty {
+        crate::FieldAnimationType::Poiseuille | crate::FieldAnimationType::WindTunnel => 0,
+        _ => 1,
+    }
+}
+
 fn is_sd_sphere(p: &app_surface::math::Position, r: f32) -> bool {
     if p.length() > r {
         false
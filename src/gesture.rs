@@ -0,0 +1,186 @@
+use app_surface::math::Position;
+
+// 手指起止距离小于此值视为点击
+const TAP_SLOP: f32 = 10.0;
+
+pub(crate) struct Pointer {
+    // 由 touch_move 直接加入的指针（如桌面端的鼠标移动）没有起点
+    pub start: Option<Position>,
+    pub pre_pos: Position,
+    pub is_tap: bool,
+}
+
+// 按触摸 id 跟踪多个手指，每个手指独立产生笔画
+#[derive(Default)]
+pub(crate) struct PointerTracker {
+    pointers: Vec<(u64, Pointer)>,
+}
+
+impl PointerTracker {
+    pub fn begin(&mut self, id: u64, pos: Position) {
+        self.remove(id);
+        self.pointers.push((id, Pointer { start: Some(pos), pre_pos: pos, is_tap: true }));
+    }
+
+    // 返回该手指上一次的位置，未跟踪的手指从此处开始
+    pub fn move_to(&mut self, id: u64, pos: Position) -> Option<Position> {
+        match self.pointers.iter_mut().find(|(i, _)| *i == id) {
+            Some((_, p)) => {
+                let pre_pos = p.pre_pos;
+                p.pre_pos = pos;
+                if p.is_tap && p.start.map_or(true, |s| pos.distance(&s) > TAP_SLOP) {
+                    p.is_tap = false;
+                }
+                Some(pre_pos)
+            }
+            None => {
+                self.pointers.push((id, Pointer { start: None, pre_pos: pos, is_tap: false }));
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Pointer> {
+        self.pointers.iter().find(|(i, _)| *i == id).map(|(_, p)| p)
+    }
+
+    pub fn end(&mut self, id: u64) -> Option<Pointer> {
+        self.remove(id)
+    }
+
+    // 是否有按下（由 begin 开始）的手指
+    pub fn is_pressed(&self) -> bool {
+        self.pointers.iter().any(|(_, p)| p.start.is_some())
+    }
+
+    // 按下的手指数量，不包含悬停的鼠标
+    pub fn len(&self) -> usize {
+        self.pressed().count()
+    }
+
+    // 最先按下的两个手指的位置
+    pub fn first_two(&self) -> Option<(Position, Position)> {
+        let mut pressed = self.pressed();
+        match (pressed.next(), pressed.next()) {
+            (Some(a), Some(b)) => Some((a.pre_pos, b.pre_pos)),
+            _ => None,
+        }
+    }
+
+    fn pressed(&self) -> impl Iterator<Item = &Pointer> {
+        self.pointers.iter().map(|(_, p)| p).filter(|p| p.start.is_some())
+    }
+
+    fn remove(&mut self, id: u64) -> Option<Pointer> {
+        let index = self.pointers.iter().position(|(i, _)| *i == id)?;
+        Some(self.pointers.remove(index).1)
+    }
+}
+
+// 双指捏合与旋转
+#[derive(Default)]
+pub(crate) struct PinchRotate {
+    // 上一次两指的距离与连线角度
+    pre: Option<(f32, f32)>,
+}
+
+impl PinchRotate {
+    // 返回相对上一次的缩放比例与旋转角度（弧度）
+    pub fn update(&mut self, a: Position, b: Position) -> Option<(f32, f32)> {
+        let dis = a.distance(&b);
+        let angle = (b.y - a.y).atan2(b.x - a.x);
+        let delta = match self.pre {
+            Some((pre_dis, pre_angle)) if pre_dis > 1.0 => {
+                let mut rotation = angle - pre_angle;
+                // 角度跨过 ±π 时取最短的方向
+                if rotation > std::f32::consts::PI {
+                    rotation -= 2.0 * std::f32::consts::PI;
+                } else if rotation < -std::f32::consts::PI {
+                    rotation += 2.0 * std::f32::consts::PI;
+                }
+                Some((dis / pre_dis, rotation))
+            }
+            _ => None,
+        };
+        self.pre = Some((dis, angle));
+        delta
+    }
+
+    // 手指数量变化后重新开始
+    pub fn reset(&mut self) {
+        self.pre = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hover_pointer_is_not_counted() {
+        let mut tracker = PointerTracker::default();
+        assert!(tracker.move_to(0, Position::new(5.0, 5.0)).is_none());
+        tracker.begin(1, Position::new(0.0, 0.0));
+        assert_eq!(tracker.len(), 1);
+        assert!(tracker.first_two().is_none());
+
+        tracker.begin(2, Position::new(10.0, 0.0));
+        assert_eq!(tracker.len(), 2);
+        let (a, b) = tracker.first_two().unwrap();
+        assert_eq!((a.x, b.x), (0.0, 10.0));
+    }
+
+    #[test]
+    fn tap_until_moved_past_slop() {
+        let mut tracker = PointerTracker::default();
+        tracker.begin(1, Position::new(0.0, 0.0));
+        assert_eq!(tracker.move_to(1, Position::new(3.0, 0.0)).map(|p| p.x), Some(0.0));
+        assert!(tracker.get(1).unwrap().is_tap);
+        tracker.move_to(1, Position::new(TAP_SLOP + 1.0, 0.0));
+        assert!(!tracker.end(1).unwrap().is_tap);
+        assert!(!tracker.is_pressed());
+        assert!(tracker.end(1).is_none());
+    }
+
+    #[test]
+    fn begin_restarts_tracked_pointer() {
+        let mut tracker = PointerTracker::default();
+        tracker.move_to(1, Position::new(0.0, 0.0));
+        assert!(!tracker.is_pressed());
+        tracker.begin(1, Position::new(20.0, 0.0));
+        assert!(tracker.is_pressed());
+        assert_eq!(tracker.len(), 1);
+        assert!(tracker.get(1).unwrap().is_tap);
+    }
+
+    #[test]
+    fn pinch_scale_and_rotation() {
+        let mut pinch = PinchRotate::default();
+        let origin = Position::new(0.0, 0.0);
+        assert!(pinch.update(origin, Position::new(10.0, 0.0)).is_none());
+        let (scale, rotation) = pinch.update(origin, Position::new(0.0, 20.0)).unwrap();
+        assert!((scale - 2.0).abs() < 1e-5);
+        assert!((rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+
+        pinch.reset();
+        assert!(pinch.update(origin, Position::new(10.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn pinch_rotation_wraps_across_pi() {
+        let origin = Position::new(0.0, 0.0);
+        let (cos, sin) = (170f32.to_radians().cos(), 170f32.to_radians().sin());
+        let upper = Position::new(cos * 10.0, sin * 10.0);
+        let lower = Position::new(cos * 10.0, -sin * 10.0);
+
+        // 连线角度从 170° 转到 -170°，实际逆时针转了 20°
+        let mut pinch = PinchRotate::default();
+        pinch.update(origin, upper);
+        let (_, rotation) = pinch.update(origin, lower).unwrap();
+        assert!((rotation - 20f32.to_radians()).abs() < 1e-4);
+
+        // 反方向跨过 -π
+        let (_, rotation) = pinch.update(origin, upper).unwrap();
+        assert!((rotation + 20f32.to_radians()).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,412 @@
+use crate::{EditTool, FieldAnimationType, FieldType, ParticleColorType};
+use app_surface::math::Position;
+use std::path::Path;
+
+const SCRIPT_HEADER: &str = "# nature input script v1";
+
+// 一次交互输入：触摸、点击或参数修改
+#[derive(Clone, Copy)]
+pub enum InputEvent {
+    // 触摸事件带有触摸点的 id，多个手指可以同时触摸
+    TouchBegin(u64, Position),
+    TouchMove(u64, Position),
+    TouchEnd(u64, Position),
+    Click(Position),
+    FluidViscosity(f32),
+    ParticlesCount(i32),
+    ParticleColor(ParticleColorType),
+    ParticlePointSize(i32),
+    AnimationType(FieldAnimationType),
+    FieldType(FieldType, FieldAnimationType),
+    Reset,
+    // 外力笔刷的像素半径与边缘衰减
+    Brush(f32, f32),
+    EditTool(EditTool),
+    Undo,
+    Redo,
+}
+
+// step: 事件发生时已执行的模拟步数，回放时在同一步数处触发
+#[derive(Clone, Copy)]
+pub struct TimedInput {
+    pub step: u64,
+    pub event: InputEvent,
+}
+
+// 可序列化的输入脚本
+// 文本格式每行一个事件：`<step> <name> [args...]`，`#` 开头的行是注释
+#[derive(Clone, Default)]
+pub struct InputScript {
+    pub events: Vec<TimedInput>,
+}
+
+impl InputScript {
+    pub fn new() -> Self {
+        Self { events: vec![] }
+    }
+
+    // 事件需要按步数递增的顺序加入
+    pub fn push(&mut self, step: u64, event: InputEvent) {
+        let step = self.events.last().map_or(step, |last| step.max(last.step));
+        self.events.push(TimedInput { step, event });
+    }
+
+    // 脚本覆盖的模拟步数
+    pub fn duration_steps(&self) -> u64 {
+        self.events.last().map_or(0, |last| last.step)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", SCRIPT_HEADER);
+        for input in self.events.iter() {
+            let line = match input.event {
+                InputEvent::TouchBegin(id, p) => format!("touch_begin {} {} {}", id, p.x, p.y),
+                InputEvent::TouchMove(id, p) => format!("touch_move {} {} {}", id, p.x, p.y),
+                InputEvent::TouchEnd(id, p) => format!("touch_end {} {} {}", id, p.x, p.y),
+                InputEvent::Click(p) => format!("click {} {}", p.x, p.y),
+                InputEvent::FluidViscosity(nu) => format!("viscosity {}", nu),
+                InputEvent::ParticlesCount(count) => format!("particles_count {}", count),
+                InputEvent::ParticleColor(ty) => format!("particle_color {}", color_name(ty)),
+                InputEvent::ParticlePointSize(size) => format!("point_size {}", size),
+                InputEvent::AnimationType(ty) => format!("animation {}", animation_name(ty)),
+                InputEvent::FieldType(field_ty, animation_ty) => {
+                    format!("field {} {}", field_name(field_ty), animation_name(animation_ty))
+                }
+                InputEvent::Reset => "reset".to_string(),
+                InputEvent::Brush(radius, falloff) => format!("brush {} {}", radius, falloff),
+                InputEvent::EditTool(tool) => format!("tool {}", tool_name(tool)),
+                InputEvent::Undo => "undo".to_string(),
+                InputEvent::Redo => "redo".to_string(),
+            };
+            text += &format!("{} {}\n", input.step, line);
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut script = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (step, event) =
+                parse_line(line).map_err(|e| format!("line {}: {} ({})", i + 1, e, line))?;
+            if step < script.duration_steps() {
+                return Err(format!("line {}: steps must not decrease ({})", i + 1, line));
+            }
+            script.push(step, event);
+        }
+        Ok(script)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::from_text(&text)
+    }
+}
+
+// 录制：累计已执行的模拟步数，输入事件按当时的步数写入脚本
+pub(crate) struct ScriptRecorder {
+    script: InputScript,
+    step: u64,
+}
+
+impl ScriptRecorder {
+    pub fn new() -> Self {
+        Self { script: InputScript::new(), step: 0 }
+    }
+
+    pub fn record(&mut self, event: InputEvent) {
+        self.script.push(self.step, event);
+    }
+
+    pub fn advance(&mut self, steps: u32) {
+        self.step += steps as u64;
+    }
+
+    pub fn finish(self) -> InputScript {
+        self.script
+    }
+}
+
+// 回放：事件在脚本记录的模拟步数处触发，与帧率无关
+pub(crate) struct ScriptReplayer {
+    script: InputScript,
+    cursor: usize,
+    step: u64,
+    // 回放结束后从头开始，用于展台循环演示
+    pub looping: bool,
+}
+
+impl ScriptReplayer {
+    pub fn new(script: InputScript, looping: bool) -> Self {
+        Self { script, cursor: 0, step: 0, looping }
+    }
+
+    // 取出当前步数已到达的事件
+    pub fn take_due(&mut self) -> Vec<InputEvent> {
+        let mut due = vec![];
+        while let Some(input) = self.script.events.get(self.cursor) {
+            if input.step > self.step {
+                break;
+            }
+            due.push(input.event);
+            self.cursor += 1;
+        }
+        due
+    }
+
+    // 本帧的模拟步数不能越过下一个事件
+    pub fn clamp_steps(&self, steps: u32) -> u32 {
+        match self.script.events.get(self.cursor) {
+            Some(next) => steps.min((next.step - self.step) as u32),
+            None => steps,
+        }
+    }
+
+    pub fn advance(&mut self, steps: u32) {
+        self.step += steps as u64;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.script.events.len()
+    }
+
+    pub fn rewind(&mut self) {
+        self.cursor = 0;
+        self.step = 0;
+    }
+}
+
+fn parse_line(line: &str) -> Result<(u64, InputEvent), String> {
+    let mut words = line.split_whitespace();
+    let step = words
+        .next()
+        .and_then(|w| w.parse::<u64>().ok())
+        .ok_or_else(|| "missing step".to_string())?;
+    let name = words.next().ok_or_else(|| "missing event name".to_string())?;
+    let args: Vec<&str> = words.collect();
+    let arg = |i: usize| args.get(i).copied().ok_or_else(|| format!("missing argument {}", i));
+    let f32_arg = |i: usize| arg(i)?.parse::<f32>().map_err(|e| e.to_string());
+    let i32_arg = |i: usize| arg(i)?.parse::<i32>().map_err(|e| e.to_string());
+    let pos_arg = || -> Result<Position, String> { Ok(Position::new(f32_arg(0)?, f32_arg(1)?)) };
+    // 触摸事件：`<id> x y`，省略 id 时为 0
+    let touch_arg = || -> Result<(u64, Position), String> {
+        if args.len() < 3 {
+            return Ok((0, pos_arg()?));
+        }
+        let id = arg(0)?.parse::<u64>().map_err(|e| e.to_string())?;
+        Ok((id, Position::new(f32_arg(1)?, f32_arg(2)?)))
+    };
+
+    let event = match name {
+        "touch_begin" => {
+            let (id, pos) = touch_arg()?;
+            InputEvent::TouchBegin(id, pos)
+        }
+        "touch_move" => {
+            let (id, pos) = touch_arg()?;
+            InputEvent::TouchMove(id, pos)
+        }
+        "touch_end" => {
+            let (id, pos) = touch_arg()?;
+            InputEvent::TouchEnd(id, pos)
+        }
+        "click" => InputEvent::Click(pos_arg()?),
+        "viscosity" => InputEvent::FluidViscosity(f32_arg(0)?),
+        "particles_count" => InputEvent::ParticlesCount(i32_arg(0)?),
+        "particle_color" => InputEvent::ParticleColor(parse_color(arg(0)?)?),
+        "point_size" => InputEvent::ParticlePointSize(i32_arg(0)?),
+        "animation" => InputEvent::AnimationType(parse_animation(arg(0)?)?),
+        "field" => InputEvent::FieldType(parse_field(arg(0)?)?, parse_animation(arg(1)?)?),
+        "reset" => InputEvent::Reset,
+        "brush" => InputEvent::Brush(f32_arg(0)?, f32_arg(1)?),
+        "tool" => InputEvent::EditTool(parse_tool(arg(0)?)?),
+        "undo" => InputEvent::Undo,
+        "redo" => InputEvent::Redo,
+        _ => return Err(format!("unknown event `{}`", name)),
+    };
+    Ok((step, event))
+}
+
+fn field_name(ty: FieldType) -> &'static str {
+    match ty {
+        FieldType::Field => "field",
+        FieldType::Fluid => "fluid",
+        FieldType::D3Fluid => "d3_fluid",
+        FieldType::ClothInFluid => "cloth_in_fluid",
+    }
+}
+
+fn parse_field(name: &str) -> Result<FieldType, String> {
+    Ok(match name {
+        "field" => FieldType::Field,
+        "fluid" => FieldType::Fluid,
+        "d3_fluid" => FieldType::D3Fluid,
+        "cloth_in_fluid" => FieldType::ClothInFluid,
+        _ => return Err(format!("unknown field type `{}`", name)),
+    })
+}
+
+fn animation_name(ty: FieldAnimationType) -> &'static str {
+    match ty {
+        FieldAnimationType::Basic => "basic",
+        FieldAnimationType::JuliaSet => "julia_set",
+        FieldAnimationType::Spirl => "spirl",
+        FieldAnimationType::Poiseuille => "poiseuille",
+        FieldAnimationType::LidDrivenCavity => "lid_driven_cavity",
+        FieldAnimationType::WindTunnel => "wind_tunnel",
+        FieldAnimationType::Custom => "custom",
+    }
+}
+
+fn parse_animation(name: &str) -> Result<FieldAnimationType, String> {
+    Ok(match name {
+        "basic" => FieldAnimationType::Basic,
+        "julia_set" => FieldAnimationType::JuliaSet,
+        "spirl" => FieldAnimationType::Spirl,
+        "poiseuille" => FieldAnimationType::Poiseuille,
+        "lid_driven_cavity" => FieldAnimationType::LidDrivenCavity,
+        "wind_tunnel" => FieldAnimationType::WindTunnel,
+        "custom" => FieldAnimationType::Custom,
+        _ => return Err(format!("unknown animation type `{}`", name)),
+    })
+}
+
+fn color_name(ty: ParticleColorType) -> &'static str {
+    match ty {
+        ParticleColorType::Uniform => "uniform",
+        ParticleColorType::MovementAngle => "movement_angle",
+        ParticleColorType::Speed => "speed",
+    }
+}
+
+fn parse_color(name: &str) -> Result<ParticleColorType, String> {
+    Ok(match name {
+        "uniform" => ParticleColorType::Uniform,
+        "movement_angle" => ParticleColorType::MovementAngle,
+        "speed" => ParticleColorType::Speed,
+        _ => return Err(format!("unknown particle color type `{}`", name)),
+    })
+}
+
+fn tool_name(tool: EditTool) -> &'static str {
+    match tool {
+        EditTool::Force => "force",
+        EditTool::Obstacle => "obstacle",
+        EditTool::Eraser => "eraser",
+        EditTool::Wall => "wall",
+        EditTool::Inlet => "inlet",
+        EditTool::Outlet => "outlet",
+    }
+}
+
+fn parse_tool(name: &str) -> Result<EditTool, String> {
+    Ok(match name {
+        "force" => EditTool::Force,
+        "obstacle" => EditTool::Obstacle,
+        "eraser" => EditTool::Eraser,
+        "wall" => EditTool::Wall,
+        "inlet" => EditTool::Inlet,
+        "outlet" => EditTool::Outlet,
+        _ => return Err(format!("unknown edit tool `{}`", name)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_script() -> InputScript {
+        let mut script = InputScript::new();
+        script.push(0, InputEvent::FieldType(FieldType::Fluid, FieldAnimationType::Poiseuille));
+        script.push(0, InputEvent::FluidViscosity(0.02));
+        script.push(3, InputEvent::TouchBegin(1, Position::new(10.0, 20.5)));
+        script.push(5, InputEvent::TouchMove(1, Position::new(12.0, 21.0)));
+        script.push(8, InputEvent::TouchEnd(1, Position::new(12.0, 21.0)));
+        script.push(8, InputEvent::EditTool(EditTool::Inlet));
+        script.push(10, InputEvent::Brush(6.0, 0.5));
+        script.push(12, InputEvent::Undo);
+        script
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let script = sample_script();
+        let text = script.to_text();
+        let parsed = InputScript::from_text(&text).unwrap();
+        assert_eq!(parsed.events.len(), script.events.len());
+        assert_eq!(parsed.duration_steps(), 12);
+        assert_eq!(parsed.to_text(), text);
+        assert!(
+            matches!(parsed.events[2].event, InputEvent::TouchBegin(1, p) if p.x == 10.0 && p.y == 20.5)
+        );
+    }
+
+    #[test]
+    fn touch_id_defaults_to_zero() {
+        let script = InputScript::from_text("4 touch_move 1.5 2.5").unwrap();
+        assert!(
+            matches!(script.events[0].event, InputEvent::TouchMove(0, p) if p.x == 1.5 && p.y == 2.5)
+        );
+    }
+
+    #[test]
+    fn decreasing_steps_are_rejected() {
+        let err = InputScript::from_text("5 reset\n3 undo").err().unwrap();
+        assert!(err.contains("line 2"));
+        assert!(err.contains("steps must not decrease"));
+    }
+
+    #[test]
+    fn unknown_event_is_rejected() {
+        let err = InputScript::from_text("# comment\n\n1 jump").err().unwrap();
+        assert!(err.starts_with("line 3"));
+    }
+
+    #[test]
+    fn replayer_stops_at_each_event() {
+        let mut replayer = ScriptReplayer::new(sample_script(), false);
+        // 第 0 步的事件立即触发
+        assert_eq!(replayer.take_due().len(), 2);
+        assert_eq!(replayer.clamp_steps(10), 3);
+        replayer.advance(3);
+        assert_eq!(replayer.take_due().len(), 1);
+        assert_eq!(replayer.clamp_steps(1), 1);
+        replayer.advance(1);
+        assert!(replayer.take_due().is_empty());
+        assert_eq!(replayer.clamp_steps(10), 1);
+        replayer.advance(1);
+        assert_eq!(replayer.take_due().len(), 1);
+        replayer.advance(replayer.clamp_steps(10));
+        assert_eq!(replayer.take_due().len(), 2);
+        replayer.advance(replayer.clamp_steps(10));
+        replayer.take_due();
+        replayer.advance(replayer.clamp_steps(10));
+        assert_eq!(replayer.take_due().len(), 1);
+        assert!(replayer.is_finished());
+        // 脚本结束后不再限制步数
+        assert_eq!(replayer.clamp_steps(7), 7);
+
+        replayer.rewind();
+        assert!(!replayer.is_finished());
+        assert_eq!(replayer.take_due().len(), 2);
+    }
+
+    #[test]
+    fn recorder_uses_accumulated_steps() {
+        let mut recorder = ScriptRecorder::new();
+        recorder.record(InputEvent::Reset);
+        recorder.advance(4);
+        recorder.advance(2);
+        recorder.record(InputEvent::Redo);
+        let script = recorder.finish();
+        assert_eq!(script.events[0].step, 0);
+        assert_eq!(script.events[1].step, 6);
+    }
+}
@@ -6,7 +6,7 @@ use std::usize;
 mod field_player;
 use field_player::FieldPlayer;
 mod field_velocity_code;
-use field_velocity_code::get_velocity_code_segment;
+pub use field_velocity_code::get_velocity_code_segment;
 
 mod setting_obj;
 pub use setting_obj::SettingObj;
@@ -16,6 +16,12 @@ use fluid::{D3FluidPlayer, FluidPlayer};
 
 mod combinate_canvas;
 pub use combinate_canvas::CombinateCanvas;
+mod sim_clock;
+pub use sim_clock::{SimClock, SIM_STEPS_PER_SECOND};
+mod input_script;
+pub use input_script::{InputEvent, InputScript, TimedInput};
+mod edit_history;
+mod gesture;
 
 mod diffraction;
 use diffraction::Diffraction;
@@ -28,7 +34,11 @@ use floor::Floor;
 mod noise;
 
 mod pbd;
-pub use pbd::PBDCanvas;
+use pbd::FlagPlayer;
+pub use pbd::{
+    ClothCanvas, ClothConfig, ClothMaterial, ColliderObj, Compliance, MeshCloth, MeshClothCanvas,
+    ObjMesh, PBDCanvas,
+};
 
 mod brick;
 pub use brick::Brick;
@@ -47,10 +57,7 @@ pub use web::*;
 pub use std::println as console_log;
 
 mod util;
-#[cfg(not(target_arch = "wasm32"))]
 use util::shader::{create_shader_module, insert_code_then_create};
-#[cfg(target_arch = "wasm32")]
-use web::{create_shader_module, insert_code_then_create};
 
 use util::vertex::PosColor as PosTangent;
 use util::vertex::PosOnly;
@@ -69,17 +76,60 @@ trait Player {
 
     fn on_click(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, _pos: Position) {}
 
-    fn touch_begin(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) {}
+    // id: 触摸点的标识，多个手指同时触摸时各自独立
+    fn touch_begin(
+        &mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, _id: u64, _pos: Position,
+    ) {
+    }
 
-    fn touch_move(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, _pos: Position) {}
+    fn touch_move(
+        &mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, _id: u64, _pos: Position,
+    ) {
+    }
 
-    fn touch_end(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) {}
+    fn touch_end(
+        &mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, _id: u64, _pos: Position,
+    ) {
+    }
 
     fn reset(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue) {}
 
+    // 外力笔刷：radius 为像素半径，falloff 为 [0, 1] 的边缘衰减
+    fn set_brush(&mut self, _radius: f32, _falloff: f32) {}
+
+    // 触摸与点击使用的编辑工具
+    fn set_edit_tool(&mut self, _tool: EditTool) {}
+
+    // 取出最近一笔完成的材质修改，笔画进行中时返回 None
+    fn take_lattice_edit(&mut self) -> Option<fluid::LatticeEdit> {
+        None
+    }
+
+    // 撤销/重做材质修改
+    fn apply_lattice_edit(&mut self, _edit: &fluid::LatticeEdit, _is_undo: bool) {}
+
+    // shader 热重载：changed 为源文件有变化的 shader 名，重建用到它们的节点的 pipeline
+    fn reload_shaders(&mut self, _device: &wgpu::Device, _changed: &[String]) {}
+
+    // 被替换之前调用：把可以复用的纹理与 buffer 还给 resource_pool，下一个 player 创建时取用
+    fn recycle_resources(&mut self) {}
+
+    // 模拟时钟由 canvas 持有，所有 player 共用，暂停时仍会调用 enter_frame 重绘，只是模拟步数为 0
+    // 以下方法在 canvas 修改时钟之后调用，player 在这里处理自身与时钟相关的状态
+    fn pause(&mut self) {}
+
+    fn resume(&mut self) {}
+
+    // 暂停状态下推进 n 个模拟步
+    fn step(&mut self, _n: u32) {}
+
+    fn set_time_scale(&mut self, _scale: f32) {}
+
+    // steps: 本帧需要推进的模拟步数，由 SimClock 按真实时间换算，为 0 时只绘制
+    // profiler: 各个 pass 用 begin_scope / end_scope 计时，未开启时为空操作
     fn enter_frame(
         &mut self, device: &wgpu::Device, queue: &wgpu::Queue, frame_view: &wgpu::TextureView,
-        _setting: &mut crate::SettingObj,
+        _setting: &mut crate::SettingObj, steps: u32, profiler: &mut crate::util::GpuProfiler,
     );
 }
 
@@ -88,6 +138,8 @@ pub enum FieldType {
     Field,
     Fluid,
     D3Fluid,
+    // pbd cloth immersed in the 2d lbm fluid
+    ClothInFluid,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -97,16 +149,32 @@ pub enum FieldAnimationType {
     Spirl,
     Poiseuille,
     LidDrivenCavity,
+    // poiseuille channel without the preset obstacles
+    WindTunnel,
     Custom,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum ParticleColorType {
     Uniform = 0,
     MovementAngle = 1,
     Speed = 2,
 }
 
+// 流体场景的编辑工具
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditTool {
+    // 拖动产生外力，轻点放置障碍物
+    Force,
+    Obstacle,
+    // 擦除障碍物与墙，恢复为流体
+    Eraser,
+    // 以下为按下到抬起之间的线段
+    Wall,
+    Inlet,
+    Outlet,
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, AsBytes, FromBytes)]
 pub struct FieldUniform {
@@ -123,6 +191,15 @@ pub struct FieldUniform {
     // align to 16 * n
     pub _padding: [f32; 3],
 }
+util::uniform_layout!(FieldUniform {
+    lattice_size,
+    lattice_pixel_size,
+    canvas_size,
+    normalized_space_size,
+    pixel_distance,
+    speed_ty,
+});
+
 #[repr(C)]
 #[derive(Copy, Clone, AsBytes, FromBytes)]
 pub struct ParticleUniform {
@@ -140,6 +217,16 @@ pub struct ParticleUniform {
     // 1: not draw on the canvas
     pub is_only_update_pos: i32,
 }
+util::uniform_layout!(ParticleUniform {
+    color,
+    num,
+    point_size,
+    life_time,
+    fade_out_factor,
+    speed_factor,
+    color_ty,
+    is_only_update_pos,
+});
 
 #[repr(C)]
 #[derive(Copy, Clone, AsBytes, FromBytes)]
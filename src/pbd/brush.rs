@@ -1,4 +1,5 @@
 use crate::util::node::{BufferlessFullscreenNode, ComputeNode, ViewNode, ViewNodeBuilder};
+use crate::util::shader::reload_shader;
 use crate::util::{vertex::VertexEmpty, BufferObj};
 
 use super::{
@@ -287,6 +288,25 @@ impl MaoBrush {
         instance
     }
 
+    // shader 热重载：重建用到了 changed 中 shader 的节点
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "pbd/brush/predict") {
+            self.predict_solver.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/brush/stretch_solver") {
+            self.stretch_solver.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/brush/bending_solver") {
+            self.bend_solver.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/brush/debug_plane") {
+            self.debug_plane.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/brush/display") {
+            self.display_node.reload(device, &shader);
+        }
+    }
+
     pub fn rotate(&mut self, app_view: &app_surface::AppSurface, x: f32, y: f32) {
         let mut model_rotate_mat = glm::rotate_x(&glm::Mat4::identity(), 0.8 * x);
         model_rotate_mat = glm::rotate_y(&model_rotate_mat, 0.8 * y);
@@ -353,17 +373,27 @@ impl MaoBrush {
     }
 
     pub fn enter_frame(&mut self, app_view: &mut AppSurface) {
+        let (frame, frame_view) = app_view.get_current_frame_view();
+        self.draw_frame(app_view, &frame_view, 1);
+        frame.present();
+    }
+
+    // 推进 frames 个参考帧的模拟并绘制到 frame_view，由调用方 present
+    pub fn draw_frame(
+        &mut self, app_view: &AppSurface, frame_view: &wgpu::TextureView, frames: u32,
+    ) {
         let mut encoder = app_view.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("MaoBrush encoder"),
         });
-        self.step_solver(&mut encoder);
+        for _ in 0..frames {
+            self.step_solver(&mut encoder);
+        }
 
-        let (frame, frame_view) = app_view.get_current_frame_view();
         {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("MaoBrush render pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &frame_view,
+                    view: frame_view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(crate::util::utils::alpha_color()),
@@ -385,6 +415,5 @@ impl MaoBrush {
             self.debug_plane.draw_rpass(&mut rpass);
         }
         app_view.queue.submit(Some(encoder.finish()));
-        frame.present();
     }
 }
@@ -1,22 +1,42 @@
 use crate::util::node::ComputeNode;
 use crate::util::node::{ViewNode, ViewNodeBuilder};
-use crate::util::{vertex::PosParticleIndex, BufferObj};
+use crate::util::shader::reload_shader;
+use crate::util::{vertex::PosParticleIndex, BufferObj, GpuProfiler, UniformBuffer};
 
-use super::{generate_cloth_particles, ClothUniform, MeshColoringObj};
+use super::{
+    cloth_triangle_indices, generate_cloth_particles, is_bending_torn, regroup_torn_constraints,
+    torn_pairs, BendingConstraintObj, ClothCollision, ClothConfig, ClothPicker, MeshColoringObj,
+    StretchConstraintObj, TearReadback,
+};
 
 use app_surface::{
     math::{Position, Size},
     AppSurface, SurfaceFrame, Touch, TouchPhase,
 };
+use std::collections::HashSet;
 use zerocopy::AsBytes;
 
 pub struct Cloth {
-    particle_buf: BufferObj,
+    config: ClothConfig,
+    pub particle_x_num: u32,
+    pub particle_y_num: u32,
+    // 初始时布料的半高，布料中心在原点
+    half_height: f32,
+    pub uniform_buf: UniformBuffer<ClothUniform>,
+    pub particle_buf: BufferObj,
     constraint_buf: BufferObj,
     group_constraints_buf: BufferObj,
+    reorder_constraints_buf: BufferObj,
+    stretch_coloring_buf: BufferObj,
     bend_constraints_buf: BufferObj,
 
+    constraints: Vec<StretchConstraintObj>,
+    reorder_constraints: Vec<[i32; 3]>,
+    // 跨过撕裂处的弯曲约束被置为 v = -1
+    bend_constraints: Vec<BendingConstraintObj>,
     stretch_mesh_coloring: Vec<MeshColoringObj>,
+    // stretch_coloring_buf 能容纳的分组数
+    stretch_coloring_capacity: usize,
     bend_mesh_coloring: Vec<MeshColoringObj>,
 
     // 预测位置并重置约束的 lambda 等参数
@@ -26,10 +46,36 @@ pub struct Cloth {
     display_node: ViewNode,
     depth_texture_view: wgpu::TextureView,
     frame_count: usize,
+    // 触点抓取，固定
+    pub picker: ClothPicker,
+    touch_start: Option<Position>,
+    is_dragging: bool,
+    tear_readback: TearReadback,
+    // 碰撞体与自碰撞
+    pub collision: ClothCollision,
 }
 
+// 每隔多少帧检查一次撕裂
+const TEAR_CHECK_INTERVAL: usize = 30;
+
+// 各个着色分组的计时标签，超出的分组计入最后一个
+const STRETCH_GROUP_LABELS: [&str; 12] = [
+    "cloth stretch group 0",
+    "cloth stretch group 1",
+    "cloth stretch group 2",
+    "cloth stretch group 3",
+    "cloth stretch group 4",
+    "cloth stretch group 5",
+    "cloth stretch group 6",
+    "cloth stretch group 7",
+    "cloth stretch group 8",
+    "cloth stretch group 9",
+    "cloth stretch group 10",
+    "cloth stretch group 11+",
+];
+
 impl Cloth {
-    pub fn new(app_view: &AppSurface) -> Self {
+    pub fn new(app_view: &AppSurface, config: ClothConfig) -> Self {
         let _encoder =
             app_view.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         let viewport_size: Size<f32> = (&app_view.config).into();
@@ -45,32 +91,14 @@ impl Cloth {
             },
             None,
         );
-        // （32， 64） 这个组合，约束分组后为 9 组，且没有极小数据量的分组
-        // 为何 （32， 64）导致在 particle buffer 里开始部分数据顺序乱了？
-        let particle_x_num = 32_u32;
-        let particle_y_num = 58_u32;
-
-        //static const float MODE_COMPLIANCE[eModeMax] = {
-        //  0.0f,            // Miles Macklin's blog (http://blog.mmacklin.com/2016/10/12/xpbd-slides-and-stiffness/)
-        //  0.00000000004f, // 0.04 x 10^(-9) (M^2/N) Concrete
-        //  0.00000000016f, // 0.16 x 10^(-9) (M^2/N) Wood
-        //  0.000000001f,   // 1.0  x 10^(-8) (M^2/N) Leather
-        //  0.000000002f,   // 0.2  x 10^(-7) (M^2/N) Tendon
-        //  0.0000001f,     // 1.0  x 10^(-6) (M^2/N) Rubber
-        //  0.00002f,       // 0.2  x 10^(-3) (M^2/N) Muscle
-        //  0.0001f,        // 1.0  x 10^(-3) (M^2/N) Fat
-        //};
-        let uniform_buf = BufferObj::create_uniform_buffer(
+        let particle_x_num = config.particle_x_num;
+        let particle_y_num = config.particle_y_num;
+        let uniform_buf = UniformBuffer::new(
             &app_view.device,
-            &ClothUniform {
-                num_x: particle_x_num as i32,
-                num_y: particle_y_num as i32,
-                triangle_num: 0,
-                compliance: 0.00000000016 / (0.016 * 0.016),
-                dt: 0.016,
-            },
+            config.to_uniform(((particle_x_num - 1) * (particle_y_num - 1) * 2) as i32),
             Some("cloth uniform"),
         );
+        let (pixel_width, pixel_height) = config.pixel_size(app_view.config.width as f32);
         let (
             (_tl_x, tl_y),
             particles,
@@ -84,19 +112,18 @@ impl Cloth {
         ) = generate_cloth_particles(
             particle_x_num as usize,
             particle_y_num as usize,
-            app_view.config.width as f32,
-            app_view.config.width as f32 / 1863.0 * 3312.0,
+            pixel_width,
+            pixel_height,
             factor.0 / viewport_size.width,
+            config.areal_density,
         );
         // dynamit uniform
         let dynamic_offset =
             app_view.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
-        let stretch_coloring_buf = BufferObj::create_empty_dynamic_uniform_buffer(
-            &app_view.device,
-            stretch_mesh_coloring.len() as u64 * dynamic_offset,
-            None,
-            Some("stretch_coloring_buf"),
-        );
+        // 撕裂后重新着色的分组数可能会多于初始分组，超出时再扩容
+        let stretch_coloring_capacity = stretch_mesh_coloring.len() + 4;
+        let stretch_coloring_buf =
+            Self::create_stretch_coloring_buf(&app_view.device, stretch_coloring_capacity);
         let mut offset = 0;
         for mc in stretch_mesh_coloring.iter() {
             app_view.queue.write_buffer(
@@ -118,18 +145,22 @@ impl Cloth {
             app_view.queue.write_buffer(
                 &bend_coloring_buf.buffer,
                 offset,
-                mc.get_push_constants_data().as_bytes(),
+                mc.get_bending_dynamic_uniform(0).as_bytes(),
             );
             offset += dynamic_offset;
         }
         let particle_buf =
             BufferObj::create_storage_buffer(&app_view.device, &particles, Some("particle buf"));
 
-        let constraint_buf = BufferObj::create_storage_buffer(
+        // 撕裂检查需要读回约束
+        let constraint_buf = BufferObj::create_buffer(
             &app_view.device,
-            &constraints,
+            Some(&constraints),
+            None,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
             Some("constraint_buf"),
         );
+        let tear_readback = TearReadback::new(&app_view.device, &constraint_buf);
 
         let group_constraints_buf = BufferObj::create_storage_buffer(
             &app_view.device,
@@ -159,65 +190,46 @@ impl Cloth {
         let predict_and_reset = ComputeNode::new(
             &app_view.device,
             (((particle_x_num * particle_y_num + 31) as f32 / 32.0).floor() as u32, 1, 1),
-            vec![&uniform_buf],
+            vec![&uniform_buf.buffer],
             vec![&particle_buf, &constraint_buf, &group_constraints_buf, &reorder_constraints_buf],
             vec![],
             &predict_and_reset_shader,
         );
 
-        let constraint_solver_shader = crate::util::shader::create_shader_module(
-            &app_view.device,
-            "pbd/cloth_stretch_solver",
-            None,
-        );
-        let stretch_solver = ComputeNode::new_with_dynamic_uniforms(
+        let stretch_solver = Self::create_stretch_solver(
             &app_view.device,
-            (0, 0, 0),
-            vec![&uniform_buf],
-            vec![(&stretch_coloring_buf)],
-            vec![&particle_buf, &constraint_buf, &group_constraints_buf, &reorder_constraints_buf],
-            vec![],
-            &constraint_solver_shader,
+            &uniform_buf.buffer,
+            &stretch_coloring_buf,
+            &particle_buf,
+            &constraint_buf,
+            &group_constraints_buf,
+            &reorder_constraints_buf,
         );
 
+        // 与 ClothX 共用三角形弯曲约束的求解器
         let bend_solver_shader = crate::util::shader::create_shader_module(
             &app_view.device,
-            "pbd/cloth_bend_solver",
+            "pbd/xxpbd/cloth_bending_solver",
             None,
         );
-        let size = particle_x_num * particle_y_num * 4 * 16;
-        let debug_buf = BufferObj::create_empty_storage_buffer(
-            &app_view.device,
-            size as wgpu::BufferAddress,
-            false,
-            Some("debug_buf"),
-        );
         let bend_solver = ComputeNode::new_with_dynamic_uniforms(
             &app_view.device,
             (0, 0, 0),
-            vec![&uniform_buf],
+            vec![&uniform_buf.buffer],
             vec![&bend_coloring_buf],
-            vec![&particle_buf, &bend_constraints_buf, &reorder_bendings_buf, &debug_buf],
+            vec![&particle_buf, &bend_constraints_buf, &reorder_bendings_buf],
             vec![],
             &bend_solver_shader,
         );
 
         let mut vertex_data: Vec<PosParticleIndex> = Vec::new();
-        let mut index_data: Vec<u32> = Vec::new();
         // 按行遍历
         for h in 0..particle_y_num {
             for w in 0..particle_x_num {
                 vertex_data.push(PosParticleIndex::new([w, h, 0]));
-                if h > 0 && w > 0 {
-                    let current: u32 = particle_x_num * h + w;
-                    // 找到上一行同一行位置的索引
-                    let top: u32 = current - particle_x_num;
-                    let mut lines: Vec<u32> =
-                        vec![current, top, top - 1, current, top - 1, current - 1];
-                    index_data.append(&mut lines);
-                }
             }
         }
+        let index_data = cloth_triangle_indices(particle_x_num, particle_y_num, &HashSet::new());
         // 1863*3312
         // let img_path = PathBuf::from(&base_path).join("assets/paper/3.png");
 
@@ -231,8 +243,9 @@ impl Cloth {
             crate::util::shader::create_shader_module(&app_view.device, "pbd/cloth_display", None);
         let display_node_builder =
             ViewNodeBuilder::<PosParticleIndex>::new(vec![(&texture, None)], &display_shader)
-                .with_uniform_buffers(vec![&mvp_buf, &uniform_buf])
+                .with_uniform_buffers(vec![&mvp_buf, &uniform_buf.buffer])
                 .with_storage_buffers(vec![&particle_buf])
+                .with_color_format(app_view.config.format)
                 .with_use_depth_stencil(true)
                 .with_cull_mode(None)
                 .with_shader_stages(vec![
@@ -276,11 +289,42 @@ impl Cloth {
         let depth_texture_view =
             crate::util::depth_stencil::create_depth_texture_view(size, &app_view.device);
 
+        let particle_spacing = {
+            let (p0, p1) = (particles[0].pos, particles[1].pos);
+            ((p0[0] - p1[0]).powi(2) + (p0[1] - p1[1]).powi(2) + (p0[2] - p1[2]).powi(2)).sqrt()
+        };
+        let collision = ClothCollision::new(
+            &app_view.device,
+            &uniform_buf,
+            &particle_buf,
+            &particles,
+            particle_spacing,
+        );
+        let picker = ClothPicker::new(
+            &app_view.device,
+            viewport_size,
+            proj_mat * mv_mat,
+            &uniform_buf.buffer,
+            &particle_buf,
+            particle_x_num * particle_y_num,
+        );
+
         let instance = Self {
+            config,
+            particle_x_num,
+            particle_y_num,
+            half_height,
+            uniform_buf,
             particle_buf,
             constraint_buf,
             group_constraints_buf,
+            reorder_constraints_buf,
+            stretch_coloring_buf,
+            constraints,
+            reorder_constraints,
+            bend_constraints,
             stretch_mesh_coloring,
+            stretch_coloring_capacity,
             bend_mesh_coloring,
             bend_constraints_buf,
             predict_and_reset,
@@ -289,6 +333,11 @@ impl Cloth {
             display_node,
             depth_texture_view,
             frame_count: 0,
+            picker,
+            touch_start: None,
+            is_dragging: false,
+            tear_readback,
+            collision,
         };
 
         // instance.step_solver(&mut encoder);
@@ -297,69 +346,285 @@ impl Cloth {
         instance
     }
 
-    fn step_solver(&mut self, encoder: &mut wgpu::CommandEncoder) {
+    // shader 热重载：重建用到了 changed 中 shader 的节点
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_predict_and_reset") {
+            self.predict_and_reset.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_stretch_solver") {
+            self.stretch_solver.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/xxpbd/cloth_bending_solver") {
+            self.bend_solver.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_display") {
+            self.display_node.reload(device, &shader);
+        }
+        self.picker.reload_shaders(device, changed);
+        self.collision.reload_shaders(device, changed);
+    }
+
+    pub fn half_height(&self) -> f32 {
+        self.half_height
+    }
+
+    pub fn config(&self) -> &ClothConfig {
+        &self.config
+    }
+
+    // 运行时修改材料、阻尼、子步、重力等参数
+    // 粒子数、尺寸与面密度决定了粒子与约束的初始数据，修改它们需要重新创建 Cloth
+    pub fn set_config(&mut self, queue: &wgpu::Queue, config: ClothConfig) {
+        self.config = ClothConfig {
+            particle_x_num: self.config.particle_x_num,
+            particle_y_num: self.config.particle_y_num,
+            size: self.config.size,
+            areal_density: self.config.areal_density,
+            ..config
+        };
+        let triangle_num = ((self.particle_x_num - 1) * (self.particle_y_num - 1) * 2) as i32;
+        let uniform = self.config.to_uniform(triangle_num);
+        self.uniform_buf.update(queue, |data| *data = uniform);
+    }
+
+    pub fn touch(&mut self, touch: Touch) {
+        match touch.phase {
+            TouchPhase::Started => {
+                self.picker.grab(touch.position);
+                self.touch_start = Some(touch.position);
+                self.is_dragging = false;
+            }
+            TouchPhase::Moved => {
+                if let Some(start) = self.touch_start {
+                    let offset = touch.position.minus(&start);
+                    if offset.x.abs() + offset.y.abs() > 8.0 {
+                        self.is_dragging = true;
+                    }
+                }
+                self.picker.drag(touch.position);
+            }
+            _ => {
+                // 轻点切换粒子的固定状态
+                if self.touch_start.is_some() && !self.is_dragging {
+                    self.picker.toggle_pin(touch.position);
+                } else {
+                    self.picker.release();
+                }
+                self.touch_start = None;
+                self.is_dragging = false;
+            }
+        }
+    }
+
+    // profiler 正在计时时，预测、每个着色分组与碰撞各自放在单独的 compute pass 中
+    pub fn step_solver(
+        &mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder,
+        profiler: Option<&mut GpuProfiler>,
+    ) {
         // if self.frame_count >= 1 {
         //     return;
         // }
-        // 重用 cpass 在 macOS 上不能提升性能， 但是在 iOS 上提升明显
-        // 64*64，8 约束，迭代20 ：Xs Max, 12ms -> 8ms
-        let mut cpass =
-            encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("solver pass") });
-        self.predict_and_reset.dispatch(&mut cpass);
-
-        let dynamic_offset = 256;
-        for _ in 0..25 {
-            cpass.set_pipeline(&self.stretch_solver.pipeline);
-            cpass.set_bind_group(0, &self.stretch_solver.bg_setting.bind_group, &[]);
-            let mut index = 0;
-            for mc in self.stretch_mesh_coloring.iter() {
-                if let Some(bg) = &self.stretch_solver.dy_uniform_bg {
-                    cpass.set_bind_group(1, &bg.bind_group, &[index * dynamic_offset]);
+        self.picker.update_uniform(queue);
+        match profiler {
+            Some(profiler) if profiler.is_timing() => {
+                for substep in 0..self.config.substeps {
+                    profiler.compute_scope(encoder, "cloth predict", self, |cloth, cpass| {
+                        cloth.dispatch_predict(cpass, substep)
+                    });
+                    for _ in 0..self.config.iterations {
+                        for index in 0..self.stretch_mesh_coloring.len() {
+                            let label =
+                                STRETCH_GROUP_LABELS[index.min(STRETCH_GROUP_LABELS.len() - 1)];
+                            profiler.compute_scope(encoder, label, self, |cloth, cpass| {
+                                cloth.dispatch_stretch_group(cpass, index)
+                            });
+                        }
+                        profiler.compute_scope(encoder, "cloth bend", self, |cloth, cpass| {
+                            cloth.dispatch_bend(cpass)
+                        });
+                        profiler.compute_scope(encoder, "cloth collision", self, |cloth, cpass| {
+                            cloth.collision.dispatch(cpass)
+                        });
+                    }
+                }
+            }
+            _ => {
+                // 重用 cpass 在 macOS 上不能提升性能， 但是在 iOS 上提升明显
+                // 64*64，8 约束，迭代20 ：Xs Max, 12ms -> 8ms
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("solver pass"),
+                });
+                for substep in 0..self.config.substeps {
+                    self.dispatch_predict(&mut cpass, substep);
+                    for _ in 0..self.config.iterations {
+                        for index in 0..self.stretch_mesh_coloring.len() {
+                            self.dispatch_stretch_group(&mut cpass, index);
+                        }
+                        self.dispatch_bend(&mut cpass);
+                        self.collision.dispatch(&mut cpass);
+                    }
                 }
-                cpass.dispatch_workgroups(mc.thread_group.0, mc.thread_group.1, 1);
-                index += 1;
             }
-
-            // cpass.set_pipeline(&self.bend_solver.pipeline);
-            // cpass.set_bind_group(0, &self.bend_solver.bg_setting.bind_group, &[]);
-            // index = 0;
-            // for mc in self.bend_mesh_coloring.iter() {
-            //     if let Some(bg) = &self.bend_solver.dy_uniform_bg {
-            //         cpass.set_bind_group(1, &bg.bind_group, &[index * dynamic_offset]);
-            //     }
-            //     cpass.dispatch_workgroups(mc.thread_group.0, mc.thread_group.1, 1);
-            //     index += 1;
-            // }
         }
 
+        if self.frame_count % TEAR_CHECK_INTERVAL == 0 {
+            self.tear_readback.copy_constraints(encoder, &self.constraint_buf);
+        }
         self.frame_count += 1;
     }
 
+    fn dispatch_predict<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>, substep: u32) {
+        self.predict_and_reset.dispatch(cpass);
+        // 抓取的动作每帧只需执行一次
+        if substep == 0 {
+            self.picker.dispatch(cpass);
+        }
+        self.collision.build_bins(cpass);
+    }
+
+    // 同一着色分组里的约束没有共用的粒子，可以并行求解
+    fn dispatch_stretch_group<'a, 'b: 'a>(
+        &'b self, cpass: &mut wgpu::ComputePass<'a>, index: usize,
+    ) {
+        let mc = &self.stretch_mesh_coloring[index];
+        cpass.set_pipeline(&self.stretch_solver.pipeline);
+        cpass.set_bind_group(0, &self.stretch_solver.bg_setting.bind_group, &[]);
+        if let Some(bg) = &self.stretch_solver.dy_uniform_bg {
+            cpass.set_bind_group(1, &bg.bind_group, &[index as wgpu::DynamicOffset * 256]);
+        }
+        cpass.dispatch_workgroups(mc.thread_group.0, mc.thread_group.1, 1);
+    }
+
+    // 弯曲约束按着色分组依次求解
+    fn dispatch_bend<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        cpass.set_pipeline(&self.bend_solver.pipeline);
+        cpass.set_bind_group(0, &self.bend_solver.bg_setting.bind_group, &[]);
+        for (index, mc) in self.bend_mesh_coloring.iter().enumerate() {
+            if let Some(bg) = &self.bend_solver.dy_uniform_bg {
+                cpass.set_bind_group(1, &bg.bind_group, &[index as wgpu::DynamicOffset * 256]);
+            }
+            cpass.dispatch_workgroups(mc.thread_group.0, mc.thread_group.1, 1);
+        }
+    }
+
+    fn create_stretch_coloring_buf(device: &wgpu::Device, capacity: usize) -> BufferObj {
+        let dynamic_offset =
+            device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        BufferObj::create_empty_dynamic_uniform_buffer(
+            device,
+            capacity as u64 * dynamic_offset,
+            None,
+            Some("stretch_coloring_buf"),
+        )
+    }
+
+    fn create_stretch_solver(
+        device: &wgpu::Device, uniform_buf: &BufferObj, stretch_coloring_buf: &BufferObj,
+        particle_buf: &BufferObj, constraint_buf: &BufferObj, group_constraints_buf: &BufferObj,
+        reorder_constraints_buf: &BufferObj,
+    ) -> ComputeNode {
+        let shader =
+            crate::util::shader::create_shader_module(device, "pbd/cloth_stretch_solver", None);
+        ComputeNode::new_with_dynamic_uniforms(
+            device,
+            (0, 0, 0),
+            vec![uniform_buf],
+            vec![stretch_coloring_buf],
+            vec![particle_buf, constraint_buf, group_constraints_buf, reorder_constraints_buf],
+            vec![],
+            &shader,
+        )
+    }
+
+    // 提交 step_solver 所在的 encoder 之后调用
+    pub fn check_tearing(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.tear_readback.map_async();
+        let broken = match self.tear_readback.poll(device) {
+            Some(broken) => broken,
+            None => return,
+        };
+        let torn = torn_pairs(&self.constraints, &broken);
+        // 不再绘制撕裂处的三角形
+        let indices = cloth_triangle_indices(self.particle_x_num, self.particle_y_num, &torn);
+        self.display_node.update_indices(queue, &indices);
+        // 移除跨过撕裂处的弯曲约束
+        let removed = BendingConstraintObj { v: -1, b0: -1, b1: -1, h0: 0.0 };
+        let bending_size = std::mem::size_of::<BendingConstraintObj>() as wgpu::BufferAddress;
+        for (index, bending) in self.bend_constraints.iter_mut().enumerate() {
+            if bending.v >= 0 && is_bending_torn(bending, &torn) {
+                *bending = removed;
+                queue.write_buffer(
+                    &self.bend_constraints_buf.buffer,
+                    index as wgpu::BufferAddress * bending_size,
+                    removed.as_bytes(),
+                );
+            }
+        }
+
+        let (mesh_coloring, reorder_constraints) =
+            regroup_torn_constraints(&self.constraints, &self.reorder_constraints, &broken);
+        if mesh_coloring.len() > self.stretch_coloring_capacity {
+            self.stretch_coloring_capacity = mesh_coloring.len() + 4;
+            self.stretch_coloring_buf =
+                Self::create_stretch_coloring_buf(device, self.stretch_coloring_capacity);
+            self.stretch_solver = Self::create_stretch_solver(
+                device,
+                &self.uniform_buf.buffer,
+                &self.stretch_coloring_buf,
+                &self.particle_buf,
+                &self.constraint_buf,
+                &self.group_constraints_buf,
+                &self.reorder_constraints_buf,
+            );
+        }
+        let dynamic_offset =
+            device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let mut offset = 0;
+        for mc in mesh_coloring.iter() {
+            queue.write_buffer(
+                &self.stretch_coloring_buf.buffer,
+                offset,
+                mc.get_push_constants_data().as_bytes(),
+            );
+            offset += dynamic_offset;
+        }
+        queue.write_buffer(&self.reorder_constraints_buf.buffer, 0, reorder_constraints.as_bytes());
+        self.reorder_constraints = reorder_constraints;
+        self.stretch_mesh_coloring = mesh_coloring;
+    }
+
     pub fn enter_frame(&mut self, app_view: &mut AppSurface) {
         let mut encoder = app_view.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("cloth encoder"),
         });
-        self.step_solver(&mut encoder);
+        self.step_solver(&app_view.queue, &mut encoder, None);
         let (frame, frame_view) = app_view.get_current_frame_view();
-        {
-            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("cloth render pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &frame_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(crate::util::utils::alpha_color()),
-                        store: true,
-                    },
-                })],
-                // depth_stencil_attachment: None,
-                depth_stencil_attachment: Some(
-                    crate::util::utils::depth_stencil::create_attachment(&self.depth_texture_view),
-                ),
-            });
-            self.display_node.draw_render_pass(&mut rpass);
-        }
+        self.draw_render_pass(
+            &mut encoder,
+            &frame_view,
+            wgpu::LoadOp::Clear(crate::util::utils::alpha_color()),
+        );
         app_view.queue.submit(Some(encoder.finish()));
         frame.present();
+        self.check_tearing(&app_view.device, &app_view.queue);
+    }
+
+    pub fn draw_render_pass(
+        &self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView,
+        load_op: wgpu::LoadOp<wgpu::Color>,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("cloth render pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: load_op, store: true },
+            })],
+            // depth_stencil_attachment: None,
+            depth_stencil_attachment: Some(crate::util::utils::depth_stencil::create_attachment(
+                &self.depth_texture_view,
+            )),
+        });
+        self.display_node.draw_render_pass(&mut rpass);
     }
 }
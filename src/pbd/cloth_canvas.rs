@@ -0,0 +1,73 @@
+use super::{Cloth, ClothConfig, ColliderObj, MeshCloth, ObjMesh};
+use crate::util::resource_pool;
+
+use app_surface::{AppSurface, SurfaceFrame, Touch};
+
+// 可抓取、固定、撕裂的布料
+pub struct ClothCanvas {
+    pub app_view: AppSurface,
+    pub cloth: Cloth,
+}
+
+impl ClothCanvas {
+    pub fn new(app_view: AppSurface) -> Self {
+        // 池里的资源属于之前的 device
+        resource_pool::clear();
+        let mut cloth = Cloth::new(&app_view, ClothConfig::default());
+        // 布料下边缘前方的球与更下方的地面，撕下的布片会搭在球上或堆在地面上
+        let half_height = cloth.half_height();
+        let colliders = [
+            ColliderObj::sphere([0.0, -half_height, half_height * 0.4], half_height * 0.3),
+            ColliderObj::plane([0.0, -half_height * 1.5, 0.0], [0.0, 1.0, 0.0]).with_friction(0.6),
+        ];
+        cloth.collision.set_colliders(&app_view.queue, &colliders);
+        Self { app_view, cloth }
+    }
+
+    // 自碰撞默认开启
+    pub fn set_self_collision(&mut self, enabled: bool) {
+        self.cloth.collision.set_self_collision(&self.app_view.queue, enabled);
+    }
+}
+
+impl SurfaceFrame for ClothCanvas {
+    fn resize_surface(&mut self) {}
+
+    fn touch(&mut self, touch: Touch) {
+        self.cloth.touch(touch);
+    }
+
+    fn enter_frame(&mut self) {
+        let changed = crate::util::changed_shaders();
+        if !changed.is_empty() {
+            self.cloth.reload_shaders(&self.app_view.device, &changed);
+        }
+        self.cloth.enter_frame(&mut self.app_view);
+    }
+}
+
+// 由 OBJ 网格生成的布料
+pub struct MeshClothCanvas {
+    pub app_view: AppSurface,
+    pub cloth: MeshCloth,
+}
+
+impl MeshClothCanvas {
+    // pinned 为固定粒子的顶点索引
+    pub fn new(app_view: AppSurface, mesh: &ObjMesh, pinned: &[u32]) -> Self {
+        // 池里的资源属于之前的 device
+        resource_pool::clear();
+        let cloth = MeshCloth::new(&app_view, mesh, pinned, ClothConfig::small_steps());
+        Self { app_view, cloth }
+    }
+}
+
+impl SurfaceFrame for MeshClothCanvas {
+    fn resize_surface(&mut self) {}
+
+    fn touch(&mut self, _touch: Touch) {}
+
+    fn enter_frame(&mut self) {
+        self.cloth.enter_frame(&mut self.app_view);
+    }
+}
@@ -0,0 +1,298 @@
+use super::{particle::ParticleBufferObj, BinUniform, ClothUniform};
+use crate::util::node::{Bindings, ComputeNode};
+use crate::util::shader::reload_shader;
+use crate::util::{BufferObj, ShaderReflection, UniformBuffer};
+use zerocopy::{AsBytes, FromBytes};
+
+// 碰撞体缓冲区能容纳的最大数量
+pub const MAX_COLLIDERS: usize = 16;
+
+const COLLIDER_SPHERE: i32 = 0;
+const COLLIDER_CAPSULE: i32 = 1;
+const COLLIDER_BOX: i32 = 2;
+const COLLIDER_PLANE: i32 = 3;
+
+// 解析形式的碰撞体
+#[repr(C)]
+#[derive(Copy, Clone, AsBytes, FromBytes)]
+pub struct ColliderObj {
+    ty: i32,
+    pub friction: f32,
+    radius: f32,
+    padding: f32,
+    // sphere: 球心，capsule: 端点 a，box: 中心，plane: 平面上的一点
+    p0: [f32; 4],
+    // capsule: 端点 b，box: 半边长，plane: 法线
+    p1: [f32; 4],
+}
+
+impl ColliderObj {
+    pub fn sphere(center: [f32; 3], radius: f32) -> Self {
+        Self::new(COLLIDER_SPHERE, radius, center, [0.0; 3])
+    }
+
+    // 两个端点之间的线段加上半径
+    pub fn capsule(a: [f32; 3], b: [f32; 3], radius: f32) -> Self {
+        Self::new(COLLIDER_CAPSULE, radius, a, b)
+    }
+
+    // 轴对齐的盒子
+    pub fn aabb(center: [f32; 3], half_size: [f32; 3]) -> Self {
+        Self::new(COLLIDER_BOX, 0.0, center, half_size)
+    }
+
+    pub fn plane(point: [f32; 3], normal: [f32; 3]) -> Self {
+        Self::new(COLLIDER_PLANE, 0.0, point, normal)
+    }
+
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = friction;
+        self
+    }
+
+    fn new(ty: i32, radius: f32, p0: [f32; 3], p1: [f32; 3]) -> Self {
+        Self {
+            ty,
+            friction: 0.2,
+            radius,
+            padding: 0.0,
+            p0: [p0[0], p0[1], p0[2], 1.0],
+            p1: [p1[0], p1[1], p1[2], 0.0],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, AsBytes, FromBytes)]
+pub struct CollisionUniform {
+    collider_count: i32,
+    // 粒子与碰撞体表面，粒子与粒子之间保持的最小距离
+    pub thickness: f32,
+    pub self_collision: i32,
+    padding: f32,
+}
+crate::util::uniform_layout!(CollisionUniform { collider_count, thickness, self_collision });
+
+// 布料与解析碰撞体及布料自身的碰撞
+pub struct ClothCollision {
+    uniform_buf: UniformBuffer<CollisionUniform>,
+    collider_buf: BufferObj,
+    clear_node: ComputeNode,
+    insert_node: ComputeNode,
+    collider_node: ComputeNode,
+    self_collision_node: ComputeNode,
+    self_collision_apply_node: ComputeNode,
+}
+
+impl ClothCollision {
+    pub fn new(
+        device: &wgpu::Device, cloth_uniform_buf: &UniformBuffer<ClothUniform>,
+        particle_buf: &BufferObj, particles: &[ParticleBufferObj], particle_spacing: f32,
+    ) -> Self {
+        let particle_count = particles.len() as u32;
+        // 厚度小于粒子间距，碰撞距离内的粒子都在相邻的格子里
+        // 初始距离小于厚度的粒子对（如折叠的层）由 shader 按初始距离处理
+        let uniform_data = CollisionUniform {
+            collider_count: 0,
+            thickness: particle_spacing * 0.6,
+            self_collision: 1,
+            padding: 0.0,
+        };
+        let uniform_buf = UniformBuffer::new(device, uniform_data, Some("collision uniform"));
+        let collider_buf = BufferObj::create_empty_storage_buffer(
+            device,
+            (std::mem::size_of::<ColliderObj>() * MAX_COLLIDERS) as wgpu::BufferAddress,
+            false,
+            Some("collider buf"),
+        );
+
+        let bin_uniform = cal_bin_uniform(particles, particle_spacing);
+        let bin_uniform_buf = UniformBuffer::new(device, bin_uniform, Some("bin uniform"));
+        let bin_count_buf = BufferObj::create_empty_storage_buffer(
+            device,
+            bin_uniform.bin_num[3] as wgpu::BufferAddress * 4,
+            false,
+            Some("bin count buf"),
+        );
+        let bin_buf = BufferObj::create_empty_storage_buffer(
+            device,
+            (bin_uniform.bin_num[3] * bin_uniform.max_bin_count) as wgpu::BufferAddress * 4,
+            false,
+            Some("bin buf"),
+        );
+
+        let clear_shader = crate::util::shader::create_shader_module(
+            device,
+            "pbd/cloth_bin_clear",
+            Some("cloth_bin_clear"),
+        );
+        let clear_node = ComputeNode::with_bindings(
+            device,
+            ((bin_uniform.bin_num[3] as u32 + 63) / 64, 1, 1),
+            &ShaderReflection::new("pbd/cloth_bin_clear", None, &[]),
+            Bindings::new().uniform("bin", &bin_uniform_buf).buffer("bin_counts", &bin_count_buf),
+            &clear_shader,
+        );
+        let insert_shader = crate::util::shader::create_shader_module(
+            device,
+            "pbd/cloth_bin_insert",
+            Some("cloth_bin_insert"),
+        );
+        let insert_node = ComputeNode::with_bindings(
+            device,
+            ((particle_count + 31) / 32, 1, 1),
+            &ShaderReflection::new("pbd/cloth_bin_insert", None, &[]),
+            Bindings::new()
+                .uniform("bin", &bin_uniform_buf)
+                .buffer("particles", particle_buf)
+                .buffer("bin_counts", &bin_count_buf)
+                .buffer("bins", &bin_buf),
+            &insert_shader,
+        );
+        let collider_shader = crate::util::shader::create_shader_module(
+            device,
+            "pbd/cloth_collision",
+            Some("cloth_collision"),
+        );
+        let collider_node = ComputeNode::with_bindings(
+            device,
+            ((particle_count + 31) / 32, 1, 1),
+            &ShaderReflection::new("pbd/cloth_collision", None, &[]),
+            Bindings::new()
+                .uniform("cloth", cloth_uniform_buf)
+                .uniform("collision", &uniform_buf)
+                .buffer("particles", particle_buf)
+                .buffer("colliders", &collider_buf),
+            &collider_shader,
+        );
+        let self_collision_shader = crate::util::shader::create_shader_module(
+            device,
+            "pbd/cloth_self_collision",
+            Some("cloth_self_collision"),
+        );
+        // 初始位置作为自碰撞的参考距离
+        let rest_pos: Vec<[f32; 4]> = particles.iter().map(|p| p.pos).collect();
+        let rest_pos_buf =
+            BufferObj::create_storage_buffer(device, &rest_pos, Some("rest pos buf"));
+        let correction_buf = BufferObj::create_empty_storage_buffer(
+            device,
+            particle_count as wgpu::BufferAddress * 16,
+            false,
+            Some("self collision correction buf"),
+        );
+        let self_collision_node = ComputeNode::with_bindings(
+            device,
+            ((particle_count + 31) / 32, 1, 1),
+            &ShaderReflection::new("pbd/cloth_self_collision", None, &[]),
+            Bindings::new()
+                .uniform("bin", &bin_uniform_buf)
+                .uniform("collision", &uniform_buf)
+                .buffer("particles", particle_buf)
+                .buffer("rest_pos", &rest_pos_buf)
+                .buffer("bin_counts", &bin_count_buf)
+                .buffer("bins", &bin_buf)
+                .buffer("corrections", &correction_buf),
+            &self_collision_shader,
+        );
+        let self_collision_apply_shader = crate::util::shader::create_shader_module(
+            device,
+            "pbd/cloth_self_collision_apply",
+            Some("cloth_self_collision_apply"),
+        );
+        let self_collision_apply_node = ComputeNode::with_bindings(
+            device,
+            ((particle_count + 31) / 32, 1, 1),
+            &ShaderReflection::new("pbd/cloth_self_collision_apply", None, &[]),
+            Bindings::new()
+                .buffer("particles", particle_buf)
+                .buffer("corrections", &correction_buf),
+            &self_collision_apply_shader,
+        );
+
+        Self {
+            uniform_buf,
+            collider_buf,
+            clear_node,
+            insert_node,
+            collider_node,
+            self_collision_node,
+            self_collision_apply_node,
+        }
+    }
+
+    // shader 热重载：重建用到了 changed 中 shader 的节点
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_bin_clear") {
+            self.clear_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_bin_insert") {
+            self.insert_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_collision") {
+            self.collider_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_self_collision") {
+            self.self_collision_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_self_collision_apply") {
+            self.self_collision_apply_node.reload(device, &shader);
+        }
+    }
+
+    // 替换全部碰撞体，超出 MAX_COLLIDERS 的部分会被忽略
+    pub fn set_colliders(&mut self, queue: &wgpu::Queue, colliders: &[ColliderObj]) {
+        let count = colliders.len().min(MAX_COLLIDERS);
+        if count > 0 {
+            queue.write_buffer(&self.collider_buf.buffer, 0, colliders[..count].as_bytes());
+        }
+        self.uniform_buf.update(queue, |uniform| uniform.collider_count = count as i32);
+    }
+
+    pub fn set_self_collision(&mut self, queue: &wgpu::Queue, enabled: bool) {
+        self.uniform_buf.update(queue, |uniform| uniform.self_collision = enabled as i32);
+    }
+
+    // 预测位置之后调用：更新空间 hash
+    pub fn build_bins<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        if self.uniform_buf.data().self_collision == 0 {
+            return;
+        }
+        self.clear_node.dispatch(cpass);
+        self.insert_node.dispatch(cpass);
+    }
+
+    // 每次约束迭代之后调用
+    pub fn dispatch<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        if self.uniform_buf.data().self_collision != 0 {
+            // 先算出全部修正量再施加
+            self.self_collision_node.dispatch(cpass);
+            self.self_collision_apply_node.dispatch(cpass);
+        }
+        if self.uniform_buf.data().collider_count > 0 {
+            self.collider_node.dispatch(cpass);
+        }
+    }
+}
+
+// 格子边长等于粒子间距，碰撞距离内的粒子一定落在相邻的 27 个格子里
+fn cal_bin_uniform(particles: &[ParticleBufferObj], particle_spacing: f32) -> BinUniform {
+    let mut extent = 0.0_f32;
+    for p in particles.iter() {
+        for i in 0..3 {
+            extent = extent.max(p.pos[i].abs());
+        }
+    }
+    // 布料运动范围按初始范围的 2 倍估算，超出的粒子会被放进边界格子
+    let half_size = extent * 2.0;
+    let axis_num = (half_size * 2.0 / particle_spacing).ceil() as i32;
+    // hash 容器数取粒子数的 2 倍
+    let hash_num = (particles.len() * 2).next_power_of_two() as i32;
+    BinUniform {
+        bin_num: [axis_num, axis_num, axis_num, hash_num],
+        bin_max_index: [axis_num - 1, axis_num - 1, axis_num - 1, 0],
+        bin_size: [particle_spacing, particle_spacing, particle_spacing, 0.0],
+        pos_offset: [half_size, half_size, half_size, 0.0],
+        max_bin_count: 16,
+        padding: [0.0; 3],
+    }
+}
@@ -0,0 +1,149 @@
+use super::ClothUniform;
+
+// 材料的 XPBD 柔度（compliance，单位 M^2/N），柔度越小越硬
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Compliance {
+    pub stretch: f32,
+    // Cloth 与 ClothX 中是三角形弯曲约束，MeshCloth 中是二面角约束
+    pub bend: f32,
+    pub shear: f32,
+}
+
+// 材料预设
+// 拉伸柔度取自 http://blog.mmacklin.com/2016/10/12/xpbd-slides-and-stiffness/ 里的表，
+// 弯曲与剪切柔度按经验取拉伸柔度的倍数
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ClothMaterial {
+    // 完全刚性
+    Rigid,
+    Concrete,
+    Wood,
+    Leather,
+    Tendon,
+    Rubber,
+    Muscle,
+    Fat,
+    // 不易拉伸，但极易弯曲
+    Silk,
+    Custom(Compliance),
+}
+
+impl ClothMaterial {
+    pub fn compliance(&self) -> Compliance {
+        let stretch = match self {
+            ClothMaterial::Rigid => 0.0,
+            ClothMaterial::Concrete => 0.00000000004,
+            ClothMaterial::Wood => 0.00000000016,
+            ClothMaterial::Leather => 0.000000001,
+            ClothMaterial::Tendon => 0.000000002,
+            ClothMaterial::Rubber => 0.0000001,
+            ClothMaterial::Muscle => 0.00002,
+            ClothMaterial::Fat => 0.0001,
+            ClothMaterial::Silk => {
+                return Compliance { stretch: 0.00000000016, bend: 0.0001, shear: 0.000000016 }
+            }
+            ClothMaterial::Custom(compliance) => return *compliance,
+        };
+        Compliance { stretch, bend: stretch * 1000.0, shear: stretch * 10.0 }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ClothConfig {
+    // 粒子个数
+    pub particle_x_num: u32,
+    pub particle_y_num: u32,
+    // 布料的宽高（像素），为 None 时宽度撑满视口，高度按贴图比例计算
+    pub size: Option<(f32, f32)>,
+    // 单位面积质量，长度单位为布料空间；为 None 时内部粒子的 invert_mass 固定为 0.1
+    pub areal_density: Option<f32>,
+    pub material: ClothMaterial,
+    // 速度阻尼 [0, 1]
+    pub damping: f32,
+    // 每帧的子步数，每个子步都会重新预测位置
+    pub substeps: u32,
+    // 每个子步里约束的迭代次数
+    pub iterations: u32,
+    pub frame_dt: f32,
+    // Cloth 中是加速度，ClothX 与 MeshCloth 中是力
+    pub gravity: [f32; 3],
+    // 拉伸超过 rest_length * (1 + tear_threshold) 的约束会被撕裂，<= 0 时不撕裂
+    pub tear_threshold: f32,
+}
+
+impl Default for ClothConfig {
+    fn default() -> Self {
+        Self {
+            // （32， 64） 这个组合，约束分组后为 9 组，且没有极小数据量的分组
+            particle_x_num: 32,
+            particle_y_num: 58,
+            size: None,
+            areal_density: None,
+            material: ClothMaterial::Wood,
+            damping: 0.0,
+            substeps: 1,
+            iterations: 25,
+            frame_dt: 0.016,
+            // 重力加速度不能太小，会导致布料飘来飘去，没有重量感
+            gravity: [0.0, -3.98, 0.0],
+            tear_threshold: 0.35,
+        }
+    }
+}
+
+impl ClothConfig {
+    // 小步长 XPBD：多个子步，每个子步只迭代一次，ClothX 与 MeshCloth 使用
+    // 它们的预测 shader 把 gravity 当作力，乘以粒子的 invert_mass
+    pub fn small_steps() -> Self {
+        Self {
+            material: ClothMaterial::Custom(Compliance {
+                stretch: 0.0000000016,
+                bend: 0.0000016,
+                shear: 0.000000016,
+            }),
+            substeps: 15,
+            iterations: 1,
+            gravity: [0.0, -29.98, 0.0],
+            tear_threshold: 0.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_material(mut self, material: ClothMaterial) -> Self {
+        self.material = material;
+        self
+    }
+
+    // 子步的时间步长
+    pub fn dt(&self) -> f32 {
+        self.frame_dt / self.substeps.max(1) as f32
+    }
+
+    // 布料尺寸：宽度撑满视口时，高度按 1863*3312 的贴图比例
+    pub fn pixel_size(&self, viewport_width: f32) -> (f32, f32) {
+        match self.size {
+            Some(size) => size,
+            None => (viewport_width, viewport_width / 1863.0 * 3312.0),
+        }
+    }
+
+    pub fn to_uniform(&self, triangle_num: i32) -> ClothUniform {
+        let dt = self.dt();
+        // α~ = α / dt^2 直接在这里计算好
+        let dt2 = dt * dt;
+        let compliance = self.material.compliance();
+        ClothUniform {
+            num_x: self.particle_x_num as i32,
+            num_y: self.particle_y_num as i32,
+            triangle_num,
+            compliance: compliance.stretch / dt2,
+            dt,
+            tear_threshold: self.tear_threshold,
+            bend_compliance: compliance.bend / dt2,
+            shear_compliance: compliance.shear / dt2,
+            gravity: [self.gravity[0], self.gravity[1], self.gravity[2], 0.0],
+            damping: self.damping,
+            padding: [0.0; 3],
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use super::{Cloth, ClothConfig, TriangleObj};
+use crate::fluid::{AAD2Q9Node, LBM_STEPS_PER_FRAME};
+use crate::util::shader::reload_shader;
+use crate::util::{node::ComputeNode, BufferObj};
+use app_surface::{math::Size, AppSurface};
+use zerocopy::{AsBytes, FromBytes};
+
+#[repr(C)]
+#[derive(Copy, Clone, AsBytes, FromBytes)]
+pub struct FluidCouplingUniform {
+    pub mvp: [[f32; 4]; 4],
+    // 格子速度 -> 布料空间速度的缩放
+    pub velocity_scale: f32,
+    pub drag_coefficient: f32,
+    // 布料覆盖的格子保持为外力格子的 lbm 迭代次数
+    pub boundary_iter: i32,
+    // lbm 格子速度的上限，超出会导致数值发散
+    pub max_boundary_speed: f32,
+}
+
+// 布料与 lbm 流体的双向耦合
+// 流体 -> 布料：粒子按所在格子的流速受到空气阻力
+// 布料 -> 流体：布料三角形覆盖的格子成为随布料运动的外力格子
+pub struct ClothFluidCoupling {
+    pub uniform_data: FluidCouplingUniform,
+    pub uniform_buf: BufferObj,
+    triangle_buf: BufferObj,
+    drag_node: ComputeNode,
+    boundary_node: ComputeNode,
+}
+
+impl ClothFluidCoupling {
+    pub fn new(app_view: &AppSurface, cloth: &Cloth, fluid: &AAD2Q9Node) -> Self {
+        let device = &app_view.device;
+        let viewport_size: Size<f32> = (&app_view.config).into();
+        // 与 Cloth 使用相同的投影，才能把粒子映射到正确的格子上
+        let (proj_mat, mv_mat, factor) =
+            crate::util::utils::matrix_helper::perspective_mvp(viewport_size);
+        let a_pixel_on_ndc = factor.0 / viewport_size.width;
+        let lattice_on_ndc = fluid.lattice_pixel_size as f32 * a_pixel_on_ndc;
+        let uniform_data = FluidCouplingUniform {
+            mvp: (proj_mat * mv_mat).into(),
+            velocity_scale: velocity_scale(lattice_on_ndc, cloth.config()),
+            drag_coefficient: 2.0,
+            boundary_iter: LBM_STEPS_PER_FRAME as i32,
+            max_boundary_speed: 0.1,
+        };
+        let uniform_buf =
+            BufferObj::create_uniform_buffer(device, &uniform_data, Some("fluid coupling uniform"));
+
+        let triangles = generate_cloth_triangles(cloth.particle_x_num, cloth.particle_y_num);
+        let triangle_buf =
+            BufferObj::create_storage_buffer(device, &triangles, Some("cloth triangle buf"));
+
+        let particle_count = cloth.particle_x_num * cloth.particle_y_num;
+        let drag_shader = crate::util::shader::create_shader_module(
+            device,
+            "pbd/cloth_fluid_drag",
+            Some("cloth_fluid_drag"),
+        );
+        let drag_node = ComputeNode::new(
+            device,
+            ((particle_count + 31) / 32, 1, 1),
+            vec![&cloth.uniform_buf.buffer, &uniform_buf, &fluid.fluid_uniform_buf.buffer],
+            vec![&cloth.particle_buf],
+            vec![(&*fluid.macro_tex, None)],
+            &drag_shader,
+        );
+
+        let boundary_shader = crate::util::shader::create_shader_module(
+            device,
+            "pbd/cloth_fluid_boundary",
+            Some("cloth_fluid_boundary"),
+        );
+        let boundary_node = ComputeNode::new(
+            device,
+            ((triangles.len() as u32 + 31) / 32, 1, 1),
+            vec![&cloth.uniform_buf.buffer, &uniform_buf, &fluid.fluid_uniform_buf.buffer],
+            vec![&cloth.particle_buf, &triangle_buf, &*fluid.info_buf],
+            vec![],
+            &boundary_shader,
+        );
+
+        Self { uniform_data, uniform_buf, triangle_buf, drag_node, boundary_node }
+    }
+
+    // shader 热重载：重建用到了 changed 中 shader 的节点
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_fluid_drag") {
+            self.drag_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_fluid_boundary") {
+            self.boundary_node.reload(device, &shader);
+        }
+    }
+
+    // 在流体迭代之后调用：先把流速作用到布料上，再把布料写回为下一帧的运动边界
+    pub fn dispatch<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        self.drag_node.dispatch(cpass);
+        self.boundary_node.dispatch(cpass);
+    }
+}
+
+// 一个布料帧对应 LBM_STEPS_PER_FRAME 个 lbm 步，格子速度（格子/步）换算为布料空间的速度（/秒）
+// 与子步数无关：粒子速度按子步的 dt 计算，本身就是每秒的速度
+fn velocity_scale(lattice_on_ndc: f32, config: &ClothConfig) -> f32 {
+    lattice_on_ndc * LBM_STEPS_PER_FRAME as f32 / config.frame_dt
+}
+
+// 与 Cloth 的 index_data 相同的三角形划分
+fn generate_cloth_triangles(particle_x_num: u32, particle_y_num: u32) -> Vec<TriangleObj> {
+    let mut triangles: Vec<TriangleObj> =
+        Vec::with_capacity(((particle_x_num - 1) * (particle_y_num - 1) * 2) as usize);
+    for h in 1..particle_y_num {
+        for w in 1..particle_x_num {
+            let current = (particle_x_num * h + w) as i32;
+            let top = current - particle_x_num as i32;
+            triangles.push(TriangleObj { p0: current, p1: top, p2: top - 1 });
+            triangles.push(TriangleObj { p0: current, p1: top - 1, p2: current - 1 });
+        }
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn velocity_scale_follows_frame_dt() {
+        let config = ClothConfig::default();
+        let scale = velocity_scale(0.01, &config);
+        assert!((scale - 0.01 * LBM_STEPS_PER_FRAME as f32 / config.frame_dt).abs() < 1e-6);
+
+        // 帧时长减半，同样的格子速度对应两倍的布料速度
+        let half_dt = ClothConfig { frame_dt: config.frame_dt / 2.0, ..ClothConfig::default() };
+        assert!((velocity_scale(0.01, &half_dt) - scale * 2.0).abs() < 1e-4);
+        // 子步只影响粒子速度的计算精度，不影响换算
+        let small_steps = ClothConfig::small_steps();
+        assert_eq!(small_steps.frame_dt, config.frame_dt);
+        assert_eq!(velocity_scale(0.01, &small_steps), scale);
+    }
+}
@@ -0,0 +1,382 @@
+use super::{
+    group_distance_constraints, BendingConstraintObj, MeshColoringObj, StretchConstraintObj,
+};
+use crate::util::shader::reload_shader;
+use crate::util::{node::ComputeNode, BufferObj};
+use app_surface::math::{Position, Size};
+use nalgebra_glm as glm;
+use std::collections::HashSet;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use zerocopy::{AsBytes, FromBytes};
+
+const ACTION_IDLE: i32 = 0;
+const ACTION_PICK: i32 = 1;
+const ACTION_DRAG: i32 = 2;
+const ACTION_RELEASE: i32 = 3;
+const ACTION_TOGGLE_PIN: i32 = 4;
+
+#[repr(C)]
+#[derive(Copy, Clone, AsBytes, FromBytes)]
+pub struct InteractionUniform {
+    ray_origin: [f32; 4],
+    ray_dir: [f32; 4],
+    action: i32,
+    pub pick_radius: f32,
+    pub drag_stiffness: f32,
+    // 取消固定后恢复的质量倒数
+    pub default_invert_mass: f32,
+    // pick_key 低位存放粒子索引的位数，其余的高位存放射线距离
+    index_bits: u32,
+    _padding: [u32; 3],
+}
+
+// 射线距离至少保留的位数
+const MIN_DISTANCE_BITS: u32 = 8;
+
+#[repr(C)]
+#[derive(Copy, Clone, AsBytes, FromBytes)]
+struct InteractionState {
+    pick_key: u32,
+    particle_index: i32,
+    ray_t: f32,
+    saved_invert_mass: f32,
+}
+
+// 布料的抓取与固定
+// 拾取与拖动都在 GPU 上完成，不需要把粒子位置读回 CPU
+pub struct ClothPicker {
+    pub uniform_data: InteractionUniform,
+    uniform_buf: BufferObj,
+    state_buf: BufferObj,
+    // 下一帧要执行的动作
+    pending_action: i32,
+    inv_mvp: glm::TMat4<f32>,
+    viewport_size: Size<f32>,
+    pick_node: ComputeNode,
+    grab_node: ComputeNode,
+}
+
+impl ClothPicker {
+    pub fn new(
+        device: &wgpu::Device, viewport_size: Size<f32>, mvp: glm::TMat4<f32>,
+        cloth_uniform_buf: &BufferObj, particle_buf: &BufferObj, particle_count: u32,
+    ) -> Self {
+        let index_bits = (32 - particle_count.saturating_sub(1).leading_zeros()).max(1);
+        assert!(
+            index_bits <= 32 - MIN_DISTANCE_BITS,
+            "cloth picking supports at most {} particles",
+            1u32 << (32 - MIN_DISTANCE_BITS)
+        );
+        let uniform_data = InteractionUniform {
+            ray_origin: [0.0; 4],
+            ray_dir: [0.0, 0.0, -1.0, 0.0],
+            action: ACTION_IDLE,
+            pick_radius: 0.08,
+            drag_stiffness: 0.5,
+            default_invert_mass: 0.1,
+            index_bits,
+            _padding: [0; 3],
+        };
+        let uniform_buf = BufferObj::create_uniform_buffer(
+            device,
+            &uniform_data,
+            Some("cloth interaction uniform"),
+        );
+        let state_buf = BufferObj::create_storage_buffer(
+            device,
+            &[InteractionState {
+                pick_key: u32::MAX,
+                particle_index: -1,
+                ray_t: 0.0,
+                saved_invert_mass: 0.0,
+            }],
+            Some("cloth interaction state"),
+        );
+
+        let pick_shader =
+            crate::util::shader::create_shader_module(device, "pbd/cloth_pick", Some("cloth_pick"));
+        let pick_node = ComputeNode::new(
+            device,
+            ((particle_count + 31) / 32, 1, 1),
+            vec![cloth_uniform_buf, &uniform_buf],
+            vec![particle_buf, &state_buf],
+            vec![],
+            &pick_shader,
+        );
+        let grab_shader =
+            crate::util::shader::create_shader_module(device, "pbd/cloth_grab", Some("cloth_grab"));
+        let grab_node = ComputeNode::new(
+            device,
+            (1, 1, 1),
+            vec![cloth_uniform_buf, &uniform_buf],
+            vec![particle_buf, &state_buf],
+            vec![],
+            &grab_shader,
+        );
+
+        Self {
+            uniform_data,
+            uniform_buf,
+            state_buf,
+            pending_action: ACTION_IDLE,
+            inv_mvp: glm::inverse(&mvp),
+            viewport_size,
+            pick_node,
+            grab_node,
+        }
+    }
+
+    // 抓取离触点最近的粒子
+    // shader 热重载：重建用到了 changed 中 shader 的节点
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, changed: &[String]) {
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_pick") {
+            self.pick_node.reload(device, &shader);
+        }
+        if let Some(shader) = reload_shader(device, changed, "pbd/cloth_grab") {
+            self.grab_node.reload(device, &shader);
+        }
+    }
+
+    pub fn grab(&mut self, pos: Position) {
+        self.update_ray(pos);
+        self.pending_action = ACTION_PICK;
+    }
+
+    pub fn drag(&mut self, pos: Position) {
+        self.update_ray(pos);
+        // 拾取还未执行时，保持 pick 动作
+        if self.pending_action != ACTION_PICK {
+            self.pending_action = ACTION_DRAG;
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.pending_action = ACTION_RELEASE;
+    }
+
+    // 切换离触点最近的粒子的固定状态
+    pub fn toggle_pin(&mut self, pos: Position) {
+        self.update_ray(pos);
+        self.pending_action = ACTION_TOGGLE_PIN;
+    }
+
+    // 屏幕坐标反投影为布料空间的射线
+    fn update_ray(&mut self, pos: Position) {
+        let ndc_x = pos.x / self.viewport_size.width * 2.0 - 1.0;
+        let ndc_y = 1.0 - pos.y / self.viewport_size.height * 2.0;
+        let near = self.inv_mvp * glm::vec4(ndc_x, ndc_y, -1.0, 1.0);
+        let far = self.inv_mvp * glm::vec4(ndc_x, ndc_y, 1.0, 1.0);
+        let near = near.xyz() / near.w;
+        let far = far.xyz() / far.w;
+        let dir = glm::normalize(&(far - near));
+        self.uniform_data.ray_origin = [near.x, near.y, near.z, 1.0];
+        self.uniform_data.ray_dir = [dir.x, dir.y, dir.z, 0.0];
+    }
+
+    // 需在提交本帧的 encoder 之前调用
+    pub fn update_uniform(&mut self, queue: &wgpu::Queue) {
+        self.uniform_data.action = self.pending_action;
+        queue.write_buffer(&self.uniform_buf.buffer, 0, self.uniform_data.as_bytes());
+        if self.is_picking() {
+            queue.write_buffer(&self.state_buf.buffer, 0, u32::MAX.as_bytes());
+        }
+        self.pending_action = match self.pending_action {
+            ACTION_PICK | ACTION_DRAG => ACTION_DRAG,
+            _ => ACTION_IDLE,
+        };
+    }
+
+    fn is_picking(&self) -> bool {
+        self.uniform_data.action == ACTION_PICK || self.uniform_data.action == ACTION_TOGGLE_PIN
+    }
+
+    pub fn dispatch<'a, 'b: 'a>(&'b self, cpass: &mut wgpu::ComputePass<'a>) {
+        if self.uniform_data.action == ACTION_IDLE {
+            return;
+        }
+        if self.is_picking() {
+            self.pick_node.dispatch(cpass);
+        }
+        self.grab_node.dispatch(cpass);
+    }
+}
+
+// 撕裂后把约束读回 CPU，剔除断开的约束并重新着色分组
+pub struct TearReadback {
+    staging_buf: wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    in_flight: bool,
+    is_map_requested: bool,
+    is_mapped: Arc<AtomicBool>,
+    broken_count: usize,
+}
+
+impl TearReadback {
+    pub fn new(device: &wgpu::Device, constraint_buf: &BufferObj) -> Self {
+        let staging_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tear readback buf"),
+            size: constraint_buf.size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            staging_buf,
+            size: constraint_buf.size,
+            in_flight: false,
+            is_map_requested: false,
+            is_mapped: Arc::new(AtomicBool::new(false)),
+            broken_count: 0,
+        }
+    }
+
+    pub fn copy_constraints(
+        &mut self, encoder: &mut wgpu::CommandEncoder, constraint_buf: &BufferObj,
+    ) {
+        if self.in_flight {
+            return;
+        }
+        encoder.copy_buffer_to_buffer(&constraint_buf.buffer, 0, &self.staging_buf, 0, self.size);
+        self.in_flight = true;
+    }
+
+    // encoder 提交之后调用
+    pub fn map_async(&mut self) {
+        if !self.in_flight || self.is_map_requested {
+            return;
+        }
+        self.is_map_requested = true;
+        let is_mapped = self.is_mapped.clone();
+        self.staging_buf.slice(..).map_async(wgpu::MapMode::Read, move |res| {
+            if res.is_ok() {
+                is_mapped.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    // 返回新断开了约束的 tear 标记，没有新的撕裂或还未读回时返回 None
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Vec<bool>> {
+        if !self.in_flight {
+            return None;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        if !self.is_mapped.load(Ordering::Acquire) {
+            return None;
+        }
+        let broken: Vec<bool> = {
+            let data = self.staging_buf.slice(..).get_mapped_range();
+            data.chunks_exact(std::mem::size_of::<StretchConstraintObj>())
+                .map(|bytes| {
+                    let c = StretchConstraintObj::read_from(bytes).unwrap();
+                    c.rest_length < 0.0
+                })
+                .collect()
+        };
+        self.staging_buf.unmap();
+        self.is_mapped.store(false, Ordering::Release);
+        self.is_map_requested = false;
+        self.in_flight = false;
+
+        let count = broken.iter().filter(|b| **b).count();
+        if count == self.broken_count {
+            return None;
+        }
+        self.broken_count = count;
+        Some(broken)
+    }
+}
+
+// 已断开的约束两端的粒子对，小的索引在前
+pub fn torn_pairs(constraints: &[StretchConstraintObj], broken: &[bool]) -> HashSet<(i32, i32)> {
+    constraints
+        .iter()
+        .zip(broken.iter())
+        .filter(|(_, is_broken)| **is_broken)
+        .map(|(c, _)| (c.particle0.min(c.particle1), c.particle0.max(c.particle1)))
+        .collect()
+}
+
+// 跨过撕裂处的弯曲约束：v 与 b0 或 b1 之间的约束已断开
+pub fn is_bending_torn(bending: &BendingConstraintObj, torn: &HashSet<(i32, i32)>) -> bool {
+    [bending.b0, bending.b1].iter().any(|b| torn.contains(&(bending.v.min(*b), bending.v.max(*b))))
+}
+
+// 布料网格的三角形索引，跳过有边已撕裂的三角形
+// 每个格子的两个三角形的边都是拉伸或剪切约束
+pub fn cloth_triangle_indices(
+    particle_x_num: u32, particle_y_num: u32, torn: &HashSet<(i32, i32)>,
+) -> Vec<u32> {
+    let is_torn = |a: u32, b: u32| torn.contains(&(a.min(b) as i32, a.max(b) as i32));
+    let mut index_data: Vec<u32> = Vec::new();
+    for h in 1..particle_y_num {
+        for w in 1..particle_x_num {
+            let current: u32 = particle_x_num * h + w;
+            // 上一行同一列位置的索引
+            let top: u32 = current - particle_x_num;
+            for [a, b, c] in [[current, top, top - 1], [current, top - 1, current - 1]] {
+                if !is_torn(a, b) && !is_torn(b, c) && !is_torn(c, a) {
+                    index_data.extend_from_slice(&[a, b, c]);
+                }
+            }
+        }
+    }
+    index_data
+}
+
+// 移除已断开的约束后重新着色分组
+// 返回的 reorder 数组长度不变，末尾用 -1 填充
+pub fn regroup_torn_constraints(
+    constraints: &[StretchConstraintObj], reorder_constraints: &[[i32; 3]], broken: &[bool],
+) -> (Vec<MeshColoringObj>, Vec<[i32; 3]>) {
+    let mut particle_constraints: Vec<[i32; 3]> = Vec::with_capacity(reorder_constraints.len());
+    for pcs in reorder_constraints.iter() {
+        // 着色器遇到第一个 -1 就跳过整组，所以有效的约束需要排在前面
+        let mut list = [-1; 3];
+        let mut count = 0;
+        for c in pcs.iter() {
+            if *c >= 0 && !broken[*c as usize] {
+                list[count] = *c;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            particle_constraints.push(list);
+        }
+    }
+    let (mesh_colorings, mut reorder) =
+        group_distance_constraints(constraints, &particle_constraints);
+    reorder.resize(reorder_constraints.len(), [-1; 3]);
+    (mesh_colorings, reorder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraint(particle0: i32, particle1: i32) -> StretchConstraintObj {
+        StretchConstraintObj { rest_length: 1.0, lambda: 0.0, particle0, particle1 }
+    }
+
+    #[test]
+    fn torn_edge_drops_its_triangles_and_bendings() {
+        // 3x3 的网格，断开中心粒子 4 与右侧粒子 5 之间的约束
+        let constraints = [constraint(3, 4), constraint(5, 4)];
+        let torn = torn_pairs(&constraints, &[false, true]);
+        assert!(torn.contains(&(4, 5)));
+
+        assert_eq!(cloth_triangle_indices(3, 3, &HashSet::new()).len(), 8 * 3);
+        let indices = cloth_triangle_indices(3, 3, &torn);
+        // 4-5 边两侧的两个三角形被移除
+        assert_eq!(indices.len(), 6 * 3);
+        for triangle in indices.chunks_exact(3) {
+            assert!(!(triangle.contains(&4) && triangle.contains(&5)));
+        }
+
+        let across = BendingConstraintObj { v: 4, b0: 3, b1: 5, h0: 0.0 };
+        let beside = BendingConstraintObj { v: 4, b0: 1, b1: 7, h0: 0.0 };
+        assert!(is_bending_torn(&across, &torn));
+        assert!(!is_bending_torn(&beside, &torn));
+    }
+}
@@ -1,8 +1,8 @@
 use crate::util::node::ComputeNode;
 use crate::util::node::{ViewNode, ViewNodeBuilder};
-use crate::util::{vertex::PosParticleIndex, BufferObj};
+use crate::util::{vertex::PosParticleIndex, BufferObj, UniformBuffer};
 
-use super::{generate_cloth_particles, ClothUniform, MeshColoringObj};
+use super::{generate_cloth_particles, ClothConfig, ClothUniform, MeshColoringObj};
 
 use app_surface::{
     math::{Position, Size},
@@ -11,6 +11,8 @@ use app_surface::{
 use zerocopy::AsBytes;
 
 pub struct ClothX {
+    config: ClothConfig,
+    uniform_buf: UniformBuffer<ClothUniform>,
     particle_buf: BufferObj,
     constraint_buf: BufferObj,
     bend_constraints_buf: BufferObj,
@@ -25,12 +27,15 @@ pub struct ClothX {
     display_node: ViewNode,
     depth_texture_view: wgpu::TextureView,
     frame_count: usize,
-    // 迭代次数
+    // 子步数
     pbd_iter_count: usize,
+    // 每个子步里拉伸约束的迭代次数
+    iterations: usize,
 }
 
 impl ClothX {
-    pub fn new(app_view: &AppSurface) -> Self {
+    // 与之前固定的参数一致时使用 ClothConfig::small_steps()
+    pub fn new(app_view: &AppSurface, config: ClothConfig) -> Self {
         let _encoder =
             app_view.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         let viewport_size: Size<f32> = (&app_view.config).into();
@@ -46,34 +51,12 @@ impl ClothX {
             },
             None,
         );
-        // （32， 64） 这个组合，约束分组后为 9 组，且没有极小数据量的分组
-        // 为何 （32， 64）导致在 particle buffer 里开始部分数据顺序乱了？
-        let particle_x_num = 32_u32;
-        let particle_y_num = 58_u32;
-
-        //static const float MODE_COMPLIANCE[eModeMax] = {
-        //  0.0f,            // Miles Macklin's blog (http://blog.mmacklin.com/2016/10/12/xpbd-slides-and-stiffness/)
-        //  0.00000000004f, // 0.04 x 10^(-9) (M^2/N) Concrete
-        //  0.00000000016f, // 0.16 x 10^(-9) (M^2/N) Wood
-        //  0.000000001f,   // 1.0  x 10^(-8) (M^2/N) Leather
-        //  0.000000002f,   // 0.2  x 10^(-7) (M^2/N) Tendon
-        //  0.0000001f,     // 1.0  x 10^(-6) (M^2/N) Rubber
-        //  0.00002f,       // 0.2  x 10^(-3) (M^2/N) Muscle
-        //  0.0001f,        // 1.0  x 10^(-3) (M^2/N) Fat
-        //};
-        let pbd_iter_count = 15;
-        let delta_time = 0.016 / pbd_iter_count as f32;
-        let uniform_buf = BufferObj::create_uniform_buffer(
-            &app_view.device,
-            &ClothUniform {
-                num_x: particle_x_num as i32,
-                num_y: particle_y_num as i32,
-                triangle_num: 0,
-                compliance: 0.0000000016 / (delta_time * delta_time),
-                dt: delta_time,
-            },
-            Some("cloth uniform"),
-        );
+        let particle_x_num = config.particle_x_num;
+        let particle_y_num = config.particle_y_num;
+        let pbd_iter_count = config.substeps as usize;
+        let uniform_buf =
+            UniformBuffer::new(&app_view.device, config.to_uniform(0), Some("cloth uniform"));
+        let (pixel_width, pixel_height) = config.pixel_size(app_view.config.width as f32);
         let (
             (_tl_x, _tl_y),
             particles,
@@ -87,9 +70,10 @@ impl ClothX {
         ) = generate_cloth_particles(
             particle_x_num as usize,
             particle_y_num as usize,
-            app_view.config.width as f32,
-            app_view.config.width as f32 / 1863.0 * 3312.0,
+            pixel_width,
+            pixel_height,
             factor.0 / viewport_size.width,
+            config.areal_density,
         );
         // dynamit uniform
         let dynamic_offset =
@@ -122,7 +106,7 @@ impl ClothX {
                 app_view.queue.write_buffer(
                     &bend_coloring_buf.buffer,
                     offset,
-                    mc.get_bending_dynamic_uniform(i).as_bytes(),
+                    mc.get_bending_dynamic_uniform(i as i32).as_bytes(),
                 );
                 offset += dynamic_offset;
             }
@@ -174,7 +158,7 @@ impl ClothX {
         let predict_and_reset = ComputeNode::new_with_dynamic_uniforms(
             &app_view.device,
             (((particle_x_num * particle_y_num + 31) as f32 / 32.0).floor() as u32, 1, 1),
-            vec![&uniform_buf],
+            vec![&uniform_buf.buffer],
             vec![&predict_dynamic_buf],
             vec![&particle_buf, &constraint_buf, &reorder_constraints_buf],
             vec![],
@@ -189,7 +173,7 @@ impl ClothX {
         let stretch_solver = ComputeNode::new_with_dynamic_uniforms(
             &app_view.device,
             (0, 0, 0),
-            vec![&uniform_buf],
+            vec![&uniform_buf.buffer],
             vec![(&stretch_coloring_buf)],
             vec![&particle_buf, &constraint_buf, &reorder_constraints_buf],
             vec![],
@@ -211,7 +195,7 @@ impl ClothX {
         let bend_solver = ComputeNode::new_with_dynamic_uniforms(
             &app_view.device,
             (0, 0, 0),
-            vec![&uniform_buf],
+            vec![&uniform_buf.buffer],
             vec![&bend_coloring_buf],
             vec![&particle_buf, &bend_constraints_buf, &reorder_bendings_buf],
             vec![],
@@ -247,7 +231,7 @@ impl ClothX {
             crate::util::shader::create_shader_module(&app_view.device, "pbd/cloth_display", None);
         let display_node_builder =
             ViewNodeBuilder::<PosParticleIndex>::new(vec![(&texture, None)], &display_shader)
-                .with_uniform_buffers(vec![&mvp_buf, &uniform_buf])
+                .with_uniform_buffers(vec![&mvp_buf, &uniform_buf.buffer])
                 .with_storage_buffers(vec![&particle_buf])
                 .with_use_depth_stencil(true)
                 .with_cull_mode(None)
@@ -271,6 +255,8 @@ impl ClothX {
             crate::util::depth_stencil::create_depth_texture_view(size, &app_view.device);
 
         let instance = Self {
+            config,
+            uniform_buf,
             particle_buf,
             constraint_buf,
             stretch_mesh_coloring,
@@ -282,12 +268,32 @@ impl ClothX {
             display_node,
             depth_texture_view,
             frame_count: 0,
-            pbd_iter_count: pbd_iter_count as usize,
+            pbd_iter_count,
+            iterations: config.iterations as usize,
         };
 
         instance
     }
 
+    pub fn config(&self) -> &ClothConfig {
+        &self.config
+    }
+
+    // 运行时修改材料、阻尼、子步、重力等参数，粒子数、尺寸与面密度保持不变
+    pub fn set_config(&mut self, queue: &wgpu::Queue, config: ClothConfig) {
+        self.config = ClothConfig {
+            particle_x_num: self.config.particle_x_num,
+            particle_y_num: self.config.particle_y_num,
+            size: self.config.size,
+            areal_density: self.config.areal_density,
+            ..config
+        };
+        self.pbd_iter_count = self.config.substeps as usize;
+        self.iterations = self.config.iterations as usize;
+        let uniform = self.config.to_uniform(0);
+        self.uniform_buf.update(queue, |data| *data = uniform);
+    }
+
     fn step_solver(&mut self, encoder: &mut wgpu::CommandEncoder) {
         // if self.frame_count >= 1 {
         //     return;
@@ -303,15 +309,17 @@ impl ClothX {
             let offset = if i == 0 { 256 } else { 0 };
             self.predict_and_reset.dispatch_by_offsets(&mut cpass, Some(vec![vec![offset]]));
 
-            cpass.set_pipeline(&self.stretch_solver.pipeline);
-            cpass.set_bind_group(0, &self.stretch_solver.bg_setting.bind_group, &[]);
-            let mut index = 0;
-            for mc in self.stretch_mesh_coloring.iter() {
-                if let Some(bg) = &self.stretch_solver.dy_uniform_bg {
-                    cpass.set_bind_group(1, &bg.bind_group, &[index * dynamic_offset]);
+            for _ in 0..self.iterations {
+                cpass.set_pipeline(&self.stretch_solver.pipeline);
+                cpass.set_bind_group(0, &self.stretch_solver.bg_setting.bind_group, &[]);
+                let mut index = 0;
+                for mc in self.stretch_mesh_coloring.iter() {
+                    if let Some(bg) = &self.stretch_solver.dy_uniform_bg {
+                        cpass.set_bind_group(1, &bg.bind_group, &[index * dynamic_offset]);
+                    }
+                    cpass.dispatch_workgroups(mc.thread_group.0, mc.thread_group.1, 1);
+                    index += 1;
                 }
-                cpass.dispatch_workgroups(mc.thread_group.0, mc.thread_group.1, 1);
-                index += 1;
             }
 
             let bending_dynamic_uniform_offset =
@@ -319,7 +327,7 @@ impl ClothX {
                     as wgpu::DynamicOffset;
             cpass.set_pipeline(&self.bend_solver.pipeline);
             cpass.set_bind_group(0, &self.bend_solver.bg_setting.bind_group, &[]);
-            index = 0;
+            let mut index = 0;
             for mc in self.bend_mesh_coloring.iter() {
                 if let Some(bg) = &self.bend_solver.dy_uniform_bg {
                     cpass.set_bind_group(
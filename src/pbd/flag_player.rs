@@ -0,0 +1,125 @@
+use super::{Cloth, ClothConfig, ClothFluidCoupling};
+use crate::util::{BufferObj, GpuProfiler};
+use crate::{setting_obj::SettingObj, FluidPlayer, Player};
+use app_surface::math::{Position, Size};
+use wgpu::{CommandEncoderDescriptor, Device, Queue};
+
+// 风洞里飘动的旗帜：lbm 流体与 pbd 布料双向耦合
+pub struct FlagPlayer {
+    fluid: FluidPlayer,
+    cloth: Cloth,
+    coupling: ClothFluidCoupling,
+    // 不足一个参考帧的模拟步数
+    pending_steps: u32,
+}
+
+impl FlagPlayer {
+    pub fn new(
+        app_view: &app_surface::AppSurface, canvas_size: Size<u32>, canvas_buf: &BufferObj,
+        setting: &SettingObj,
+    ) -> Self {
+        let fluid = FluidPlayer::new(app_view, canvas_size, canvas_buf, setting);
+        let cloth = Cloth::new(app_view, ClothConfig::default());
+        let coupling = ClothFluidCoupling::new(app_view, &cloth, &fluid.fluid_compute_node);
+
+        FlagPlayer { fluid, cloth, coupling, pending_steps: 0 }
+    }
+}
+
+impl Player for FlagPlayer {
+    fn recycle_resources(&mut self) {
+        self.fluid.recycle_resources();
+    }
+
+    fn update_uniforms(&mut self, queue: &Queue, setting: &SettingObj) {
+        self.fluid.update_uniforms(queue, setting);
+    }
+
+    fn on_click(&mut self, device: &Device, queue: &Queue, pos: Position) {
+        self.fluid.on_click(device, queue, pos);
+    }
+
+    fn touch_begin(&mut self, device: &Device, queue: &Queue, id: u64, pos: Position) {
+        self.fluid.touch_begin(device, queue, id, pos);
+    }
+
+    fn touch_move(&mut self, device: &Device, queue: &Queue, id: u64, pos: Position) {
+        self.fluid.touch_move(device, queue, id, pos);
+    }
+
+    fn touch_end(&mut self, device: &Device, queue: &Queue, id: u64, pos: Position) {
+        self.fluid.touch_end(device, queue, id, pos);
+    }
+
+    fn reset(&mut self, device: &Device, queue: &Queue) {
+        self.fluid.reset(device, queue);
+    }
+
+    fn set_brush(&mut self, radius: f32, falloff: f32) {
+        self.fluid.set_brush(radius, falloff);
+    }
+
+    fn set_edit_tool(&mut self, tool: crate::EditTool) {
+        self.fluid.set_edit_tool(tool);
+    }
+
+    fn take_lattice_edit(&mut self) -> Option<crate::fluid::LatticeEdit> {
+        self.fluid.take_lattice_edit()
+    }
+
+    fn apply_lattice_edit(&mut self, edit: &crate::fluid::LatticeEdit, is_undo: bool) {
+        self.fluid.apply_lattice_edit(edit, is_undo);
+    }
+
+    // 暂停时丢弃不足一帧的步数，单步从参考帧的起点开始计数
+    fn pause(&mut self) {
+        self.fluid.pause();
+        self.pending_steps = 0;
+    }
+
+    fn resume(&mut self) {
+        self.fluid.resume();
+    }
+
+    fn step(&mut self, n: u32) {
+        self.fluid.step(n);
+    }
+
+    fn set_time_scale(&mut self, scale: f32) {
+        self.fluid.set_time_scale(scale);
+    }
+
+    fn reload_shaders(&mut self, device: &Device, changed: &[String]) {
+        self.fluid.reload_shaders(device, changed);
+        self.cloth.reload_shaders(device, changed);
+        self.coupling.reload_shaders(device, changed);
+    }
+
+    fn enter_frame(
+        &mut self, device: &Device, queue: &Queue, frame_view: &wgpu::TextureView,
+        setting: &mut SettingObj, steps: u32, profiler: &mut GpuProfiler,
+    ) {
+        // 流体迭代并绘制背景
+        self.fluid.enter_frame(device, queue, frame_view, setting, steps, profiler);
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("flag player encoder"),
+        });
+        // 布料的时间步长固定为一个参考帧
+        let frames = crate::sim_clock::steps_to_frames(&mut self.pending_steps, steps);
+        for _ in 0..frames {
+            let scope = profiler.begin_scope(&mut encoder, "coupling");
+            {
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("cloth fluid coupling"),
+                });
+                self.coupling.dispatch(&mut cpass);
+            }
+            profiler.end_scope(&mut encoder, scope);
+            self.cloth.step_solver(queue, &mut encoder, Some(&mut *profiler));
+        }
+        self.cloth.draw_render_pass(&mut encoder, frame_view, wgpu::LoadOp::Load);
+        queue.submit(Some(encoder.finish()));
+        self.cloth.check_tearing(device, queue);
+    }
+}
@@ -0,0 +1,186 @@
+use super::MeshColoringObj;
+
+// 约束的图着色分组
+// 同一分组内的约束没有公共粒子，可以在一次 dispatch 里并行求解
+//
+// items: 待分组的约束（或约束组）
+// particles_of: 返回约束涉及的全部粒子索引，小于 0 的索引会被忽略
+// balanced: 为 true 时，每个约束优先放进当前最小的可用分组，使各组大小尽量接近；
+//           否则放进第一个可用分组（first-fit），分组数通常更少，但最后几组可能极小
+pub fn color_constraints<T, F>(
+    items: &[T], particle_count: usize, particles_of: F, balanced: bool,
+) -> Vec<Vec<T>>
+where
+    T: Copy,
+    F: Fn(&T) -> Vec<i32>,
+{
+    let groups = assign_colors(items, particle_count, &particles_of, false, 0);
+    if !balanced {
+        return groups;
+    }
+    // 先用 first-fit 得到分组数，只在这些分组内做均衡
+    assign_colors(items, particle_count, &particles_of, true, groups.len())
+}
+
+fn assign_colors<T: Copy>(
+    items: &[T], particle_count: usize, particles_of: &dyn Fn(&T) -> Vec<i32>, balanced: bool,
+    color_count: usize,
+) -> Vec<Vec<T>> {
+    // 每个分组里已被占用的粒子
+    let mut used: Vec<Vec<bool>> = vec![vec![false; particle_count]; color_count];
+    let mut groups: Vec<Vec<T>> = vec![vec![]; color_count];
+
+    for item in items.iter() {
+        let particles: Vec<usize> =
+            particles_of(item).into_iter().filter(|p| *p >= 0).map(|p| p as usize).collect();
+        let is_free = |u: &Vec<bool>| particles.iter().all(|p| !u[*p]);
+        let color = if balanced {
+            (0..groups.len()).filter(|c| is_free(&used[*c])).min_by_key(|c| groups[*c].len())
+        } else {
+            used.iter().position(is_free)
+        };
+        let color = match color {
+            Some(c) => c,
+            None => {
+                used.push(vec![false; particle_count]);
+                groups.push(vec![]);
+                groups.len() - 1
+            }
+        };
+        for p in particles.iter() {
+            used[color][*p] = true;
+        }
+        groups[color].push(*item);
+    }
+    groups.retain(|g| !g.is_empty());
+    groups
+}
+
+// 检查分组内的约束是否互相独立
+pub fn validate_coloring<T, F>(
+    groups: &[Vec<T>], particle_count: usize, particles_of: F,
+) -> Result<(), String>
+where
+    F: Fn(&T) -> Vec<i32>,
+{
+    for (color, a_group) in groups.iter().enumerate() {
+        // 记录粒子被分组内的哪个约束占用
+        let mut owner: Vec<Option<usize>> = vec![None; particle_count];
+        for (index, item) in a_group.iter().enumerate() {
+            let mut particles = particles_of(item);
+            particles.sort();
+            particles.dedup();
+            for p in particles.into_iter().filter(|p| *p >= 0) {
+                if p as usize >= particle_count {
+                    return Err(format!("group {}: particle {} out of range", color, p));
+                }
+                if let Some(other) = owner[p as usize] {
+                    return Err(format!(
+                        "group {}: constraints {} and {} share particle {}",
+                        color, other, index, p
+                    ));
+                }
+                owner[p as usize] = Some(index);
+            }
+        }
+    }
+    Ok(())
+}
+
+// 分组展开为连续的数组，并生成每组的 offset 与 dispatch 维度
+pub fn mesh_coloring_from_groups<T>(groups: Vec<Vec<T>>) -> (Vec<MeshColoringObj>, Vec<T>) {
+    let mut mesh_colorings: Vec<MeshColoringObj> = vec![];
+    let mut reorder: Vec<T> = vec![];
+    let mut offset = 0;
+    for mut a_group in groups.into_iter() {
+        let group_len = a_group.len() as u32;
+        mesh_colorings.push(MeshColoringObj {
+            offset,
+            max_num_x: 0,
+            max_num_y: 0,
+            group_len,
+            thread_group: ((group_len + 31) / 32, 1),
+        });
+        reorder.append(&mut a_group);
+        offset += group_len;
+    }
+    (mesh_colorings, reorder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 粒子数不同、含 -1 占位的不规则约束
+    fn irregular_constraints() -> Vec<[i32; 3]> {
+        vec![
+            [0, 1, -1],
+            [1, 2, 3],
+            [3, 4, -1],
+            [0, 4, 5],
+            [5, -1, -1],
+            [2, 6, 7],
+            [6, 7, -1],
+            [1, 7, -1],
+            [8, 9, 0],
+            [9, -1, 4],
+        ]
+    }
+
+    fn particles_of(c: &[i32; 3]) -> Vec<i32> {
+        c.to_vec()
+    }
+
+    #[test]
+    fn groups_are_independent() {
+        let items = irregular_constraints();
+        for balanced in [false, true] {
+            let groups = color_constraints(&items, 10, particles_of, balanced);
+            assert!(validate_coloring(&groups, 10, particles_of).is_ok());
+            // 每个约束恰好出现一次
+            let mut all: Vec<[i32; 3]> = groups.concat();
+            all.sort();
+            let mut expected = items.clone();
+            expected.sort();
+            assert_eq!(all, expected);
+        }
+    }
+
+    #[test]
+    fn balanced_groups_have_similar_sizes() {
+        // 链上相邻的边互相冲突分成两组，first-fit 会把后面互相独立的边全部放进第一组
+        let mut items: Vec<[i32; 3]> = (0..8).map(|i| [i, i + 1, -1]).collect();
+        items.extend((0..4).map(|i| [20 + 2 * i, 21 + 2 * i, -1]));
+        let first_fit = color_constraints(&items, 32, particles_of, false);
+        let balanced = color_constraints(&items, 32, particles_of, true);
+        assert!(validate_coloring(&balanced, 32, particles_of).is_ok());
+        assert_eq!(first_fit.len(), balanced.len());
+
+        let spread = |groups: &Vec<Vec<[i32; 3]>>| {
+            let sizes: Vec<usize> = groups.iter().map(|g| g.len()).collect();
+            sizes.iter().max().unwrap() - sizes.iter().min().unwrap()
+        };
+        assert!(spread(&balanced) <= 1);
+        assert!(spread(&balanced) < spread(&first_fit));
+    }
+
+    #[test]
+    fn conflicting_coloring_is_rejected() {
+        let groups = vec![vec![[0, 1, -1], [2, 3, -1]], vec![[1, 2, -1], [4, 1, -1]]];
+        let err = validate_coloring(&groups, 5, particles_of).unwrap_err();
+        assert!(err.contains("group 1") && err.contains("particle 1"), "{}", err);
+
+        let groups = vec![vec![[0, 7, -1]]];
+        assert!(validate_coloring(&groups, 5, particles_of).is_err());
+    }
+
+    #[test]
+    fn flattened_groups_keep_offsets() {
+        let groups = vec![vec![1, 2, 3], vec![4], vec![5, 6]];
+        let (colorings, flat) = mesh_coloring_from_groups(groups);
+        assert_eq!(flat, vec![1, 2, 3, 4, 5, 6]);
+        let offsets: Vec<u32> = colorings.iter().map(|c| c.offset).collect();
+        assert_eq!(offsets, vec![0, 3, 4]);
+        assert_eq!(colorings[2].group_len, 2);
+    }
+}
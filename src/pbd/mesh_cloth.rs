@@ -0,0 +1,301 @@
+use crate::util::node::{Bindings, ComputeNode};
+use crate::util::node::{ViewNode, ViewNodeBuilder};
+use crate::util::{vertex::PosParticleIndex, BufferObj, ShaderReflection, UniformBuffer};
+
+use super::{
+    generate_mesh_particles, ClothConfig, ClothUniform, DihedralConstraintObj, MeshColoringObj,
+    ObjMesh,
+};
+
+use app_surface::{math::Size, AppSurface};
+use zerocopy::{AsBytes, FromBytes};
+
+// 由任意三角网格生成的布料 / 软体
+// 求解方式与 ClothX 相同：每帧多个子步，每个子步各约束只迭代一次
+pub struct MeshCloth {
+    config: ClothConfig,
+    triangle_num: i32,
+    uniform_buf: UniformBuffer<ClothUniform>,
+    particle_buf: BufferObj,
+    stretch_mesh_coloring: Vec<MeshColoringObj>,
+    dihedral_mesh_coloring: Vec<MeshColoringObj>,
+
+    predict: ComputeNode,
+    stretch_solver: ComputeNode,
+    dihedral_solver: ComputeNode,
+    display_node: ViewNode,
+    depth_texture_view: wgpu::TextureView,
+    // 子步数
+    pbd_iter_count: usize,
+}
+
+impl MeshCloth {
+    // 使用 mesh 的副本缩放到视口中央，pinned 为固定粒子的顶点索引
+    // config 里的粒子数、尺寸与面密度不适用于网格，会被忽略
+    pub fn new(app_view: &AppSurface, mesh: &ObjMesh, pinned: &[u32], config: ClothConfig) -> Self {
+        let viewport_size: Size<f32> = (&app_view.config).into();
+        let (proj_mat, mv_mat, factor) =
+            crate::util::utils::matrix_helper::perspective_mvp(viewport_size);
+        let mvp_buf = BufferObj::create_uniform_buffer(
+            &app_view.device,
+            &crate::MVPMatUniform {
+                mv: mv_mat.into(),
+                proj: proj_mat.into(),
+                mvp: (proj_mat * mv_mat).into(),
+                normal: mv_mat.into(),
+            },
+            None,
+        );
+        let mut mesh = mesh.clone();
+        mesh.fit_to(factor.0.min(factor.1) * 0.8);
+        let data = generate_mesh_particles(&mesh, pinned);
+        let particle_count = data.particles.len() as u32;
+
+        let pbd_iter_count = config.substeps as usize;
+        // 网格粒子没有行列结构，显示时按 num_x 个粒子的单行处理
+        let config = ClothConfig { particle_x_num: particle_count, particle_y_num: 1, ..config };
+        let triangle_num = (data.indices.len() / 3) as i32;
+        let uniform_buf = UniformBuffer::new(
+            &app_view.device,
+            config.to_uniform(triangle_num),
+            Some("mesh cloth uniform"),
+        );
+
+        let dynamic_offset =
+            app_view.device.limits().min_uniform_buffer_offset_alignment as wgpu::BufferAddress;
+        let stretch_coloring_buf =
+            create_coloring_buf(app_view, &data.stretch_mesh_coloring, dynamic_offset);
+        let dihedral_coloring_buf =
+            create_coloring_buf(app_view, &data.dihedral_mesh_coloring, dynamic_offset);
+
+        let predict_dynamic_buf = BufferObj::create_empty_dynamic_uniform_buffer(
+            &app_view.device,
+            dynamic_offset,
+            None,
+            Some("predict_dynamic_buf"),
+        );
+        let particle_buf = BufferObj::create_storage_buffer(
+            &app_view.device,
+            &data.particles,
+            Some("mesh particle buf"),
+        );
+        let constraint_buf = BufferObj::create_storage_buffer(
+            &app_view.device,
+            &data.constraints,
+            Some("mesh constraint_buf"),
+        );
+        let reorder_constraints_buf = BufferObj::create_storage_buffer(
+            &app_view.device,
+            &data.reorder_constraints,
+            Some("mesh reorder_constraints_buf"),
+        );
+        // 平面网格也可能没有二面角约束，storage buffer 不能为空
+        let dihedrals = if data.dihedrals.is_empty() {
+            vec![DihedralConstraintObj::new_zeroed()]
+        } else {
+            data.dihedrals.clone()
+        };
+        let dihedral_buf =
+            BufferObj::create_storage_buffer(&app_view.device, &dihedrals, Some("dihedral_buf"));
+        let reorder_dihedrals = if data.reorder_dihedrals.is_empty() {
+            vec![0]
+        } else {
+            data.reorder_dihedrals.clone()
+        };
+        let reorder_dihedrals_buf = BufferObj::create_storage_buffer(
+            &app_view.device,
+            &reorder_dihedrals,
+            Some("reorder_dihedrals_buf"),
+        );
+
+        let predict_shader = crate::util::shader::create_shader_module(
+            &app_view.device,
+            "pbd/xxpbd/cloth_predict",
+            None,
+        );
+        let predict = ComputeNode::with_bindings_and_dynamic_uniforms(
+            &app_view.device,
+            ((particle_count + 31) / 32, 1, 1),
+            &ShaderReflection::new("pbd/xxpbd/cloth_predict", None, &[]),
+            Bindings::new()
+                .uniform("cloth", &uniform_buf)
+                .buffer("particles", &particle_buf)
+                .buffer("constraints", &constraint_buf)
+                .buffer("reorder_constraints", &reorder_constraints_buf),
+            vec![&predict_dynamic_buf],
+            &predict_shader,
+        );
+        let stretch_shader = crate::util::shader::create_shader_module(
+            &app_view.device,
+            "pbd/xxpbd/cloth_stretch_solver",
+            None,
+        );
+        let stretch_solver = ComputeNode::with_bindings_and_dynamic_uniforms(
+            &app_view.device,
+            (0, 0, 0),
+            &ShaderReflection::new("pbd/xxpbd/cloth_stretch_solver", None, &[]),
+            Bindings::new()
+                .uniform("cloth", &uniform_buf)
+                .buffer("particles", &particle_buf)
+                .buffer("constraints", &constraint_buf)
+                .buffer("reorder_constraints", &reorder_constraints_buf),
+            vec![&stretch_coloring_buf],
+            &stretch_shader,
+        );
+        let dihedral_shader = crate::util::shader::create_shader_module(
+            &app_view.device,
+            "pbd/xxpbd/cloth_dihedral_solver",
+            None,
+        );
+        let dihedral_solver = ComputeNode::with_bindings_and_dynamic_uniforms(
+            &app_view.device,
+            (0, 0, 0),
+            &ShaderReflection::new("pbd/xxpbd/cloth_dihedral_solver", None, &[]),
+            Bindings::new()
+                .uniform("cloth", &uniform_buf)
+                .buffer("particles", &particle_buf)
+                .buffer("constraints", &dihedral_buf)
+                .buffer("reorder_constraints", &reorder_dihedrals_buf),
+            vec![&dihedral_coloring_buf],
+            &dihedral_shader,
+        );
+
+        let vertex_data: Vec<PosParticleIndex> =
+            (0..particle_count).map(|i| PosParticleIndex::new([i, 0, 0])).collect();
+        let (texture, _) = crate::util::load_texture::from_path(
+            "dragon.png",
+            app_view,
+            wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            false,
+        );
+        let display_shader =
+            crate::util::shader::create_shader_module(&app_view.device, "pbd/cloth_display", None);
+        let display_node =
+            ViewNodeBuilder::<PosParticleIndex>::new(vec![(&texture, None)], &display_shader)
+                .with_uniform_buffers(vec![&mvp_buf, &uniform_buf.buffer])
+                .with_storage_buffers(vec![&particle_buf])
+                .with_color_format(app_view.config.format)
+                .with_use_depth_stencil(true)
+                .with_cull_mode(None)
+                .with_shader_stages(vec![
+                    wgpu::ShaderStages::VERTEX,
+                    wgpu::ShaderStages::VERTEX,
+                    wgpu::ShaderStages::VERTEX,
+                    wgpu::ShaderStages::FRAGMENT,
+                    wgpu::ShaderStages::FRAGMENT,
+                ])
+                .with_vertices_and_indices((vertex_data, data.indices))
+                .build(&app_view.device);
+
+        let size = wgpu::Extent3d {
+            width: app_view.config.width,
+            height: app_view.config.height,
+            depth_or_array_layers: 1,
+        };
+        let depth_texture_view =
+            crate::util::depth_stencil::create_depth_texture_view(size, &app_view.device);
+
+        Self {
+            config,
+            triangle_num,
+            uniform_buf,
+            particle_buf,
+            stretch_mesh_coloring: data.stretch_mesh_coloring,
+            dihedral_mesh_coloring: data.dihedral_mesh_coloring,
+            predict,
+            stretch_solver,
+            dihedral_solver,
+            display_node,
+            depth_texture_view,
+            pbd_iter_count,
+        }
+    }
+
+    pub fn config(&self) -> &ClothConfig {
+        &self.config
+    }
+
+    // 运行时修改材料、阻尼、子步、重力等参数，粒子数由网格决定，保持不变
+    pub fn set_config(&mut self, queue: &wgpu::Queue, config: ClothConfig) {
+        self.config = ClothConfig {
+            particle_x_num: self.config.particle_x_num,
+            particle_y_num: self.config.particle_y_num,
+            ..config
+        };
+        self.pbd_iter_count = self.config.substeps as usize;
+        let uniform = self.config.to_uniform(self.triangle_num);
+        self.uniform_buf.update(queue, |data| *data = uniform);
+    }
+
+    pub fn step_solver(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let mut cpass = encoder
+            .begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("mesh solver pass") });
+        for _ in 0..self.pbd_iter_count {
+            self.predict.dispatch_by_offsets(&mut cpass, Some(vec![vec![0]]));
+            dispatch_groups(&mut cpass, &self.stretch_solver, &self.stretch_mesh_coloring);
+            dispatch_groups(&mut cpass, &self.dihedral_solver, &self.dihedral_mesh_coloring);
+        }
+    }
+
+    pub fn enter_frame(&mut self, app_view: &mut AppSurface) {
+        let mut encoder = app_view.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("mesh cloth encoder"),
+        });
+        self.step_solver(&mut encoder);
+        let (frame, frame_view) = app_view.get_current_frame_view();
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("mesh cloth render pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &frame_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(crate::util::utils::alpha_color()),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(
+                    crate::util::utils::depth_stencil::create_attachment(&self.depth_texture_view),
+                ),
+            });
+            self.display_node.draw_render_pass(&mut rpass);
+        }
+        app_view.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    pub fn particle_buf(&self) -> &BufferObj {
+        &self.particle_buf
+    }
+}
+
+fn create_coloring_buf(
+    app_view: &AppSurface, mesh_coloring: &[MeshColoringObj], dynamic_offset: wgpu::BufferAddress,
+) -> BufferObj {
+    let buf = BufferObj::create_empty_dynamic_uniform_buffer(
+        &app_view.device,
+        mesh_coloring.len().max(1) as u64 * dynamic_offset,
+        None,
+        Some("mesh coloring buf"),
+    );
+    let mut offset = 0;
+    for mc in mesh_coloring.iter() {
+        app_view.queue.write_buffer(&buf.buffer, offset, mc.get_push_constants_data().as_bytes());
+        offset += dynamic_offset;
+    }
+    buf
+}
+
+fn dispatch_groups<'a, 'b: 'a>(
+    cpass: &mut wgpu::ComputePass<'a>, node: &'b ComputeNode, mesh_coloring: &[MeshColoringObj],
+) {
+    let dynamic_offset = 256;
+    cpass.set_pipeline(&node.pipeline);
+    cpass.set_bind_group(0, &node.bg_setting.bind_group, &[]);
+    for (index, mc) in mesh_coloring.iter().enumerate() {
+        if let Some(bg) = &node.dy_uniform_bg {
+            cpass.set_bind_group(1, &bg.bind_group, &[index as u32 * dynamic_offset]);
+        }
+        cpass.dispatch_workgroups(mc.thread_group.0, mc.thread_group.1, 1);
+    }
+}
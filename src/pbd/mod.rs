@@ -1,13 +1,34 @@
 mod particle;
 pub use particle::generate_cloth_particles;
+use particle::group_distance_constraints;
+
+mod graph_coloring;
+pub use graph_coloring::{color_constraints, mesh_coloring_from_groups, validate_coloring};
 
 mod cal_bend_constraints;
 use cal_bend_constraints::cal_bend_constraints2;
 
+mod cloth_config;
+pub use cloth_config::{ClothConfig, ClothMaterial, Compliance};
 mod cloth;
 pub use cloth::Cloth;
+mod cloth_interaction;
+use cloth_interaction::{
+    cloth_triangle_indices, is_bending_torn, regroup_torn_constraints, torn_pairs, ClothPicker,
+    TearReadback,
+};
+mod cloth_collision;
+pub use cloth_collision::{ClothCollision, ColliderObj, CollisionUniform};
+mod obj_mesh;
+pub use obj_mesh::{generate_mesh_particles, DihedralConstraintObj, ObjMesh};
+mod mesh_cloth;
+pub use mesh_cloth::MeshCloth;
 mod cloth_x;
 pub use cloth_x::ClothX;
+mod cloth_fluid;
+pub use cloth_fluid::ClothFluidCoupling;
+mod flag_player;
+pub use flag_player::FlagPlayer;
 
 mod bristle;
 mod brush;
@@ -15,6 +36,8 @@ pub use brush::MaoBrush;
 
 mod pbd_canvas;
 pub use pbd_canvas::PBDCanvas;
+mod cloth_canvas;
+pub use cloth_canvas::{ClothCanvas, MeshClothCanvas};
 
 use zerocopy::{AsBytes, FromBytes};
 
@@ -42,7 +65,27 @@ pub struct ClothUniform {
     triangle_num: i32,
     compliance: f32,
     dt: f32,
+    // 拉伸超过 rest_length * (1 + tear_threshold) 的约束会被撕裂，<= 0 时不撕裂
+    tear_threshold: f32,
+    bend_compliance: f32,
+    shear_compliance: f32,
+    gravity: [f32; 4],
+    // 速度阻尼
+    damping: f32,
+    padding: [f32; 3],
 }
+crate::util::uniform_layout!(ClothUniform {
+    num_x,
+    num_y,
+    triangle_num,
+    compliance,
+    dt,
+    tear_threshold,
+    bend_compliance,
+    shear_compliance,
+    gravity,
+    damping,
+});
 
 #[repr(C)]
 #[derive(Copy, Clone, AsBytes, FromBytes)]
@@ -57,6 +100,13 @@ pub struct BinUniform {
     max_bin_count: i32,
     padding: [f32; 3],
 }
+crate::util::uniform_layout!(BinUniform {
+    bin_num,
+    bin_max_index,
+    bin_size,
+    pos_offset,
+    max_bin_count,
+});
 
 // 拉伸 | 距离约束
 #[repr(C)]
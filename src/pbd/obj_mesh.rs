@@ -0,0 +1,486 @@
+use super::{
+    color_constraints, group_distance_constraints, mesh_coloring_from_groups,
+    particle::ParticleBufferObj, MeshColoringObj, StretchConstraintObj,
+};
+use crate::util::math::Point3D;
+use std::collections::HashMap;
+use zerocopy::{AsBytes, FromBytes};
+
+// Wavefront OBJ 三角网格，只解析 v, vt, f
+#[derive(Clone, Debug)]
+pub struct ObjMesh {
+    pub positions: Vec<[f32; 3]>,
+    // 与 positions 一一对应，共用同一位置的不同纹理坐标只保留第一个
+    pub uvs: Vec<[f32; 2]>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl ObjMesh {
+    pub fn from_path(path: &str) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut positions: Vec<[f32; 3]> = vec![];
+        let mut tex_coords: Vec<[f32; 2]> = vec![];
+        let mut uvs: Vec<Option<[f32; 2]>> = vec![];
+        let mut triangles: Vec<[u32; 3]> = vec![];
+
+        for (line_index, line) in source.lines().enumerate() {
+            let line_num = line_index + 1;
+            let mut items = line.split_whitespace();
+            match items.next() {
+                Some("v") => {
+                    let v = parse_floats::<3>(items, line_num)?;
+                    positions.push(v);
+                    uvs.push(None);
+                }
+                Some("vt") => {
+                    let vt = parse_floats::<2>(items, line_num)?;
+                    // webgpu 的纹理坐标是左上角为 0，0
+                    tex_coords.push([vt[0], 1.0 - vt[1]]);
+                }
+                Some("f") => {
+                    let mut face: Vec<u32> = vec![];
+                    for item in items {
+                        let mut refs = item.split('/');
+                        let v = resolve_index(refs.next(), positions.len(), line_num)?;
+                        if let Some(vt) = refs.next() {
+                            if !vt.is_empty() {
+                                let vt = resolve_index(Some(vt), tex_coords.len(), line_num)?;
+                                if uvs[v as usize].is_none() {
+                                    uvs[v as usize] = Some(tex_coords[vt as usize]);
+                                }
+                            }
+                        }
+                        face.push(v);
+                    }
+                    if face.len() < 3 {
+                        return Err(format!("line {}: face needs at least 3 vertices", line_num));
+                    }
+                    // 多边形按扇形拆分为三角形
+                    for i in 1..(face.len() - 1) {
+                        triangles.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if triangles.is_empty() {
+            return Err("mesh has no faces".to_string());
+        }
+
+        // 没有纹理坐标时，按 xy 包围盒生成
+        let (min, max) = bounding_box(&positions);
+        let uvs = uvs
+            .iter()
+            .zip(positions.iter())
+            .map(|(uv, p)| match uv {
+                Some(uv) => *uv,
+                None => [
+                    (p[0] - min[0]) / (max[0] - min[0]).max(f32::EPSILON),
+                    1.0 - (p[1] - min[1]) / (max[1] - min[1]).max(f32::EPSILON),
+                ],
+            })
+            .collect();
+
+        Ok(Self { positions, uvs, triangles })
+    }
+
+    // 缩放并平移到以原点为中心
+    pub fn fit_to(&mut self, half_size: f32) {
+        let (min, max) = bounding_box(&self.positions);
+        let center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0, (min[2] + max[2]) / 2.0];
+        let extent = (max[0] - min[0]).max(max[1] - min[1]).max(max[2] - min[2]) / 2.0;
+        let scale = half_size / extent.max(f32::EPSILON);
+        for p in self.positions.iter_mut() {
+            for i in 0..3 {
+                p[i] = (p[i] - center[i]) * scale;
+            }
+        }
+    }
+}
+
+fn parse_floats<'a, const N: usize>(
+    items: impl Iterator<Item = &'a str>, line_num: usize,
+) -> Result<[f32; N], String> {
+    let mut values = [0.0; N];
+    let mut count = 0;
+    for item in items.take(N) {
+        values[count] =
+            item.parse::<f32>().map_err(|e| format!("line {}: {} ({})", line_num, e, item))?;
+        count += 1;
+    }
+    if count < N {
+        return Err(format!("line {}: expected {} numbers", line_num, N));
+    }
+    Ok(values)
+}
+
+// OBJ 索引从 1 开始，负数表示从末尾倒数
+fn resolve_index(item: Option<&str>, len: usize, line_num: usize) -> Result<u32, String> {
+    let item = item.unwrap_or("");
+    let index = item.parse::<i64>().map_err(|e| format!("line {}: {} ({})", line_num, e, item))?;
+    let resolved = if index < 0 { len as i64 + index } else { index - 1 };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(format!("line {}: index {} out of range", line_num, index));
+    }
+    Ok(resolved as u32)
+}
+
+fn bounding_box(positions: &[[f32; 3]]) -> ([f32; 3], [f32; 3]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in positions.iter() {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    (min, max)
+}
+
+// 二面角弯曲约束
+// p0, p1 为两个三角形的公共边，p2, p3 为各自的对角顶点
+#[repr(C)]
+#[derive(Copy, Clone, AsBytes, FromBytes)]
+pub struct DihedralConstraintObj {
+    pub p0: i32,
+    pub p1: i32,
+    pub p2: i32,
+    pub p3: i32,
+    pub rest_angle: f32,
+    pub lambda: f32,
+    padding: [f32; 2],
+}
+
+impl DihedralConstraintObj {
+    fn particles(&self) -> [i32; 4] {
+        [self.p0, self.p1, self.p2, self.p3]
+    }
+}
+
+// 由网格生成的 pbd 数据
+pub struct MeshClothData {
+    pub particles: Vec<ParticleBufferObj>,
+    pub constraints: Vec<StretchConstraintObj>,
+    pub stretch_mesh_coloring: Vec<MeshColoringObj>,
+    pub reorder_constraints: Vec<[i32; 3]>,
+    pub dihedrals: Vec<DihedralConstraintObj>,
+    pub dihedral_mesh_coloring: Vec<MeshColoringObj>,
+    pub reorder_dihedrals: Vec<i32>,
+    pub indices: Vec<u32>,
+}
+
+// 从任意流形三角网格生成粒子，拉伸约束（网格的边）及二面角弯曲约束（共边的三角形对）
+// pinned: 质量为无穷大的粒子索引
+pub fn generate_mesh_particles(mesh: &ObjMesh, pinned: &[u32]) -> MeshClothData {
+    let points: Vec<Point3D> = mesh.positions.iter().map(|p| p.into()).collect();
+
+    // 每个顶点的质量等于与之相连的每个三角形面积的 1/3 之和
+    let mut vertex_area = vec![0.0_f32; points.len()];
+    for t in mesh.triangles.iter() {
+        let area =
+            triangle_area(&points[t[0] as usize], &points[t[1] as usize], &points[t[2] as usize]);
+        for i in t.iter() {
+            vertex_area[*i as usize] += area / 3.0;
+        }
+    }
+    // 归一化到与矩形布料相同的平均质量倒数 0.1
+    let used: Vec<f32> = vertex_area.iter().cloned().filter(|a| *a > 0.0).collect();
+    let avg_area = used.iter().sum::<f32>() / used.len().max(1) as f32;
+
+    let mut particles: Vec<ParticleBufferObj> = Vec::with_capacity(points.len());
+    for (i, p) in mesh.positions.iter().enumerate() {
+        let invert_mass = if pinned.contains(&(i as u32)) || vertex_area[i] <= 0.0 {
+            0.0
+        } else {
+            0.1 * avg_area / vertex_area[i]
+        };
+        let pos = [p[0], p[1], p[2], 0.0];
+        particles.push(ParticleBufferObj {
+            pos,
+            old_pos: pos,
+            accelerate: [0.0; 4],
+            uv_mass: [mesh.uvs[i][0], mesh.uvs[i][1], invert_mass, 0.0],
+            connect: [i as i32; 4],
+        })
+    }
+    // 法线计算使用与之相连的任意一个三角形
+    // 着色器里的法线为 cross(connect[1] - p, connect[0] - p)
+    for t in mesh.triangles.iter() {
+        for k in 0..3 {
+            let (p, a, b) = (t[k] as usize, t[(k + 1) % 3] as i32, t[(k + 2) % 3] as i32);
+            if particles[p].connect[0] == p as i32 {
+                particles[p].connect = [b, a, b, a];
+            }
+        }
+    }
+
+    // 边 -> 共享此边的三角形的对角顶点
+    let mut edges: HashMap<(u32, u32), Vec<u32>> = HashMap::new();
+    let mut edge_order: Vec<(u32, u32)> = vec![];
+    for t in mesh.triangles.iter() {
+        for k in 0..3 {
+            let (a, b, opposite) = (t[k], t[(k + 1) % 3], t[(k + 2) % 3]);
+            let key = if a < b { (a, b) } else { (b, a) };
+            let entry = edges.entry(key).or_insert_with(|| {
+                edge_order.push(key);
+                vec![]
+            });
+            entry.push(opposite);
+        }
+    }
+
+    let mut constraints: Vec<StretchConstraintObj> = Vec::with_capacity(edge_order.len());
+    let mut dihedrals: Vec<DihedralConstraintObj> = vec![];
+    // 以 particle0 归集拉伸约束，每组最多 3 个，再由图着色分组
+    let mut owned: Vec<Vec<i32>> = vec![vec![]; points.len()];
+    for (a, b) in edge_order.iter() {
+        let (p0, p1) = (&points[*a as usize], &points[*b as usize]);
+        owned[*a as usize].push(constraints.len() as i32);
+        constraints.push(StretchConstraintObj {
+            rest_length: p0.minus(p1).length(),
+            lambda: 0.0,
+            particle0: *a as i32,
+            particle1: *b as i32,
+        });
+        let opposites = &edges[&(*a, *b)];
+        // 非流形的边（超过两个三角形共享）不生成弯曲约束
+        if opposites.len() == 2 {
+            let (p2, p3) = (&points[opposites[0] as usize], &points[opposites[1] as usize]);
+            dihedrals.push(DihedralConstraintObj {
+                p0: *a as i32,
+                p1: *b as i32,
+                p2: opposites[0] as i32,
+                p3: opposites[1] as i32,
+                rest_angle: dihedral_angle(p0, p1, p2, p3),
+                lambda: 0.0,
+                padding: [0.0; 2],
+            });
+        }
+    }
+    let mut particle_constraints: Vec<[i32; 3]> = vec![];
+    for list in owned.iter() {
+        for chunk in list.chunks(3) {
+            let mut group = [-1; 3];
+            group[..chunk.len()].copy_from_slice(chunk);
+            particle_constraints.push(group);
+        }
+    }
+    let (stretch_mesh_coloring, reorder_constraints) =
+        group_distance_constraints(&constraints, &particle_constraints);
+    let (dihedral_mesh_coloring, reorder_dihedrals) =
+        group_dihedral_constraints(&dihedrals, points.len());
+
+    let indices = mesh.triangles.iter().flat_map(|t| t.iter().cloned()).collect();
+
+    MeshClothData {
+        particles,
+        constraints,
+        stretch_mesh_coloring,
+        reorder_constraints,
+        dihedrals,
+        dihedral_mesh_coloring,
+        reorder_dihedrals,
+        indices,
+    }
+}
+
+fn triangle_area(p0: &Point3D, p1: &Point3D, p2: &Point3D) -> f32 {
+    p1.minus(p0).cross(&p2.minus(p0)).length() / 2.0
+}
+
+// 与 cloth_dihedral_solver.wgsl 相同的角度定义
+fn dihedral_angle(p0: &Point3D, p1: &Point3D, p2: &Point3D, p3: &Point3D) -> f32 {
+    let e = p1.minus(p0);
+    let n1 = e.cross(&p2.minus(p0));
+    let n2 = e.cross(&p3.minus(p0));
+    let len = n1.length() * n2.length();
+    if len <= f32::EPSILON {
+        return std::f32::consts::PI;
+    }
+    let d = n1.dot(&n2) / len;
+    d.clamp(-1.0, 1.0).acos()
+}
+
+fn group_dihedral_constraints(
+    dihedrals: &[DihedralConstraintObj], particle_count: usize,
+) -> (Vec<MeshColoringObj>, Vec<i32>) {
+    let indices: Vec<i32> = (0..dihedrals.len() as i32).collect();
+    let groups = color_constraints(
+        &indices,
+        particle_count,
+        |i| dihedrals[*i as usize].particles().to_vec(),
+        true,
+    );
+    mesh_coloring_from_groups(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pbd::validate_coloring;
+
+    const QUAD: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn parse_triangles_and_uvs() {
+        let source = "# comment\nv 0 0 0\nv 2 0 0\nv 0 1 0\nvt 0.25 0.75\nf 1/1 2 3\n";
+        let mesh = ObjMesh::parse(source).unwrap();
+        assert_eq!(mesh.positions, vec![[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2]]);
+        // vt 的 v 轴被翻转；没有纹理坐标的顶点按 xy 包围盒生成
+        assert_eq!(mesh.uvs, vec![[0.25, 0.25], [1.0, 1.0], [0.0, 0.0]]);
+    }
+
+    #[test]
+    fn split_polygon_as_fan() {
+        let mesh = ObjMesh::parse(&format!("{}v 0.5 1.5 0\nf 1 2 3 5 4\n", QUAD)).unwrap();
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 4], [0, 4, 3]]);
+    }
+
+    #[test]
+    fn resolve_negative_indices() {
+        let mesh = ObjMesh::parse(&format!("{}f -4 -3 -2 -1\n", QUAD)).unwrap();
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn reject_malformed_lines() {
+        let err = ObjMesh::parse("v 0 x 0\n").unwrap_err();
+        assert!(err.starts_with("line 1:"), "{}", err);
+        assert_eq!(ObjMesh::parse("v 0 0\n").unwrap_err(), "line 1: expected 3 numbers");
+        assert_eq!(
+            ObjMesh::parse(&format!("{}f 1 2\n", QUAD)).unwrap_err(),
+            "line 5: face needs at least 3 vertices"
+        );
+        assert_eq!(
+            ObjMesh::parse(&format!("{}f 1 2 5\n", QUAD)).unwrap_err(),
+            "line 5: index 5 out of range"
+        );
+        assert_eq!(
+            ObjMesh::parse(&format!("{}f 1 2 -5\n", QUAD)).unwrap_err(),
+            "line 5: index -5 out of range"
+        );
+        assert_eq!(ObjMesh::parse(QUAD).unwrap_err(), "mesh has no faces");
+    }
+
+    // n x n 个顶点的平面网格，每个格子是一个四边形面
+    fn grid(n: u32) -> ObjMesh {
+        let mut source = String::new();
+        for y in 0..n {
+            for x in 0..n {
+                source += &format!("v {} {} 0\n", x, y);
+            }
+        }
+        for y in 0..(n - 1) {
+            for x in 0..(n - 1) {
+                let i = y * n + x + 1;
+                source += &format!("f {} {} {} {}\n", i, i + 1, i + n + 1, i + n);
+            }
+        }
+        ObjMesh::parse(&source).unwrap()
+    }
+
+    fn groups_of<T: Copy>(colorings: &[MeshColoringObj], flat: &[T]) -> Vec<Vec<T>> {
+        colorings
+            .iter()
+            .map(|c| flat[c.offset as usize..(c.offset + c.group_len) as usize].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn shared_edges_are_deduplicated() {
+        let mesh = ObjMesh::parse(&format!("{}f 1 2 3 4\n", QUAD)).unwrap();
+        let data = generate_mesh_particles(&mesh, &[]);
+        let mut edges: Vec<(i32, i32)> =
+            data.constraints.iter().map(|c| (c.particle0, c.particle1)).collect();
+        edges.sort();
+        assert_eq!(edges, vec![(0, 1), (0, 2), (0, 3), (1, 2), (2, 3)]);
+        let diagonal = data.constraints.iter().find(|c| c.particle1 - c.particle0 == 2).unwrap();
+        assert!((diagonal.rest_length - 2.0_f32.sqrt()).abs() < 1e-6);
+
+        // 唯一的内部边是对角线，平面上的二面角为 π
+        assert_eq!(data.dihedrals.len(), 1);
+        let d = &data.dihedrals[0];
+        assert_eq!((d.p0, d.p1, d.p2, d.p3), (0, 2, 1, 3));
+        assert!((d.rest_angle - std::f32::consts::PI).abs() < 1e-6);
+        assert_eq!(data.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn one_dihedral_per_interior_edge() {
+        // 四面体的每条边都被两个三角形共享
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 3 2\nf 1 2 4\nf 2 3 4\nf 1 4 3\n";
+        let data = generate_mesh_particles(&ObjMesh::parse(source).unwrap(), &[]);
+        assert_eq!(data.constraints.len(), 6);
+        assert_eq!(data.dihedrals.len(), 6);
+        let mut shared: Vec<(i32, i32)> = vec![];
+        for d in data.dihedrals.iter() {
+            let mut particles = d.particles().to_vec();
+            particles.sort();
+            assert_eq!(particles, vec![0, 1, 2, 3]);
+            shared.push((d.p0, d.p1));
+        }
+        shared.sort();
+        shared.dedup();
+        assert_eq!(shared.len(), 6);
+    }
+
+    #[test]
+    fn non_manifold_edge_has_no_dihedral() {
+        // 三个三角形共享边 1-2，其余的边都是边界
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\nf 1 2 3\nf 2 1 4\nf 1 2 5\n";
+        let data = generate_mesh_particles(&ObjMesh::parse(source).unwrap(), &[]);
+        assert_eq!(data.constraints.len(), 7);
+        assert!(data.dihedrals.is_empty());
+        assert!(data.dihedral_mesh_coloring.is_empty());
+    }
+
+    #[test]
+    fn pinned_and_unused_particles_have_infinite_mass() {
+        // 第 5 个顶点不属于任何三角形
+        let mesh = ObjMesh::parse(&format!("{}v 2 2 2\nf 1 2 3 4\n", QUAD)).unwrap();
+        let data = generate_mesh_particles(&mesh, &[0]);
+        let invert_mass: Vec<f32> = data.particles.iter().map(|p| p.uv_mass[2]).collect();
+        assert_eq!(invert_mass[0], 0.0);
+        assert_eq!(invert_mass[4], 0.0);
+        // 质量与相连三角形的面积成正比，平均质量倒数为 0.1
+        assert!((invert_mass[1] - 0.15).abs() < 1e-6);
+        assert!((invert_mass[2] - 0.075).abs() < 1e-6);
+        assert!((invert_mass[3] - 0.15).abs() < 1e-6);
+    }
+
+    #[test]
+    fn colorings_are_independent() {
+        let mesh = grid(5);
+        let particle_count = mesh.positions.len();
+        let data = generate_mesh_particles(&mesh, &[]);
+        assert_eq!(data.dihedrals.len(), 16 + 2 * 12);
+
+        let stretch_groups = groups_of(&data.stretch_mesh_coloring, &data.reorder_constraints);
+        let stretch_particles = |pcs: &[i32; 3]| {
+            let mut list: Vec<i32> = vec![];
+            for c in pcs.iter().filter(|c| **c >= 0) {
+                let constraint = &data.constraints[*c as usize];
+                list.push(constraint.particle0);
+                list.push(constraint.particle1);
+            }
+            list
+        };
+        assert!(validate_coloring(&stretch_groups, particle_count, stretch_particles).is_ok());
+        let mut stretch: Vec<i32> =
+            data.reorder_constraints.iter().flatten().cloned().filter(|c| *c >= 0).collect();
+        stretch.sort();
+        assert_eq!(stretch, (0..data.constraints.len() as i32).collect::<Vec<i32>>());
+
+        let dihedral_groups = groups_of(&data.dihedral_mesh_coloring, &data.reorder_dihedrals);
+        let dihedral_particles = |i: &i32| data.dihedrals[*i as usize].particles().to_vec();
+        assert!(validate_coloring(&dihedral_groups, particle_count, dihedral_particles).is_ok());
+        let mut dihedrals = data.reorder_dihedrals.clone();
+        dihedrals.sort();
+        assert_eq!(dihedrals, (0..data.dihedrals.len() as i32).collect::<Vec<i32>>());
+    }
+}
@@ -1,4 +1,7 @@
-use super::{cal_bend_constraints2, BendingConstraintObj, MeshColoringObj, StretchConstraintObj};
+use super::{
+    cal_bend_constraints2, color_constraints, mesh_coloring_from_groups, validate_coloring,
+    BendingConstraintObj, MeshColoringObj, StretchConstraintObj,
+};
 use crate::util::math::Point3D;
 use zerocopy::{AsBytes, FromBytes};
 
@@ -18,7 +21,7 @@ pub struct ParticleBufferObj {
 
 pub fn generate_cloth_particles(
     horizontal_num: usize, vertical_num: usize, horizontal_pixel: f32, vertical_pixel: f32,
-    a_pixel_on_ndc: f32,
+    a_pixel_on_ndc: f32, areal_density: Option<f32>,
 ) -> (
     (f32, f32),
     Vec<ParticleBufferObj>,
@@ -41,7 +44,12 @@ pub fn generate_cloth_particles(
 
     let tl_x = (-horizontal_step) * ((horizontal_num - 1) as f32 / 2.0);
     let tl_y = vertical_step * ((vertical_num - 1) as f32 / 2.0);
-    let mut invert_mass = 0.1;
+    // 内部粒子的质量等于一个网格单元的质量
+    let inner_invert_mass = match areal_density {
+        Some(density) => 1.0 / (density * horizontal_step * vertical_step),
+        None => 0.1,
+    };
+    let mut invert_mass;
     for h in 0..vertical_num {
         for w in 0..horizontal_num {
             let p =
@@ -54,16 +62,15 @@ pub fn generate_cloth_particles(
                 invert_mass = 0.0;
             } else if w == 0 || w == (horizontal_num - 1) || h == (vertical_num - 1) {
                 // 边界上的点，只有两个三角形与之相连
-                invert_mass = 0.2;
+                invert_mass = inner_invert_mass * 2.0;
             } else {
-                invert_mass = 0.1;
+                invert_mass = inner_invert_mass;
             }
             particles.push(ParticleBufferObj {
                 pos: p,
                 old_pos: p,
-                // 重力加速度不能太小，会导致布料飘来飘去，没有重量感
-                // accelerate: [0.0, 0.0, 0.0, 0.0],
-                accelerate: [0.0, -3.98, 0.0, 0.0],
+                // 重力由 ClothUniform 统一施加，这里只保存额外的外力加速度
+                accelerate: [0.0, 0.0, 0.0, 0.0],
                 // webgpu 的纹理坐标是左上角为 0，0
                 uv_mass: [uv_x_step * w as f32, uv_y_step * h as f32, invert_mass, 0.0],
                 connect: [0; 4],
@@ -233,226 +240,22 @@ fn get_constraint(
     }
 }
 
-// 计算弯曲约束，每一个顶点有三对约束?
-// 按横向遍历再纵向遍历创建约束，可避免重复
-fn cal_bend_constraints(
-    horizontal_num: usize, vertical_num: usize,
-) -> (Vec<MeshColoringObj>, Vec<BendingConstraintObj>, Vec<[i32; 3]>) {
-    // 共享同一顶点的三个三角形对
-    let mut index_groups: Vec<[i32; 3]> = vec![];
-    let mut bendings: Vec<BendingConstraintObj> =
-        Vec::with_capacity(horizontal_num * vertical_num * 3);
-    let mut reorder_bendings: Vec<[i32; 3]> = vec![];
-    // 按没有共同顶点的约束分组，再基于此生成 reorder_bendings
-    let mut bending_groups: Vec<Vec<[i32; 3]>> = vec![vec![]];
-
-    let h0: f32 = 0.0;
-    for h in 0..vertical_num {
-        for w in 0..horizontal_num {
-            let v = (h * horizontal_num + w) as i32;
-            let mut a_group: [i32; 3] = [-1; 3];
-            if w > 0 && w < (horizontal_num - 1) {
-                // 水平约束
-                let b0 = v - 1;
-                let b1 = v + 1;
-                a_group[0] = bendings.len() as i32;
-                bendings.push(BendingConstraintObj { v, b0, b1, h0 });
-            }
-            if h > 0 && h < (vertical_num - 1) {
-                // 垂直约束
-                let b0 = v - horizontal_num as i32;
-                let b1 = v + horizontal_num as i32;
-                a_group[1] = bendings.len() as i32;
-                bendings.push(BendingConstraintObj { v, b0, b1, h0 });
-            }
-            if w > 0 && w < (horizontal_num - 1) && h > 0 && h < (vertical_num - 1) {
-                // 斜向约束
-                let b0 = v + 1 - horizontal_num as i32;
-                let b1 = v - 1 + horizontal_num as i32;
-                a_group[2] = bendings.len() as i32;
-                bendings.push(BendingConstraintObj { v, b0, b1, h0 });
-            }
-
-            index_groups.push(a_group);
-        }
-    }
-    // 创建分组
-    'outter0: for p in index_groups.iter() {
-        'outter1: for a_group in bending_groups.iter_mut() {
-            'inner0: for a_p in a_group.iter() {
-                'inner1: for i in p.iter() {
-                    if i.clone() == -1 {
-                        continue 'inner1;
-                    }
-                    let c = &bendings[i.clone() as usize];
-                    'inner2: for j in a_p.iter() {
-                        if j.clone() == -1 {
-                            continue 'inner2;
-                        }
-                        let b = &bendings[j.clone() as usize];
-                        if b.is_contain(&c) {
-                            continue 'outter1;
-                        }
-                    }
-                }
-            }
-            // 如果分组里没有此约束组，则加入
-            a_group.push(p.clone());
-            continue 'outter0;
-        }
-        let new_group: Vec<[i32; 3]> = vec![p.clone()];
-        bending_groups.push(new_group);
-    }
-
-    let mut mesh_colorings: Vec<MeshColoringObj> = vec![];
-    let mut offset = 0;
-    let step: u32 = 8;
-    for a_group in bending_groups.iter_mut() {
-        println!("bend group len: {}", a_group.len());
-        let group_len = a_group.len() as u32;
-        let mut max_num_x: u32 = 0;
-        let mut max_num_y: u32 = 0;
-        for i in 1..100 {
-            max_num_x = i * step;
-            if max_num_x * max_num_y >= group_len {
-                max_num_x = cal_real_max_num(max_num_x, max_num_y, group_len);
-                break;
-            }
-            max_num_y = i * step;
-            if max_num_x * max_num_y >= group_len {
-                max_num_y = cal_real_max_num(max_num_y, max_num_x, group_len);
-                break;
-            }
-        }
-        mesh_colorings.push(MeshColoringObj {
-            offset,
-            max_num_x,
-            max_num_y,
-            group_len,
-            thread_group: (((group_len + 31) as f32 / 32.0).floor() as u32, 1),
-        });
-        reorder_bendings.append(a_group);
-        offset += group_len;
-    }
-    (mesh_colorings, bendings, reorder_bendings)
-}
-
-fn group_distance_constraints(
+pub fn group_distance_constraints(
     constraints: &[StretchConstraintObj], particle_constraints: &Vec<[i32; 3]>,
 ) -> (Vec<MeshColoringObj>, Vec<[i32; 3]>) {
-    let mut groups: Vec<Vec<[i32; 3]>> = vec![vec![]];
-
-    'outer: for pcs in particle_constraints.iter() {
-        'inner: for a_group in groups.iter_mut() {
-            if is_group_contain_pcs(constraints, a_group, pcs) {
-                continue 'inner;
-            } else {
-                a_group.push(pcs.clone());
-                continue 'outer;
-            }
+    let particle_count =
+        constraints.iter().map(|c| c.particle0.max(c.particle1) as usize + 1).max().unwrap_or(0);
+    // 一个约束组内的全部约束共用 particle0，组内所有约束涉及的粒子都不能与其它组重叠
+    let particles_of = |pcs: &[i32; 3]| {
+        let mut list: Vec<i32> = vec![];
+        for c in pcs.iter().filter(|c| **c >= 0) {
+            let constraint = &constraints[*c as usize];
+            list.push(constraint.particle0);
+            list.push(constraint.particle1);
         }
-        let mut a_group: Vec<[i32; 3]> = vec![];
-        a_group.push(pcs.clone());
-        groups.push(a_group);
-    }
-    // 不同的归组方法得到的数组不一样
-    // 直接从头到尾遍历，会使得分组更多, 且每组数据量差异极大（最后一组更可能只包含一个粒子的约束组）
-    // 由于当前使用的约束关系最远只到邻居的邻居，按照 3 * n + 1 <= vertical_num 做为分割来分两次遍历
-    // 得到的分组数落在 【9，12】区间
-    // let mult = horizontal_num / 2;
-    // let row = if (mult * 2 + 1) > horizontal_num { (mult - 1) * 2 + 1 } else { mult * 2 + 1 };
-    // for h in 0..vertical_num {
-    //     for w in 0..row {
-    //         let index = h * horizontal_num + w;
-    //         iter_groups(index, constraints, particle_constraints, &mut groups);
-    //     }
-    // }
-    // for h in 0..vertical_num {
-    //     for w in row..horizontal_num {
-    //         let index = h * horizontal_num + w;
-    //         iter_groups(index, constraints, particle_constraints, &mut groups);
-    //     }
-    // }
-
-    let mut mesh_colorings: Vec<MeshColoringObj> = vec![];
-    let mut reorder_constraints: Vec<[i32; 3]> = vec![];
-    println!("组数： {}", groups.len());
-    let mut offset = 0;
-    for a_group in groups.iter_mut() {
-        println!("group len: {}, offset: {}", a_group.len(), offset);
-
-        let group_len = a_group.len() as u32;
-        mesh_colorings.push(MeshColoringObj {
-            offset,
-            max_num_x: 0,
-            max_num_y: 0,
-            group_len,
-            thread_group: (((group_len + 31) as f32 / 32.0).floor() as u32, 1),
-        });
-        reorder_constraints.append(a_group);
-        offset += group_len;
-    }
-    (mesh_colorings, reorder_constraints)
-}
-
-fn iter_groups(
-    index: usize, constraints: &[StretchConstraintObj], particle_constraints: &Vec<[i32; 3]>,
-    groups: &mut Vec<Vec<[i32; 3]>>,
-) {
-    let pcs = &particle_constraints[index];
-    let mut need_new_group = true;
-    for a_group in groups.iter_mut() {
-        if is_group_contain_pcs(constraints, a_group, pcs) {
-            continue;
-        } else {
-            a_group.push(pcs.clone());
-            need_new_group = false;
-            break;
-        }
-    }
-    if need_new_group {
-        let mut a_group: Vec<[i32; 3]> = vec![];
-        a_group.push(pcs.clone());
-        groups.push(a_group);
-    }
-}
-
-// 计算 thread_group 维度
-fn cal_real_max_num(max_num: u32, other: u32, group_len: u32) -> u32 {
-    for i in 0..8 {
-        let val = max_num - i;
-        if val * other == group_len {
-            return val;
-        } else if val * other < group_len {
-            return max_num - i + 1;
-        }
-    }
-    return max_num - 7;
-}
-
-fn is_group_contain_pcs(
-    constraints: &[StretchConstraintObj], a_group: &Vec<[i32; 3]>, pcs: &[i32],
-) -> bool {
-    let mut is_contain = false;
-    'outer: for c in pcs.iter() {
-        let c = c.clone();
-        if c == -1 {
-            continue;
-        }
-        let constraint0 = &constraints[c as usize];
-        for ag in a_group.iter() {
-            for a in ag.iter() {
-                let a = a.clone();
-                if a == -1 {
-                    continue;
-                }
-                let constraint1 = &constraints[a as usize];
-                if constraint1.is_contain_particles(constraint0) {
-                    is_contain = true;
-                    break 'outer;
-                }
-            }
-        }
-    }
-    is_contain
+        list
+    };
+    let groups = color_constraints(particle_constraints, particle_count, particles_of, true);
+    debug_assert!(validate_coloring(&groups, particle_count, particles_of).is_ok());
+    mesh_coloring_from_groups(groups)
 }
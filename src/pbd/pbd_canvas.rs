@@ -1,4 +1,7 @@
 use super::MaoBrush;
+use crate::util::{resource_pool, FrameRecorder};
+use crate::{SimClock, SIM_STEPS_PER_SECOND};
+use std::path::PathBuf;
 
 use app_surface::{math::Position, AppSurface, SurfaceFrame, Touch, TouchPhase};
 
@@ -6,22 +9,79 @@ pub struct PBDCanvas {
     dc_origin: Position,
     pub app_view: AppSurface,
     pub pbd_node: MaoBrush,
+    recorder: Option<FrameRecorder>,
+    clock: SimClock,
+    // 不足一个参考帧的模拟步数
+    pending_steps: u32,
 }
 
 #[allow(dead_code)]
 impl PBDCanvas {
     pub fn new(app_view: AppSurface) -> Self {
+        // 池里的资源属于之前的 device
+        resource_pool::clear();
         let dc_origin =
             Position::new(app_view.config.width as f32 / 2.0, app_view.config.height as f32 / 2.0);
         let pbd_node = MaoBrush::new(&app_view);
         // let pbd_node = Line::new(&app_view);
 
-        Self { dc_origin, app_view, pbd_node }
+        Self {
+            dc_origin,
+            app_view,
+            pbd_node,
+            recorder: None,
+            clock: SimClock::new(SIM_STEPS_PER_SECOND),
+            pending_steps: 0,
+        }
+    }
+
+    // 暂停后仍会重绘，只是不再推进模拟
+    pub fn pause(&mut self) {
+        self.clock.pause();
+        self.pending_steps = 0;
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    // 暂停状态下推进 n 个模拟步
+    pub fn step(&mut self, n: u32) {
+        self.clock.step(n);
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.clock.set_time_scale(scale);
+    }
+
+    // 开始录制 PNG 图片序列，每绘制一帧保存一张
+    // 录制期间模拟仍按真实时间推进，图片序列的播放帧率在合成视频时决定
+    pub fn start_recording(&mut self, output_dir: PathBuf) -> Result<(), String> {
+        self.stop_recording();
+        self.recorder = Some(FrameRecorder::new(&mut self.app_view, output_dir)?);
+        Ok(())
+    }
+
+    // 停止录制，返回录制的帧数
+    pub fn stop_recording(&mut self) -> u32 {
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(&mut self.app_view),
+            None => 0,
+        }
     }
 }
 
 impl SurfaceFrame for PBDCanvas {
-    fn resize_surface(&mut self) {}
+    fn resize_surface(&mut self) {
+        if self.recorder.is_some() {
+            let count = self.stop_recording();
+            crate::console_log!("frame recording stopped by resize after {} frames", count);
+        }
+    }
     fn touch(&mut self, touch: Touch) {
         match touch.phase {
             TouchPhase::Moved => {
@@ -38,6 +98,21 @@ impl SurfaceFrame for PBDCanvas {
     }
 
     fn enter_frame(&mut self) {
-        self.pbd_node.enter_frame(&mut self.app_view);
+        let changed = crate::util::changed_shaders();
+        if !changed.is_empty() {
+            self.pbd_node.reload_shaders(&self.app_view.device, &changed);
+        }
+        let frames = crate::sim_clock::steps_to_frames(&mut self.pending_steps, self.clock.tick());
+        let (frame, frame_view) = self.app_view.get_current_frame_view();
+        self.pbd_node.draw_frame(&self.app_view, &frame_view, frames);
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) =
+                recorder.capture(&self.app_view.device, &self.app_view.queue, &frame.texture)
+            {
+                crate::console_log!("frame recording stopped: {}", e);
+                self.stop_recording();
+            }
+        }
+        frame.present();
     }
 }
@@ -1,4 +1,4 @@
-use crate::util::BufferObj;
+use crate::util::{BufferObj, UniformBuffer};
 use crate::{
     get_particles_data, FieldAnimationType, FieldType, ParticleColorType, ParticleUniform,
 };
@@ -15,7 +15,7 @@ pub struct SettingObj {
     pub particles_count: i32,
     pub particle_lifetime: f32,
     pub particles_uniform_data: ParticleUniform,
-    pub particles_uniform: Option<BufferObj>,
+    pub particles_uniform: Option<UniformBuffer<ParticleUniform>>,
     pub particles_buf: Option<BufferObj>,
     pub particles_size: wgpu::Extent3d,
     pub particles_threadgroup: (u32, u32, u32),
@@ -29,7 +29,7 @@ impl SettingObj {
         SettingObj {
             canvas_size: (0_u32, 0_u32).into(),
             field_type,
-            animation_type,
+            animation_type: fit_animation_type(field_type, animation_type),
             fluid_viscosity: 0.02,
             color_ty,
             particles_count,
@@ -62,6 +62,17 @@ impl SettingObj {
         }
     }
 
+    // 旗帜场景只在风洞里模拟，其它动画类型会被忽略
+    pub fn update_animation_type(&mut self, ty: FieldAnimationType) -> bool {
+        let ty = fit_animation_type(self.field_type, ty);
+        if self.field_type == FieldType::ClothInFluid && self.animation_type == ty {
+            false
+        } else {
+            self.animation_type = ty;
+            true
+        }
+    }
+
     pub fn update_canvas_size(
         &mut self, device: &wgpu::Device, queue: &wgpu::Queue, canvas_size: Size<u32>,
     ) {
@@ -83,6 +94,7 @@ impl SettingObj {
         if self.particles_uniform_data.color_ty == color_type as i32 {
             return;
         }
+        self.color_ty = color_type;
         self.particles_uniform_data.color_ty = color_type as i32;
         self.update_particles_uniform(queue);
     }
@@ -95,12 +107,9 @@ impl SettingObj {
         self.update_particles_uniform(queue);
     }
 
-    pub fn update_particles_uniform(&self, queue: &wgpu::Queue) {
-        queue.write_buffer(
-            &self.particles_uniform.as_ref().unwrap().buffer,
-            0,
-            self.particles_uniform_data.as_bytes(),
-        );
+    pub fn update_particles_uniform(&mut self, queue: &wgpu::Queue) {
+        let data = self.particles_uniform_data;
+        self.particles_uniform.as_mut().unwrap().update(queue, |uniform| *uniform = data);
     }
 
     fn update_particles_data(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
@@ -121,11 +130,19 @@ impl SettingObj {
                 wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
                 Some("particles_buf"),
             ));
-            self.particles_uniform = Some(BufferObj::create_uniform_buffer(
+            self.particles_uniform = Some(UniformBuffer::new(
                 device,
-                &self.particles_uniform_data,
+                self.particles_uniform_data,
                 Some("particle_uniform"),
             ));
         }
     }
 }
+
+fn fit_animation_type(field_type: FieldType, ty: FieldAnimationType) -> FieldAnimationType {
+    if field_type == FieldType::ClothInFluid {
+        FieldAnimationType::WindTunnel
+    } else {
+        ty
+    }
+}
@@ -0,0 +1,222 @@
+use crate::fluid::LBM_STEPS_PER_FRAME;
+
+// 参考帧率 60 fps 下，每帧固定执行 LBM_STEPS_PER_FRAME 个模拟步
+pub const SIM_STEPS_PER_SECOND: u32 = 60 * LBM_STEPS_PER_FRAME;
+
+// 与帧率无关的模拟时钟
+// 累积真实流逝的时间，每帧按固定步长换算出需要执行的模拟步数，
+// 这样 120Hz 与 60Hz 的设备上，模拟的快慢是一致的
+#[derive(Clone)]
+pub struct SimClock {
+    // 单个模拟步对应的秒数
+    step_dt: f64,
+    accumulator: f64,
+    last_time: Option<f64>,
+    time_scale: f32,
+    // 单帧最多追赶的步数，避免卡顿后模拟越追越慢
+    pub max_steps_per_frame: u32,
+    paused: bool,
+    // 暂停状态下，单步执行请求的步数
+    pending_steps: u32,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new(SIM_STEPS_PER_SECOND)
+    }
+}
+
+impl SimClock {
+    pub fn new(steps_per_second: u32) -> Self {
+        Self {
+            step_dt: 1.0 / steps_per_second as f64,
+            accumulator: 0.0,
+            last_time: None,
+            time_scale: 1.0,
+            // 最多追赶 4 个参考帧
+            max_steps_per_frame: LBM_STEPS_PER_FRAME * 4,
+            paused: false,
+            pending_steps: 0,
+        }
+    }
+
+    // 每帧调用一次，返回本帧需要执行的模拟步数
+    pub fn tick(&mut self) -> u32 {
+        self.tick_at(now_secs())
+    }
+
+    // now 为当前时间（秒），时间由调用方提供时可以精确地驱动时钟
+    pub fn tick_at(&mut self, now: f64) -> u32 {
+        // 第一帧按参考帧长计算
+        let elapsed = match self.last_time {
+            Some(last) => (now - last).max(0.0),
+            None => 1.0 / 60.0,
+        };
+        self.last_time = Some(now);
+
+        if self.paused {
+            let steps = self.pending_steps;
+            self.pending_steps = 0;
+            return steps;
+        }
+
+        self.accumulator += elapsed * self.time_scale as f64;
+        let steps = (self.accumulator / self.step_dt).floor() as u32;
+        if steps > self.max_steps_per_frame {
+            // 追不上的时间直接丢弃
+            self.accumulator = 0.0;
+            self.max_steps_per_frame
+        } else {
+            self.accumulator -= steps as f64 * self.step_dt;
+            steps
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.pending_steps = 0;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        // 暂停期间流逝的时间不计入模拟
+        self.accumulator = 0.0;
+        self.last_time = None;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // 暂停状态下，下一帧执行 n 个模拟步
+    pub fn step(&mut self, n: u32) {
+        if !self.paused {
+            self.pause();
+        }
+        self.pending_steps += n;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.max(0.0);
+    }
+
+    // 丢弃累积的时间，用于重建 player 之后
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last_time = None;
+        self.pending_steps = 0;
+    }
+}
+
+// 把模拟步数换算为参考帧数，不足一帧的步数累积到下一次
+// 用于布料、粒子等按参考帧推进的模拟
+pub fn steps_to_frames(pending_steps: &mut u32, steps: u32) -> u32 {
+    *pending_steps += steps;
+    let frames = *pending_steps / LBM_STEPS_PER_FRAME;
+    *pending_steps %= LBM_STEPS_PER_FRAME;
+    frames
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_secs() -> f64 {
+    lazy_static::lazy_static! {
+        static ref START: std::time::Instant = std::time::Instant::now();
+    }
+    START.elapsed().as_secs_f64()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_secs() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 64 步每秒，1/64 秒的倍数可以精确表示
+    fn started_clock() -> SimClock {
+        let mut clock = SimClock::new(64);
+        clock.max_steps_per_frame = 1000;
+        // 第一帧按 1/60 秒计算，执行 1 步
+        assert_eq!(clock.tick_at(10.0), 1);
+        clock
+    }
+
+    #[test]
+    fn steps_follow_elapsed_time() {
+        let mut clock = started_clock();
+        assert_eq!(clock.tick_at(10.5), 32);
+        assert_eq!(clock.tick_at(10.5), 0);
+        // 时间倒退时不执行
+        assert_eq!(clock.tick_at(9.0), 0);
+    }
+
+    #[test]
+    fn time_scale_changes_step_rate() {
+        let mut clock = started_clock();
+        clock.set_time_scale(0.5);
+        assert_eq!(clock.tick_at(11.0), 32);
+        clock.set_time_scale(2.0);
+        assert_eq!(clock.tick_at(11.5), 64);
+        clock.set_time_scale(-1.0);
+        assert_eq!(clock.time_scale(), 0.0);
+        assert_eq!(clock.tick_at(12.5), 0);
+    }
+
+    #[test]
+    fn catch_up_is_clamped() {
+        let mut clock = started_clock();
+        clock.max_steps_per_frame = 8;
+        assert_eq!(clock.tick_at(20.0), 8);
+        // 追不上的时间被丢弃，之后按正常速度推进
+        assert_eq!(clock.tick_at(20.0625), 4);
+    }
+
+    #[test]
+    fn step_while_paused() {
+        let mut clock = started_clock();
+        clock.pause();
+        assert!(clock.is_paused());
+        assert_eq!(clock.tick_at(11.0), 0);
+        clock.step(3);
+        clock.step(2);
+        assert_eq!(clock.tick_at(12.0), 5);
+        assert_eq!(clock.tick_at(13.0), 0);
+
+        // 暂停期间流逝的时间不计入模拟
+        clock.resume();
+        assert_eq!(clock.tick_at(100.0), 1);
+        assert_eq!(clock.tick_at(100.25), 16);
+    }
+
+    #[test]
+    fn step_pauses_running_clock() {
+        let mut clock = started_clock();
+        clock.step(2);
+        assert!(clock.is_paused());
+        assert_eq!(clock.tick_at(11.0), 2);
+    }
+
+    #[test]
+    fn reset_drops_pending_time() {
+        let mut clock = started_clock();
+        clock.pause();
+        clock.step(4);
+        clock.reset();
+        assert_eq!(clock.tick_at(11.0), 0);
+    }
+
+    #[test]
+    fn steps_convert_to_whole_frames() {
+        let mut pending = 0;
+        assert_eq!(steps_to_frames(&mut pending, LBM_STEPS_PER_FRAME - 1), 0);
+        assert_eq!(steps_to_frames(&mut pending, 1), 1);
+        assert_eq!(steps_to_frames(&mut pending, LBM_STEPS_PER_FRAME * 2 + 1), 2);
+        assert_eq!(pending, 1);
+    }
+}
@@ -1,9 +1,11 @@
+use super::readback::{cast_bytes, PendingRead};
 use wgpu::util::DeviceExt;
-use zerocopy::AsBytes;
+use zerocopy::{AsBytes, FromBytes};
 
 pub struct BufferObj {
     pub buffer: wgpu::Buffer,
     pub size: wgpu::BufferAddress,
+    pub usage: wgpu::BufferUsages,
     pub min_binding_size: Option<wgpu::BufferSize>,
     pub has_dynamic_offset: bool,
     pub read_only: bool,
@@ -21,30 +23,27 @@ impl BufferObj {
     pub fn create_empty_storage_buffer(
         device: &wgpu::Device, size: wgpu::BufferAddress, can_read_back: bool, label: Option<&'static str>,
     ) -> Self {
-        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            size,
-            usage: if can_read_back {
-                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC
-            } else {
-                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
-            },
-            label,
-            mapped_at_creation: false,
-        });
-        BufferObj { buffer, size, min_binding_size: None, has_dynamic_offset: false, read_only: false }
+        let usage = Self::empty_storage_usage(can_read_back);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor { size, usage, label, mapped_at_creation: false });
+        BufferObj { buffer, size, usage, min_binding_size: None, has_dynamic_offset: false, read_only: false }
+    }
+
+    // create_empty_storage_buffer 创建的 buffer 的用途
+    pub fn empty_storage_usage(can_read_back: bool) -> wgpu::BufferUsages {
+        if can_read_back {
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC
+        } else {
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+        }
     }
 
     pub fn create_empty_dynamic_uniform_buffer(
         device: &wgpu::Device, size: wgpu::BufferAddress, min_binding_size: Option<wgpu::BufferSize>,
         label: Option<&'static str>,
     ) -> Self {
-        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            size,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            label,
-            mapped_at_creation: false,
-        });
-        BufferObj { buffer, size, min_binding_size, has_dynamic_offset: true, read_only: true }
+        let usage = wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor { size, usage, label, mapped_at_creation: false });
+        BufferObj { buffer, size, usage, min_binding_size, has_dynamic_offset: true, read_only: true }
     }
 
     pub fn create_uniform_buffer<T>(device: &wgpu::Device, uniform: &T, label: Option<&'static str>) -> Self
@@ -78,11 +77,44 @@ impl BufferObj {
         // 移除staging buffer
         // 移动GPU通常是统一内存架构。这一内存架构下，CPU可以直接访问GPU所使用的内存
         // if cfg!(any(target_os = "ios", target_os = "android")) {
-        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label,
-            contents: data,
-            usage: usage | wgpu::BufferUsages::COPY_DST,
-        });
-        BufferObj { buffer, size, min_binding_size: None, has_dynamic_offset: false, read_only: false }
+        let usage = Self::init_usage(usage);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor { label, contents: data, usage });
+        BufferObj { buffer, size, usage, min_binding_size: None, has_dynamic_offset: false, read_only: false }
+    }
+
+    // create_buffer 在 usage 之上附加的用途，需要读回时由调用方在 usage 中加上 COPY_SRC
+    fn init_usage(usage: wgpu::BufferUsages) -> wgpu::BufferUsages {
+        usage | wgpu::BufferUsages::COPY_DST
+    }
+
+    // create_storage_buffer 创建的 buffer 的用途
+    pub fn storage_usage() -> wgpu::BufferUsages {
+        Self::init_usage(wgpu::BufferUsages::STORAGE)
+    }
+
+    // 读回整个 buffer，需要带有 COPY_SRC：
+    // create_empty_storage_buffer 的 can_read_back 为 true，或 create_buffer 的 usage 中带有 COPY_SRC
+    // native 上 await 会阻塞到读回完成，应直接用 read
+    pub async fn read_async<T>(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>, String>
+    where
+        T: FromBytes + Copy,
+    {
+        let bytes = self.submit_read(device, queue)?.bytes(device).await?;
+        Ok(cast_bytes(&bytes))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read<T>(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>, String>
+    where
+        T: FromBytes + Copy,
+    {
+        let bytes = self.submit_read(device, queue)?.wait(device)?;
+        Ok(cast_bytes(&bytes))
+    }
+
+    fn submit_read(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<PendingRead, String> {
+        PendingRead::submit(device, queue, self.size, |encoder, staging_buf| {
+            encoder.copy_buffer_to_buffer(&self.buffer, 0, staging_buf, 0, self.size);
+        })
     }
 }
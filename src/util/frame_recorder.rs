@@ -0,0 +1,145 @@
+use app_surface::AppSurface;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// 逐帧录制为编号的 PNG 图片序列
+// 录制时按固定的帧率推进模拟，与真实流逝的时间无关，不会丢帧
+// 读回需要阻塞等待 GPU，只适用于桌面端
+pub struct FrameRecorder {
+    width: u32,
+    height: u32,
+    // 帧缓冲区的行字节数需要按 256 对齐
+    padded_bytes_per_row: u32,
+    is_bgra: bool,
+    staging_buf: wgpu::Buffer,
+    output_dir: PathBuf,
+    // 交换链原本没有 COPY_SRC 时由录制加上，结束录制时去掉
+    added_copy_src: bool,
+    // 录制的帧率，只用于按模拟时钟推进的场景，默认 60
+    fps: u32,
+    frame_index: u32,
+    // 不足一个模拟步的累积量
+    step_remainder: f64,
+}
+
+impl FrameRecorder {
+    pub fn new(app_view: &mut AppSurface, output_dir: PathBuf) -> Result<Self, String> {
+        std::fs::create_dir_all(&output_dir).map_err(|e| e.to_string())?;
+        let is_bgra = match app_view.config.format {
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            format => return Err(format!("unsupported surface format: {:?}", format)),
+        };
+        // 交换链纹理默认只能作为渲染目标，录制期间需要加上 COPY_SRC 才能读回
+        let added_copy_src = !app_view.config.usage.contains(wgpu::TextureUsages::COPY_SRC);
+        if added_copy_src {
+            app_view.config.usage |= wgpu::TextureUsages::COPY_SRC;
+            app_view.surface.configure(&app_view.device, &app_view.config);
+        }
+        let (width, height) = (app_view.config.width, app_view.config.height);
+        let padded_bytes_per_row = super::readback::padded_bytes_per_row(width * 4);
+        let staging_buf = app_view.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("frame recorder buf"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Ok(Self {
+            width,
+            height,
+            padded_bytes_per_row,
+            is_bgra,
+            staging_buf,
+            output_dir,
+            added_copy_src,
+            fps: 60,
+            frame_index: 0,
+            step_remainder: 0.0,
+        })
+    }
+
+    pub fn with_fps(mut self, fps: u32) -> Self {
+        self.fps = fps.max(1);
+        self
+    }
+
+    // 结束录制，恢复交换链的 usage，返回录制的帧数
+    // 交换链的尺寸变化后读回的尺寸与 staging buffer 不再一致，需要先结束录制
+    pub fn finish(self, app_view: &mut AppSurface) -> u32 {
+        if self.added_copy_src {
+            app_view.config.usage.remove(wgpu::TextureUsages::COPY_SRC);
+            app_view.surface.configure(&app_view.device, &app_view.config);
+        }
+        self.frame_index
+    }
+
+    // 录制的每一帧对应的模拟步数
+    pub fn next_sim_steps(&mut self, steps_per_second: u32, time_scale: f32) -> u32 {
+        self.step_remainder += steps_per_second as f64 * time_scale as f64 / self.fps as f64;
+        let steps = self.step_remainder.floor();
+        self.step_remainder -= steps;
+        steps as u32
+    }
+
+    // 帧绘制完成、present 之前调用
+    pub fn capture(
+        &mut self, device: &wgpu::Device, queue: &wgpu::Queue, frame_texture: &wgpu::Texture,
+    ) -> Result<PathBuf, String> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("frame recorder encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: frame_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.staging_buf,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(self.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d { width: self.width, height: self.height, depth_or_array_layers: 1 },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let is_mapped = Arc::new(AtomicBool::new(false));
+        let flag = is_mapped.clone();
+        self.staging_buf.slice(..).map_async(wgpu::MapMode::Read, move |res| {
+            if res.is_ok() {
+                flag.store(true, Ordering::Release);
+            }
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if !is_mapped.load(Ordering::Acquire) {
+            return Err("failed to map the frame buffer".to_string());
+        }
+
+        let mut pixels: Vec<u8> = Vec::with_capacity((self.width * self.height * 4) as usize);
+        {
+            let data = self.staging_buf.slice(..).get_mapped_range();
+            for row in data.chunks_exact(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..(self.width * 4) as usize]);
+            }
+        }
+        self.staging_buf.unmap();
+        if self.is_bgra {
+            for p in pixels.chunks_exact_mut(4) {
+                p.swap(0, 2);
+            }
+        }
+
+        let path = self.output_dir.join(format!("frame_{:05}.png", self.frame_index));
+        image::save_buffer(&path, &pixels, self.width, self.height, image::ColorType::Rgba8)
+            .map_err(|e| e.to_string())?;
+        self.frame_index += 1;
+        Ok(path)
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::VecDeque;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+// 单帧最多能计时的 pass 数，lbm 与布料的每次迭代各算一个
+const MAX_SCOPES: u32 = 512;
+// 轮流使用的查询组数
+const QUERIES_IN_FLIGHT: usize = 3;
+// 滚动平均的采样帧数
+const ROLLING_WINDOW: usize = 60;
+
+// 一段时间内的滚动平均值
+pub struct RollingAverage {
+    samples: VecDeque<f32>,
+    sum: f32,
+}
+
+impl RollingAverage {
+    fn new() -> Self {
+        Self { samples: VecDeque::with_capacity(ROLLING_WINDOW), sum: 0.0 }
+    }
+
+    fn push(&mut self, value: f32) {
+        if self.samples.len() == ROLLING_WINDOW {
+            self.sum -= self.samples.pop_front().unwrap();
+        }
+        self.samples.push_back(value);
+        self.sum += value;
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.sum / self.samples.len() as f32
+        }
+    }
+}
+
+struct TimestampQueries {
+    query_set: wgpu::QuerySet,
+    resolve_buf: wgpu::Buffer,
+    read_buf: wgpu::Buffer,
+    // 写入了时间戳、等待读回的 pass
+    pending_scopes: Vec<&'static str>,
+    in_flight: bool,
+    is_map_requested: bool,
+    is_mapped: Arc<AtomicBool>,
+}
+
+impl TimestampQueries {
+    fn new(device: &wgpu::Device) -> Self {
+        let size = (MAX_SCOPES * 2) as wgpu::BufferAddress * 8;
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("profiler query set"),
+                ty: wgpu::QueryType::Timestamp,
+                count: MAX_SCOPES * 2,
+            }),
+            resolve_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("profiler resolve buf"),
+                size,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            read_buf: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("profiler read buf"),
+                size,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            pending_scopes: vec![],
+            in_flight: false,
+            is_map_requested: false,
+            is_mapped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+// 用 wgpu 时间戳查询统计各个 pass 的 GPU 耗时
+// 设备不支持 TIMESTAMP_QUERY 时只统计 CPU 帧时间
+// 读回有几帧的延迟，多组查询轮流使用，上一帧的结果还没读回时当前帧仍能计时
+pub struct GpuProfiler {
+    enabled: bool,
+    // 时间戳的单位：纳秒
+    period: f32,
+    queries: Vec<TimestampQueries>,
+    // 当前帧使用的查询，第一次 begin_scope 时选取
+    current: Option<usize>,
+    // 按提交顺序排列的等待读回的查询
+    in_flight: VecDeque<usize>,
+    // 当前帧已写入时间戳的 pass，第 i 个 pass 使用 2i, 2i + 1 两个查询
+    scopes: Vec<&'static str>,
+    // 所有查询都在等待读回、没能计时的帧数
+    skipped_frames: u32,
+    averages: Vec<(&'static str, RollingAverage)>,
+    frame_start: Option<f64>,
+    frame_time: RollingAverage,
+}
+
+impl GpuProfiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let queries = if device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            (0..QUERIES_IN_FLIGHT).map(|_| TimestampQueries::new(device)).collect()
+        } else {
+            vec![]
+        };
+        Self {
+            enabled: false,
+            period: queue.get_timestamp_period(),
+            queries,
+            current: None,
+            in_flight: VecDeque::new(),
+            scopes: vec![],
+            skipped_frames: 0,
+            averages: vec![],
+            frame_start: None,
+            frame_time: RollingAverage::new(),
+        }
+    }
+
+    pub fn is_timestamp_supported(&self) -> bool {
+        !self.queries.is_empty()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // 正在用时间戳计时：需要分开计时的 dispatch 应当各自放在单独的 pass 中
+    pub fn is_timing(&self) -> bool {
+        self.enabled && self.is_timestamp_supported()
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.scopes.clear();
+        self.current = None;
+    }
+
+    // 开始计时一个 pass，返回的索引传给 end_scope
+    pub fn begin_scope(
+        &mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str,
+    ) -> Option<u32> {
+        if !self.enabled {
+            return None;
+        }
+        let current = match self.current {
+            Some(current) => current,
+            None => {
+                let free = self.queries.iter().position(|ts| !ts.in_flight)?;
+                self.current = Some(free);
+                free
+            }
+        };
+        let index = self.scopes.len() as u32;
+        if index >= MAX_SCOPES {
+            return None;
+        }
+        encoder.write_timestamp(&self.queries[current].query_set, index * 2);
+        self.scopes.push(label);
+        Some(index)
+    }
+
+    pub fn end_scope(&mut self, encoder: &mut wgpu::CommandEncoder, scope: Option<u32>) {
+        if let (Some(index), Some(current)) = (scope, self.current) {
+            encoder.write_timestamp(&self.queries[current].query_set, index * 2 + 1);
+        }
+    }
+
+    // 在单独计时的 compute pass 中执行 dispatch，context 为 dispatch 用到的节点
+    pub fn compute_scope<C>(
+        &mut self, encoder: &mut wgpu::CommandEncoder, label: &'static str, context: &C,
+        dispatch: impl for<'a> FnOnce(&'a C, &mut wgpu::ComputePass<'a>),
+    ) {
+        let scope = self.begin_scope(encoder, label);
+        {
+            let mut cpass =
+                encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some(label) });
+            dispatch(context, &mut cpass);
+        }
+        self.end_scope(encoder, scope);
+    }
+
+    pub fn begin_frame(&mut self) {
+        if self.enabled {
+            self.frame_start = Some(crate::sim_clock::now_secs());
+            if self.is_timestamp_supported() && self.queries.iter().all(|ts| ts.in_flight) {
+                self.skipped_frames += 1;
+            }
+        }
+    }
+
+    // 所有 pass 都编码之后调用：把查询结果复制到可读回的缓冲区
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let current = match self.current.take() {
+            Some(current) if !self.scopes.is_empty() => current,
+            _ => return,
+        };
+        let ts = &mut self.queries[current];
+        let count = self.scopes.len() as u32 * 2;
+        encoder.resolve_query_set(&ts.query_set, 0..count, &ts.resolve_buf, 0);
+        encoder.copy_buffer_to_buffer(
+            &ts.resolve_buf,
+            0,
+            &ts.read_buf,
+            0,
+            count as wgpu::BufferAddress * 8,
+        );
+        ts.in_flight = true;
+        ts.pending_scopes = std::mem::take(&mut self.scopes);
+        self.in_flight.push_back(current);
+    }
+
+    // encoder 提交之后调用：读回已完成的查询结果并更新滚动平均
+    pub fn end_frame(&mut self, device: &wgpu::Device) {
+        if let Some(start) = self.frame_start.take() {
+            self.frame_time.push(((crate::sim_clock::now_secs() - start) * 1000.0) as f32);
+        }
+        if self.in_flight.is_empty() {
+            return;
+        }
+        for index in self.in_flight.iter() {
+            let ts = &mut self.queries[*index];
+            if !ts.is_map_requested {
+                ts.is_map_requested = true;
+                let is_mapped = ts.is_mapped.clone();
+                ts.read_buf.slice(..).map_async(wgpu::MapMode::Read, move |res| {
+                    if res.is_ok() {
+                        is_mapped.store(true, Ordering::Release);
+                    }
+                });
+            }
+        }
+        device.poll(wgpu::Maintain::Poll);
+        // 按提交顺序读回，保证滚动平均里的帧是有序的
+        while let Some(index) = self.in_flight.front().copied() {
+            if !self.queries[index].is_mapped.load(Ordering::Acquire) {
+                break;
+            }
+            self.in_flight.pop_front();
+            self.read_queries(index);
+        }
+    }
+
+    fn read_queries(&mut self, index: usize) {
+        let ts = &mut self.queries[index];
+        let scopes = std::mem::take(&mut ts.pending_scopes);
+        let timestamps: Vec<u64> = {
+            let data = ts.read_buf.slice(..).get_mapped_range();
+            data.chunks_exact(8)
+                .take(scopes.len() * 2)
+                .map(|bytes| {
+                    let mut raw = [0u8; 8];
+                    raw.copy_from_slice(bytes);
+                    u64::from_le_bytes(raw)
+                })
+                .collect()
+        };
+        ts.read_buf.unmap();
+        ts.is_mapped.store(false, Ordering::Release);
+        ts.is_map_requested = false;
+        ts.in_flight = false;
+
+        // 同一帧内重复的 pass 累加到一起
+        let mut frame_ms: Vec<(&'static str, f32)> = vec![];
+        for (i, label) in scopes.into_iter().enumerate() {
+            let ticks = timestamps[i * 2 + 1].saturating_sub(timestamps[i * 2]);
+            let ms = ticks as f32 * self.period / 1_000_000.0;
+            match frame_ms.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, sum)) => *sum += ms,
+                None => frame_ms.push((label, ms)),
+            }
+        }
+        for (label, ms) in frame_ms {
+            match self.averages.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, avg)) => avg.push(ms),
+                None => {
+                    let mut avg = RollingAverage::new();
+                    avg.push(ms);
+                    self.averages.push((label, avg));
+                }
+            }
+        }
+    }
+
+    // CPU 帧时间的滚动平均（毫秒）
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time.average()
+    }
+
+    // 各个 pass 的 GPU 耗时滚动平均（毫秒）
+    pub fn averages(&self) -> Vec<(&'static str, f32)> {
+        self.averages.iter().map(|(label, avg)| (*label, avg.average())).collect()
+    }
+
+    // 读回太慢、所有查询都被占用而没有计时的帧数
+    pub fn skipped_frames(&self) -> u32 {
+        self.skipped_frames
+    }
+}
@@ -0,0 +1,163 @@
+use super::{node::BufferlessFullscreenNode, BufferObj, GpuProfiler};
+use zerocopy::{AsBytes, FromBytes};
+
+// 标签 14 列 + 数值 6 列
+const LABEL_COLS: usize = 14;
+const HUD_COLS: usize = LABEL_COLS + 6;
+const MAX_ROWS: usize = 18;
+// 耗时条的满格对应 60fps 的一帧
+const FRAME_BUDGET_MS: f32 = 16.6;
+
+#[repr(C)]
+#[derive(Copy, Clone, AsBytes, FromBytes)]
+struct HudUniform {
+    rect: [f32; 4],
+    cols: i32,
+    rows: i32,
+    scale: f32,
+    bar_width: f32,
+}
+
+// 屏幕左上角的性能统计：每行一个 pass 的名称、平均耗时（毫秒）与耗时条
+pub struct HudOverlay {
+    uniform_data: HudUniform,
+    uniform_buf: BufferObj,
+    glyph_buf: BufferObj,
+    bar_buf: BufferObj,
+    node: BufferlessFullscreenNode,
+}
+
+impl HudOverlay {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, scale: f32) -> Self {
+        let bar_width = 60.0 * scale;
+        let uniform_data = HudUniform {
+            rect: [8.0, 8.0, (HUD_COLS * 4 + 4) as f32 * scale + bar_width, 0.0],
+            cols: HUD_COLS as i32,
+            rows: 0,
+            scale,
+            bar_width,
+        };
+        let uniform_buf =
+            BufferObj::create_uniform_buffer(device, &uniform_data, Some("hud uniform"));
+        let glyph_buf = BufferObj::create_empty_storage_buffer(
+            device,
+            (HUD_COLS * MAX_ROWS * 4) as wgpu::BufferAddress,
+            false,
+            Some("hud glyph buf"),
+        );
+        let bar_buf = BufferObj::create_empty_storage_buffer(
+            device,
+            (MAX_ROWS * 4) as wgpu::BufferAddress,
+            false,
+            Some("hud bar buf"),
+        );
+        let shader = crate::util::shader::create_shader_module(device, "hud_overlay", None);
+        let node = BufferlessFullscreenNode::new(
+            device,
+            format,
+            vec![&uniform_buf],
+            vec![&glyph_buf, &bar_buf],
+            vec![],
+            vec![],
+            &shader,
+            None,
+            false,
+        );
+        Self { uniform_data, uniform_buf, glyph_buf, bar_buf, node }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, profiler: &GpuProfiler) {
+        let mut lines: Vec<(String, f32)> = vec![("FRAME CPU".to_string(), profiler.frame_time())];
+        if profiler.is_timestamp_supported() {
+            for (label, ms) in profiler.averages() {
+                lines.push((label.to_uppercase(), ms));
+            }
+        } else {
+            lines.push(("NO GPU TIMER".to_string(), 0.0));
+        }
+        lines.truncate(MAX_ROWS);
+
+        let mut glyphs: Vec<u32> = Vec::with_capacity(HUD_COLS * lines.len());
+        let mut bars: Vec<f32> = Vec::with_capacity(lines.len());
+        for (label, ms) in lines.iter() {
+            let label: String = label.chars().take(LABEL_COLS).collect();
+            let text = format!("{:<width$}{:>6.2}", label, ms, width = LABEL_COLS);
+            glyphs.extend(text.chars().take(HUD_COLS).map(glyph_bitmap));
+            bars.push(ms / FRAME_BUDGET_MS);
+        }
+        queue.write_buffer(&self.glyph_buf.buffer, 0, glyphs.as_bytes());
+        queue.write_buffer(&self.bar_buf.buffer, 0, bars.as_bytes());
+
+        self.uniform_data.rows = lines.len() as i32;
+        self.uniform_data.rect[3] = (lines.len() * 6) as f32 * self.uniform_data.scale;
+        queue.write_buffer(&self.uniform_buf.buffer, 0, self.uniform_data.as_bytes());
+    }
+
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+        self.node.draw(frame_view, encoder, wgpu::LoadOp::Load);
+    }
+}
+
+// 3x5 点阵字体，第 r 行第 c 列对应位图的第 r * 3 + c 位
+const FONT_3X5: [(char, [&str; 5]); 42] = [
+    ('0', ["###", "#.#", "#.#", "#.#", "###"]),
+    ('1', [".#.", "##.", ".#.", ".#.", "###"]),
+    ('2', ["###", "..#", "###", "#..", "###"]),
+    ('3', ["###", "..#", "###", "..#", "###"]),
+    ('4', ["#.#", "#.#", "###", "..#", "..#"]),
+    ('5', ["###", "#..", "###", "..#", "###"]),
+    ('6', ["###", "#..", "###", "#.#", "###"]),
+    ('7', ["###", "..#", "..#", ".#.", ".#."]),
+    ('8', ["###", "#.#", "###", "#.#", "###"]),
+    ('9', ["###", "#.#", "###", "..#", "###"]),
+    ('A', [".#.", "#.#", "###", "#.#", "#.#"]),
+    ('B', ["##.", "#.#", "##.", "#.#", "##."]),
+    ('C', [".##", "#..", "#..", "#..", ".##"]),
+    ('D', ["##.", "#.#", "#.#", "#.#", "##."]),
+    ('E', ["###", "#..", "##.", "#..", "###"]),
+    ('F', ["###", "#..", "##.", "#..", "#.."]),
+    ('G', [".##", "#..", "#.#", "#.#", ".##"]),
+    ('H', ["#.#", "#.#", "###", "#.#", "#.#"]),
+    ('I', ["###", ".#.", ".#.", ".#.", "###"]),
+    ('J', ["..#", "..#", "..#", "#.#", ".#."]),
+    ('K', ["#.#", "#.#", "##.", "#.#", "#.#"]),
+    ('L', ["#..", "#..", "#..", "#..", "###"]),
+    ('M', ["#.#", "###", "###", "#.#", "#.#"]),
+    ('N', ["##.", "#.#", "#.#", "#.#", "#.#"]),
+    ('O', [".#.", "#.#", "#.#", "#.#", ".#."]),
+    ('P', ["##.", "#.#", "##.", "#..", "#.."]),
+    ('Q', [".#.", "#.#", "#.#", "##.", ".##"]),
+    ('R', ["##.", "#.#", "##.", "#.#", "#.#"]),
+    ('S', [".##", "#..", ".#.", "..#", "##."]),
+    ('T', ["###", ".#.", ".#.", ".#.", ".#."]),
+    ('U', ["#.#", "#.#", "#.#", "#.#", "###"]),
+    ('V', ["#.#", "#.#", "#.#", "#.#", ".#."]),
+    ('W', ["#.#", "#.#", "###", "###", "#.#"]),
+    ('X', ["#.#", "#.#", ".#.", "#.#", "#.#"]),
+    ('Y', ["#.#", "#.#", ".#.", ".#.", ".#."]),
+    ('Z', ["###", "..#", ".#.", "#..", "###"]),
+    ('.', ["...", "...", "...", "...", ".#."]),
+    (':', ["...", ".#.", "...", ".#.", "..."]),
+    ('/', ["..#", "..#", ".#.", "#..", "#.."]),
+    ('-', ["...", "...", "###", "...", "..."]),
+    ('_', ["...", "...", "...", "...", "###"]),
+    ('%', ["#.#", "..#", ".#.", "#..", "#.#"]),
+];
+
+// 不在字体里的字符显示为空白
+fn glyph_bitmap(c: char) -> u32 {
+    match FONT_3X5.iter().find(|(g, _)| *g == c) {
+        Some((_, rows)) => {
+            let mut bitmap = 0_u32;
+            for (r, line) in rows.iter().enumerate() {
+                for (col, px) in line.chars().enumerate() {
+                    if px == '#' {
+                        bitmap |= 1 << (r * 3 + col);
+                    }
+                }
+            }
+            bitmap
+        }
+        None => 0,
+    }
+}
@@ -1,7 +1,8 @@
+use super::readback::{cast_bytes, padded_bytes_per_row, PendingRead};
 use image::GenericImageView;
 use std::{num::NonZeroU32, path::PathBuf};
 use wgpu::{Extent3d, Sampler, Texture, TextureFormat, TextureView};
-use zerocopy::AsBytes;
+use zerocopy::{AsBytes, FromBytes};
 
 pub struct AnyTexture {
     pub size: Extent3d,
@@ -10,6 +11,96 @@ pub struct AnyTexture {
     pub format: TextureFormat,
     pub view_dimension: wgpu::TextureViewDimension,
 }
+
+#[allow(dead_code)]
+impl AnyTexture {
+    // 读回第 0 级 mipmap 的全部像素，已去掉每行对齐用的填充，多层纹理按层依次排列
+    // 纹理需要带有 COPY_SRC，不支持压缩与深度格式
+    pub async fn read_async<T>(
+        &self, device: &wgpu::Device, queue: &wgpu::Queue,
+    ) -> Result<Vec<T>, String>
+    where
+        T: FromBytes + Copy,
+    {
+        let (pending, bytes_per_row) = self.submit_read(device, queue)?;
+        let bytes = pending.bytes(device).await?;
+        Ok(cast_bytes(&strip_row_padding(&bytes, bytes_per_row)))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read<T>(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>, String>
+    where
+        T: FromBytes + Copy,
+    {
+        let (pending, bytes_per_row) = self.submit_read(device, queue)?;
+        let bytes = pending.wait(device)?;
+        Ok(cast_bytes(&strip_row_padding(&bytes, bytes_per_row)))
+    }
+
+    fn submit_read(
+        &self, device: &wgpu::Device, queue: &wgpu::Queue,
+    ) -> Result<(PendingRead, u32), String> {
+        let pixel_bytes = single_pixel_bytes(self.format);
+        if pixel_bytes == 0 {
+            return Err(format!("texture readback does not support {:?}", self.format));
+        }
+        let bytes_per_row = self.size.width * pixel_bytes;
+        let padded_bytes_per_row = padded_bytes_per_row(bytes_per_row);
+        let rows = self.size.height * self.size.depth_or_array_layers;
+        let pending = PendingRead::submit(
+            device,
+            queue,
+            (padded_bytes_per_row * rows) as wgpu::BufferAddress,
+            |encoder, staging_buf| {
+                encoder.copy_texture_to_buffer(
+                    wgpu::ImageCopyTexture {
+                        texture: &self.tex,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::ImageCopyBuffer {
+                        buffer: staging_buf,
+                        layout: wgpu::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                            rows_per_image: NonZeroU32::new(self.size.height),
+                        },
+                    },
+                    self.size,
+                );
+            },
+        )?;
+        Ok((pending, bytes_per_row))
+    }
+
+    // 第 0 级 mipmap 全部写入 0，纹理需要带有 COPY_DST
+    pub fn clear(&self, queue: &wgpu::Queue) {
+        let bytes_per_row = self.size.width * single_pixel_bytes(self.format);
+        let rows = self.size.height * self.size.depth_or_array_layers;
+        let zeros = vec![0_u8; (bytes_per_row * rows) as usize];
+        queue.write_texture(
+            self.tex.as_image_copy(),
+            &zeros,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(bytes_per_row),
+                rows_per_image: NonZeroU32::new(self.size.height),
+            },
+            self.size,
+        );
+    }
+}
+
+fn strip_row_padding(bytes: &[u8], bytes_per_row: u32) -> Vec<u8> {
+    let padded_bytes_per_row = padded_bytes_per_row(bytes_per_row) as usize;
+    let mut unpadded =
+        Vec::with_capacity(bytes.len() / padded_bytes_per_row * bytes_per_row as usize);
+    for row in bytes.chunks_exact(padded_bytes_per_row) {
+        unpadded.extend_from_slice(&row[..bytes_per_row as usize]);
+    }
+    unpadded
+}
 #[allow(dead_code)]
 pub fn from_path(
     image_path: &str, app_view: &app_surface::AppSurface, usage: wgpu::TextureUsages,
@@ -106,6 +197,7 @@ pub fn empty(
     } else {
         wgpu::TextureUsages::RENDER_ATTACHMENT
             | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC
             | wgpu::TextureUsages::TEXTURE_BINDING
             | wgpu::TextureUsages::STORAGE_BINDING
     };
@@ -242,12 +334,23 @@ fn single_pixel_bytes(format: TextureFormat) -> u32 {
         | TextureFormat::Bgra8UnormSrgb
         | TextureFormat::Rgba8Snorm
         | TextureFormat::Rgba8Unorm
-        | TextureFormat::Rgba8UnormSrgb => 4,
+        | TextureFormat::Rgba8UnormSrgb
+        | TextureFormat::R32Float
+        | TextureFormat::R32Sint
+        | TextureFormat::R32Uint
+        | TextureFormat::Rg16Float
+        | TextureFormat::Rg16Sint
+        | TextureFormat::Rg16Uint
+        | TextureFormat::Rgb10a2Unorm
+        | TextureFormat::Rg11b10Float => 4,
         TextureFormat::Rgba16Float
         | TextureFormat::Rgba16Sint
         | TextureFormat::Rgba16Snorm
         | TextureFormat::Rgba16Uint
-        | TextureFormat::Rgba16Unorm => 8,
+        | TextureFormat::Rgba16Unorm
+        | TextureFormat::Rg32Float
+        | TextureFormat::Rg32Sint
+        | TextureFormat::Rg32Uint => 8,
         TextureFormat::Rgba32Float | TextureFormat::Rgba32Sint | TextureFormat::Rgba32Uint => 16,
         _ => 0,
     }
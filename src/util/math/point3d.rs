@@ -45,6 +45,18 @@ impl Point3D {
         Point3D::new(self.x + dx, self.y + dy, self.z + dz)
     }
 
+    pub fn dot(&self, other: &Point3D) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Point3D) -> Self {
+        Point3D::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
     // 模长
     pub fn length(&self) -> f32 {
         (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
@@ -8,11 +8,32 @@ pub use utils::{depth_stencil, matrix_helper};
 mod buffer;
 pub use buffer::BufferObj;
 
+mod frame_recorder;
+pub use frame_recorder::FrameRecorder;
+
+mod gpu_profiler;
+pub use gpu_profiler::GpuProfiler;
+
+mod hud_overlay;
+pub use hud_overlay::HudOverlay;
+
 mod mvp_uniform_obj;
 pub use mvp_uniform_obj::{MVPUniform, MVPUniform2, MVPUniformObj};
 // mod dynamic_buffer;
 // pub use dynamic_buffer::DynamicBufferObj;
 
 pub mod node;
+pub mod preprocessor;
+mod readback;
+pub mod render_graph;
+pub mod resource_pool;
 pub mod shader;
+pub mod shader_reflection;
+pub use shader_reflection::ShaderReflection;
+pub mod uniform_buffer;
+pub(crate) use uniform_buffer::uniform_layout;
+pub use uniform_buffer::{UniformBuffer, UniformLayout, WgslField};
+mod shader_registry;
+mod shader_watcher;
+pub use shader_watcher::changed_shaders;
 pub mod vertex;
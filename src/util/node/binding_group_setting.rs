@@ -1,3 +1,5 @@
+use crate::util::shader_reflection::{BindingKind, ReflectedBinding, ShaderReflection};
+use crate::util::uniform_buffer::{wgsl_struct, UniformBuffer, UniformLayout, WgslField};
 use crate::util::{buffer::BufferObj, AnyTexture};
 use std::vec::Vec;
 use wgpu::{StorageTextureAccess, TextureFormat};
@@ -5,6 +7,8 @@ use wgpu::{StorageTextureAccess, TextureFormat};
 #[allow(dead_code)]
 pub struct BindingGroupSetting {
     pub bind_group_layout: wgpu::BindGroupLayout,
+    // pipeline 缓存的键
+    pub layout_entries: Vec<wgpu::BindGroupLayoutEntry>,
     pub bind_group: wgpu::BindGroup,
 }
 
@@ -115,7 +119,252 @@ impl BindingGroupSetting {
             label: None,
         });
 
-        BindingGroupSetting { bind_group_layout, bind_group }
+        BindingGroupSetting { bind_group_layout, layout_entries: layouts, bind_group }
+    }
+
+    // 按 shader 中的变量名绑定资源，layout 由 shader 反射生成
+    pub fn from_reflection(
+        device: &wgpu::Device, reflection: &ShaderReflection, group: u32, bindings: &Bindings,
+    ) -> Result<Self, String> {
+        let shader_name = &reflection.shader_name;
+        let reflected = reflection.bindings(group);
+        for (i, (name, _)) in bindings.resources.iter().enumerate() {
+            if bindings.resources[..i].iter().any(|(other, _)| other == name) {
+                return Err(format!("`{}` is bound more than once", name));
+            }
+            if !reflected.iter().any(|item| item.name == *name) {
+                return Err(format!(
+                    "shader `{}` has no binding named `{}` in group {}",
+                    shader_name, name, group
+                ));
+            }
+        }
+
+        let mut layouts: Vec<wgpu::BindGroupLayoutEntry> = vec![];
+        let mut entries: Vec<wgpu::BindGroupEntry> = vec![];
+        for item in reflected {
+            let resource = match bindings.resources.iter().find(|(name, _)| *name == item.name) {
+                Some((_, resource)) => resource,
+                None => {
+                    return Err(format!(
+                        "shader `{}` binding `{}` (@group({}) @binding({})) has no resource",
+                        shader_name, item.name, item.group, item.binding
+                    ))
+                }
+            };
+            if let BoundResource::Uniform { fields, wgsl, .. } = resource {
+                check_members(shader_name, item, fields, wgsl)?;
+            }
+            let (ty, binding_resource) = match (item.kind, resource) {
+                (BindingKind::UniformBuffer, BoundResource::Buffer(buffer_obj))
+                | (BindingKind::UniformBuffer, BoundResource::Uniform { buffer: buffer_obj, .. })
+                | (BindingKind::StorageBuffer { .. }, BoundResource::Buffer(buffer_obj)) => {
+                    if buffer_obj.size < item.min_size {
+                        return Err(format!(
+                            "`{}` needs at least {} bytes in shader `{}`, but the buffer has {}",
+                            item.name, item.min_size, shader_name, buffer_obj.size
+                        ));
+                    }
+                    let ty = match item.kind {
+                        BindingKind::StorageBuffer { read_only } => {
+                            wgpu::BufferBindingType::Storage { read_only }
+                        }
+                        _ => wgpu::BufferBindingType::Uniform,
+                    };
+                    (
+                        wgpu::BindingType::Buffer {
+                            ty,
+                            has_dynamic_offset: buffer_obj.has_dynamic_offset,
+                            min_binding_size: wgpu::BufferSize::new(0),
+                        },
+                        buffer_obj.buffer.as_entire_binding(),
+                    )
+                }
+                (
+                    BindingKind::Texture { sample_type, multisampled },
+                    BoundResource::Texture(any_tex),
+                ) => {
+                    check_view_dimension(shader_name, item, any_tex)?;
+                    let sample_type = match sample_type {
+                        wgpu::TextureSampleType::Float { .. } => wgpu::TextureSampleType::Float {
+                            filterable: texture_sample_filterable(any_tex.format),
+                        },
+                        _ => sample_type,
+                    };
+                    (
+                        wgpu::BindingType::Texture {
+                            sample_type,
+                            view_dimension: any_tex.view_dimension,
+                            multisampled,
+                        },
+                        wgpu::BindingResource::TextureView(&any_tex.tex_view),
+                    )
+                }
+                (
+                    BindingKind::StorageTexture { access, format },
+                    BoundResource::Texture(any_tex),
+                ) => {
+                    check_view_dimension(shader_name, item, any_tex)?;
+                    if let Some(format) = format {
+                        if format != any_tex.format {
+                            return Err(format!(
+                                "`{}` is {:?} in shader `{}`, but the texture is {:?}",
+                                item.name, format, shader_name, any_tex.format
+                            ));
+                        }
+                    }
+                    (
+                        wgpu::BindingType::StorageTexture {
+                            view_dimension: any_tex.view_dimension,
+                            access,
+                            format: any_tex.format,
+                        },
+                        wgpu::BindingResource::TextureView(&any_tex.tex_view),
+                    )
+                }
+                (BindingKind::Sampler { comparison }, BoundResource::Sampler(sampler)) => (
+                    wgpu::BindingType::Sampler(if comparison {
+                        wgpu::SamplerBindingType::Comparison
+                    } else {
+                        wgpu::SamplerBindingType::Filtering
+                    }),
+                    wgpu::BindingResource::Sampler(sampler),
+                ),
+                _ => {
+                    return Err(format!(
+                        "`{}` is a {} in shader `{}`, but a {} was bound",
+                        item.name,
+                        item.kind.describe(),
+                        shader_name,
+                        resource.describe()
+                    ))
+                }
+            };
+            layouts.push(wgpu::BindGroupLayoutEntry {
+                binding: item.binding,
+                visibility: item.visibility,
+                ty,
+                count: None,
+            });
+            entries
+                .push(wgpu::BindGroupEntry { binding: item.binding, resource: binding_resource });
+        }
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &layouts,
+            label: Some(shader_name),
+        });
+        let bind_group: wgpu::BindGroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &entries,
+            label: Some(shader_name),
+        });
+
+        Ok(BindingGroupSetting { bind_group_layout, layout_entries: layouts, bind_group })
+    }
+}
+
+enum BoundResource<'a> {
+    Buffer(&'a BufferObj),
+    // 带字段布局的 uniform，绑定时与 shader 中的 struct 比对
+    Uniform { buffer: &'a BufferObj, fields: Vec<WgslField>, wgsl: String },
+    Texture(&'a AnyTexture),
+    Sampler(&'a wgpu::Sampler),
+}
+
+impl<'a> BoundResource<'a> {
+    fn describe(&self) -> &'static str {
+        match self {
+            BoundResource::Buffer(_) | BoundResource::Uniform { .. } => "buffer",
+            BoundResource::Texture(_) => "texture",
+            BoundResource::Sampler(_) => "sampler",
+        }
+    }
+}
+
+// 以 shader 中的变量名为键的绑定资源，顺序无关
+#[derive(Default)]
+pub struct Bindings<'a> {
+    resources: Vec<(&'a str, BoundResource<'a>)>,
+}
+
+#[allow(dead_code)]
+impl<'a> Bindings<'a> {
+    pub fn new() -> Self {
+        Self { resources: vec![] }
+    }
+
+    // uniform 与 storage buffer 共用，类型由 shader 决定
+    pub fn buffer(mut self, name: &'a str, buffer: &'a BufferObj) -> Self {
+        self.resources.push((name, BoundResource::Buffer(buffer)));
+        self
+    }
+
+    pub fn uniform<T: UniformLayout>(
+        mut self, name: &'a str, uniform: &'a UniformBuffer<T>,
+    ) -> Self {
+        let resource = BoundResource::Uniform {
+            buffer: &uniform.buffer,
+            fields: T::fields(),
+            wgsl: wgsl_struct::<T>(),
+        };
+        self.resources.push((name, resource));
+        self
+    }
+
+    // 采样纹理与存储纹理共用，读写权限由 shader 决定
+    pub fn texture(mut self, name: &'a str, texture: &'a AnyTexture) -> Self {
+        self.resources.push((name, BoundResource::Texture(texture)));
+        self
+    }
+
+    pub fn sampler(mut self, name: &'a str, sampler: &'a wgpu::Sampler) -> Self {
+        self.resources.push((name, BoundResource::Sampler(sampler)));
+        self
+    }
+}
+
+// 成员名相同时偏移与大小必须一致，shader 中名称以 padding 或 _ 开头的成员可以没有对应字段
+fn check_members(
+    shader_name: &str, item: &ReflectedBinding, fields: &[WgslField], wgsl: &str,
+) -> Result<(), String> {
+    let error = |message: String| {
+        Err(format!(
+            "`{}` in shader `{}`: {}, the Rust struct is\n{}",
+            item.name, shader_name, message, wgsl
+        ))
+    };
+    for field in fields {
+        match item.members.iter().find(|(name, _, _)| name == field.name) {
+            Some((_, offset, size)) => {
+                if *offset != field.offset || *size != field.size {
+                    return error(format!(
+                        "member `{}` is {} bytes at offset {}, but the Rust field is {} bytes at offset {}",
+                        field.name, size, offset, field.size, field.offset
+                    ));
+                }
+            }
+            None => return error(format!("has no member `{}`", field.name)),
+        }
+    }
+    for (name, _, _) in item.members.iter() {
+        let is_padding = name.starts_with("padding") || name.starts_with('_');
+        if !is_padding && !fields.iter().any(|field| field.name == name) {
+            return error(format!("member `{}` is missing in the Rust struct", name));
+        }
+    }
+    Ok(())
+}
+
+fn check_view_dimension(
+    shader_name: &str, item: &ReflectedBinding, any_tex: &AnyTexture,
+) -> Result<(), String> {
+    match item.view_dimension {
+        Some(dimension) if dimension != any_tex.view_dimension => Err(format!(
+            "`{}` is a {:?} texture in shader `{}`, but the bound view is {:?}",
+            item.name, dimension, shader_name, any_tex.view_dimension
+        )),
+        _ => Ok(()),
     }
 }
 
@@ -1,10 +1,18 @@
-use crate::util::{AnyTexture, BufferObj};
+use super::{BindingGroupSetting, Bindings};
+use crate::util::resource_pool::{self, PipelineKey};
+use crate::util::{AnyTexture, BufferObj, ShaderReflection};
+use std::rc::Rc;
 use wgpu::{PrimitiveTopology, ShaderModule, TextureFormat};
 
 #[allow(dead_code)]
 pub struct BufferlessFullscreenNode {
     bind_group: wgpu::BindGroup,
-    pipeline: wgpu::RenderPipeline,
+    pipeline: Rc<wgpu::RenderPipeline>,
+    // 热重载时沿用首次创建时推导出的 bind group layout，bind_group 不需要重建
+    pipeline_layout: wgpu::PipelineLayout,
+    format: TextureFormat,
+    blend_state: Option<wgpu::BlendState>,
+    use_depth_stencil: bool,
 }
 
 impl BufferlessFullscreenNode {
@@ -14,56 +22,101 @@ impl BufferlessFullscreenNode {
         samplers: Vec<&wgpu::Sampler>, shader_module: &ShaderModule,
         color_blend_state: Option<wgpu::BlendState>, use_depth_stencil: bool,
     ) -> Self {
-        let pipeline_vertex_buffers = [];
         let blend_state = if color_blend_state.is_some() {
             color_blend_state
         } else {
             Some(crate::util::utils::default_blend())
         };
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("bufferless fullscreen pipeline"),
-            layout: None,
-            vertex: wgpu::VertexState {
-                module: shader_module,
-                entry_point: "vs_main",
-                buffers: &pipeline_vertex_buffers,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: shader_module,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: blend_state,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            // the bufferless vertices are in clock-wise order
-            primitive: wgpu::PrimitiveState {
-                topology: PrimitiveTopology::TriangleList,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: Some(wgpu::Face::Front),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                ..Default::default()
-            },
-            depth_stencil: if use_depth_stencil {
-                Some(crate::util::depth_stencil::create_state())
-            } else {
-                None
-            },
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
+        // 推导出的 layout 属于 pipeline 自身，共用 pipeline 时 bind_group 也能通用
+        let key =
+            PipelineKey::render(shader_module, vec![], format, blend_state, use_depth_stencil);
+        let pipeline = resource_pool::render_pipeline(key, || {
+            create_pipeline(device, None, shader_module, format, blend_state, use_depth_stencil)
         });
 
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
         let bind_group = create_bind_group(
             device,
             uniforms,
             storage_buffers,
             textures,
             samplers,
-            &pipeline.get_bind_group_layout(0),
+            &bind_group_layout,
         );
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self { bind_group, pipeline, pipeline_layout, format, blend_state, use_depth_stencil }
+    }
+
+    // 按名称绑定 shader 中 group 0 的资源
+    pub fn with_bindings(
+        device: &wgpu::Device, format: TextureFormat, reflection: &ShaderReflection,
+        bindings: Bindings, shader_module: &ShaderModule,
+        color_blend_state: Option<wgpu::BlendState>, use_depth_stencil: bool,
+    ) -> Self {
+        let blend_state = if color_blend_state.is_some() {
+            color_blend_state
+        } else {
+            Some(crate::util::utils::default_blend())
+        };
+        let bg_setting =
+            match BindingGroupSetting::from_reflection(device, reflection, 0, &bindings) {
+                Ok(setting) => setting,
+                Err(e) => panic!("{}", e),
+            };
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bg_setting.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let key = PipelineKey::render(
+            shader_module,
+            vec![bg_setting.layout_entries.clone()],
+            format,
+            blend_state,
+            use_depth_stencil,
+        );
+        let pipeline = resource_pool::render_pipeline(key, || {
+            create_pipeline(
+                device,
+                Some(&pipeline_layout),
+                shader_module,
+                format,
+                blend_state,
+                use_depth_stencil,
+            )
+        });
 
-        Self { bind_group, pipeline }
+        Self {
+            bind_group: bg_setting.bind_group,
+            pipeline,
+            pipeline_layout,
+            format,
+            blend_state,
+            use_depth_stencil,
+        }
+    }
+
+    // 热重载：绑定关系不变，用新的 shader 重建 pipeline，出错时保留旧的 pipeline
+    pub fn reload(&mut self, device: &wgpu::Device, shader_module: &ShaderModule) {
+        let pipeline = crate::util::shader::try_create(device, || {
+            create_pipeline(
+                device,
+                Some(&self.pipeline_layout),
+                shader_module,
+                self.format,
+                self.blend_state,
+                self.use_depth_stencil,
+            )
+        });
+        match pipeline {
+            Ok(pipeline) => self.pipeline = Rc::new(pipeline),
+            Err(e) => crate::console_log!("bufferless pipeline reload error: {}", e),
+        }
     }
 
     pub fn draw(
@@ -89,6 +142,42 @@ impl BufferlessFullscreenNode {
     }
 }
 
+// layout 为 None 时由 shader 推导
+fn create_pipeline(
+    device: &wgpu::Device, layout: Option<&wgpu::PipelineLayout>, shader_module: &ShaderModule,
+    format: TextureFormat, blend_state: Option<wgpu::BlendState>, use_depth_stencil: bool,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("bufferless fullscreen pipeline"),
+        layout,
+        vertex: wgpu::VertexState { module: shader_module, entry_point: "vs_main", buffers: &[] },
+        fragment: Some(wgpu::FragmentState {
+            module: shader_module,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: blend_state,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        // the bufferless vertices are in clock-wise order
+        primitive: wgpu::PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: Some(wgpu::Face::Front),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            ..Default::default()
+        },
+        depth_stencil: if use_depth_stencil {
+            Some(crate::util::depth_stencil::create_state())
+        } else {
+            None
+        },
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
 pub fn create_bind_group(
     device: &wgpu::Device, uniforms: Vec<&BufferObj>, storage_buffers: Vec<&BufferObj>,
     textures: Vec<&AnyTexture>, samplers: Vec<&wgpu::Sampler>,
@@ -1,9 +1,11 @@
 use wgpu::{PushConstantRange, ShaderModule, StorageTextureAccess};
 
-use super::{BindingGroupSetting, DynamicUniformBindingGroup};
-use crate::util::{buffer::BufferObj, AnyTexture};
+use super::{BindingGroupSetting, Bindings, DynamicUniformBindingGroup};
+use crate::util::resource_pool::{self, PipelineKey};
+use crate::util::{buffer::BufferObj, AnyTexture, ShaderReflection};
 
 use core::ops::Range;
+use std::rc::Rc;
 use std::vec::Vec;
 
 #[allow(dead_code)]
@@ -11,7 +13,8 @@ pub struct ComputeNode {
     pub bg_setting: BindingGroupSetting,
     pub dy_uniform_bg: Option<DynamicUniformBindingGroup>,
     pub pipeline_layout: wgpu::PipelineLayout,
-    pub pipeline: wgpu::ComputePipeline,
+    // 可能与其它节点共用
+    pub pipeline: Rc<wgpu::ComputePipeline>,
     pub group_count: (u32, u32, u32),
 }
 
@@ -33,6 +36,79 @@ impl ComputeNode {
         )
     }
 
+    // 按名称绑定 shader 中 group 0 的资源，资源缺失或类型不符时 panic 并给出变量名
+    pub fn with_bindings(
+        device: &wgpu::Device, group_count: (u32, u32, u32), reflection: &ShaderReflection,
+        bindings: Bindings, shader_module: &ShaderModule,
+    ) -> Self {
+        let bg_setting =
+            match BindingGroupSetting::from_reflection(device, reflection, 0, &bindings) {
+                Ok(setting) => setting,
+                Err(e) => panic!("{}", e),
+            };
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bg_setting.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            vec![bg_setting.layout_entries.clone()],
+            vec![],
+            shader_module,
+            Some(&reflection.shader_name),
+        );
+
+        ComputeNode { bg_setting, dy_uniform_bg: None, pipeline_layout, pipeline, group_count }
+    }
+
+    // group 0 按名称绑定，group 1 依次绑定 dynamic uniform buffer
+    pub fn with_bindings_and_dynamic_uniforms(
+        device: &wgpu::Device, group_count: (u32, u32, u32), reflection: &ShaderReflection,
+        bindings: Bindings, dynamic_uniforms: Vec<&BufferObj>, shader_module: &ShaderModule,
+    ) -> Self {
+        let bg_setting =
+            match BindingGroupSetting::from_reflection(device, reflection, 0, &bindings) {
+                Ok(setting) => setting,
+                Err(e) => panic!("{}", e),
+            };
+        let dynamic_count = reflection.bindings(1).len();
+        if dynamic_count != dynamic_uniforms.len() {
+            panic!(
+                "shader `{}` declares {} dynamic uniforms in group 1, but {} are bound",
+                reflection.shader_name,
+                dynamic_count,
+                dynamic_uniforms.len()
+            );
+        }
+        let dy_uniform_bg = DynamicUniformBindingGroup::new(
+            device,
+            dynamic_uniforms.into_iter().map(|obj| (obj, wgpu::ShaderStages::COMPUTE)).collect(),
+        );
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bg_setting.bind_group_layout, &dy_uniform_bg.bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            vec![bg_setting.layout_entries.clone(), dy_uniform_bg.layout_entries.clone()],
+            vec![],
+            shader_module,
+            Some(&reflection.shader_name),
+        );
+
+        ComputeNode {
+            bg_setting,
+            dy_uniform_bg: Some(dy_uniform_bg),
+            pipeline_layout,
+            pipeline,
+            group_count,
+        }
+    }
+
     pub fn new_with_dynamic_uniforms(
         device: &wgpu::Device, group_count: (u32, u32, u32), uniforms: Vec<&BufferObj>,
         dynamic_uniforms: Vec<&BufferObj>, storage_buffers: Vec<&BufferObj>,
@@ -61,12 +137,14 @@ impl ComputeNode {
             bind_group_layouts: &[&bg_setting.bind_group_layout, &dy_uniform_bg.bind_group_layout],
             push_constant_ranges: &[],
         });
-        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            module: shader_module,
-            entry_point: "cs_main",
-        });
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            vec![bg_setting.layout_entries.clone(), dy_uniform_bg.layout_entries.clone()],
+            vec![],
+            shader_module,
+            None,
+        );
 
         ComputeNode {
             bg_setting,
@@ -108,16 +186,34 @@ impl ComputeNode {
             bind_group_layouts: &[&bg_setting.bind_group_layout],
             push_constant_ranges: &ranges,
         });
-        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            module: shader_module,
-            entry_point: "cs_main",
-        });
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            vec![bg_setting.layout_entries.clone()],
+            ranges,
+            shader_module,
+            None,
+        );
 
         ComputeNode { bg_setting, dy_uniform_bg: None, pipeline_layout, pipeline, group_count }
     }
 
+    // 热重载：绑定关系不变，用新的 shader 重建 pipeline，出错时保留旧的 pipeline
+    pub fn reload(&mut self, device: &wgpu::Device, shader_module: &ShaderModule) {
+        let pipeline = crate::util::shader::try_create(device, || {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: Some(&self.pipeline_layout),
+                module: shader_module,
+                entry_point: "cs_main",
+            })
+        });
+        match pipeline {
+            Ok(pipeline) => self.pipeline = Rc::new(pipeline),
+            Err(e) => crate::console_log!("compute pipeline reload error: {}", e),
+        }
+    }
+
     pub fn compute(&self, encoder: &mut wgpu::CommandEncoder) {
         self.compute_by_offsets(encoder, None);
     }
@@ -152,3 +248,20 @@ impl ComputeNode {
         }
     }
 }
+
+// 同样的 shader 与 bind group layout 共用 resource_pool 中的 pipeline
+fn create_pipeline(
+    device: &wgpu::Device, pipeline_layout: &wgpu::PipelineLayout,
+    layouts: Vec<Vec<wgpu::BindGroupLayoutEntry>>, push_constants: Vec<PushConstantRange>,
+    shader_module: &ShaderModule, label: Option<&str>,
+) -> Rc<wgpu::ComputePipeline> {
+    let key = PipelineKey::compute(shader_module, layouts, push_constants);
+    resource_pool::compute_pipeline(key, || {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label,
+            layout: Some(pipeline_layout),
+            module: shader_module,
+            entry_point: "cs_main",
+        })
+    })
+}
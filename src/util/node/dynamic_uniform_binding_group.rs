@@ -4,6 +4,7 @@ use std::vec::Vec;
 #[allow(dead_code)]
 pub struct DynamicUniformBindingGroup {
     pub bind_group_layout: wgpu::BindGroupLayout,
+    pub layout_entries: Vec<wgpu::BindGroupLayoutEntry>,
     pub bind_group: wgpu::BindGroup,
 }
 
@@ -55,6 +56,6 @@ impl DynamicUniformBindingGroup {
             label: None,
         });
 
-        DynamicUniformBindingGroup { bind_group_layout, bind_group }
+        DynamicUniformBindingGroup { bind_group_layout, layout_entries: layouts, bind_group }
     }
 }
@@ -2,7 +2,7 @@ mod compute_node;
 pub use compute_node::ComputeNode;
 
 mod binding_group_setting;
-pub use binding_group_setting::BindingGroupSetting;
+pub use binding_group_setting::{BindingGroupSetting, Bindings};
 
 mod dynamic_uniform_binding_group;
 pub use dynamic_uniform_binding_group::DynamicUniformBindingGroup;
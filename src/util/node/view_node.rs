@@ -1,12 +1,14 @@
 use crate::util::geometry::Plane;
-use crate::util::node::BindingGroupSetting;
+use crate::util::node::{BindingGroupSetting, Bindings};
+use crate::util::resource_pool::{self, PipelineKey};
 use crate::util::vertex::Vertex;
-use crate::util::{AnyTexture, BufferObj, MVPUniform};
+use crate::util::{AnyTexture, BufferObj, MVPUniform, ShaderReflection};
 use wgpu::util::DeviceExt;
 use wgpu::StorageTextureAccess;
 
 use app_surface::math::{Position, Rect, Size};
 use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
 use zerocopy::AsBytes;
 
 pub struct NodeAttributes<'a, T: Vertex> {
@@ -19,6 +21,8 @@ pub struct NodeAttributes<'a, T: Vertex> {
     pub samplers: Vec<&'a wgpu::Sampler>,
     // 动态 uniform
     pub dynamic_uniforms: Vec<(&'a BufferObj, wgpu::ShaderStages)>,
+    // 按名称绑定，设置后忽略上面按顺序传入的 buffer、纹理与采样器
+    pub bindings: Option<(&'a ShaderReflection, Bindings<'a>)>,
 
     pub tex_rect: Option<Rect>,
     pub corlor_format: Option<wgpu::TextureFormat>,
@@ -62,6 +66,7 @@ impl<'a, T: Vertex + AsBytes> ViewNodeBuilder<'a, T> {
                 tex_views,
                 samplers: vec![],
                 dynamic_uniforms: vec![],
+                bindings: None,
                 tex_rect: None,
                 corlor_format: None,
                 color_blend_state: Some(crate::util::utils::default_blend()),
@@ -130,6 +135,13 @@ impl<'a, T: Vertex + AsBytes> ViewNodeBuilder<'a, T> {
         self
     }
 
+    pub fn with_bindings(
+        mut self, reflection: &'a ShaderReflection, bindings: Bindings<'a>,
+    ) -> Self {
+        self.bindings = Some((reflection, bindings));
+        self
+    }
+
     pub fn with_tex_rect(mut self, rect: Rect) -> Self {
         self.tex_rect = Some(rect);
         self
@@ -157,11 +169,12 @@ impl<'a, T: Vertex + AsBytes> ViewNodeBuilder<'a, T> {
 
     pub fn build(self, device: &wgpu::Device) -> ViewNode {
         debug_assert!(
-            self.shader_stages.len()
-                >= self.uniform_buffers.len()
-                    + self.samplers.len()
-                    + self.storage_buffers.len()
-                    + self.tex_views.len(),
+            self.bindings.is_some()
+                || self.shader_stages.len()
+                    >= self.uniform_buffers.len()
+                        + self.samplers.len()
+                        + self.storage_buffers.len()
+                        + self.tex_views.len(),
             "shader_stages count less than binding resource count"
         );
         ViewNode::frome_attributes::<T>(self.attributes, device)
@@ -175,7 +188,10 @@ pub struct ViewNode {
     pub index_count: usize,
     pub bg_setting: BindingGroupSetting,
     pub dy_uniform_bg: Option<super::DynamicUniformBindingGroup>,
-    pub pipeline: wgpu::RenderPipeline,
+    pub pipeline: Rc<wgpu::RenderPipeline>,
+    // 热重载时用来重建 pipeline
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline_state: PipelineState,
     view_width: f32,
     view_height: f32,
     pub clear_color: wgpu::Color,
@@ -232,14 +248,21 @@ impl ViewNode {
             } else {
                 attributes.uniform_buffers
             };
-        let bg_setting = BindingGroupSetting::new(
-            device,
-            uniform_buffers,
-            attributes.storage_buffers,
-            attributes.tex_views,
-            new_samplers,
-            stages,
-        );
+        let bg_setting = if let Some((reflection, bindings)) = attributes.bindings {
+            match BindingGroupSetting::from_reflection(device, reflection, 0, &bindings) {
+                Ok(setting) => setting,
+                Err(e) => panic!("{}", e),
+            }
+        } else {
+            BindingGroupSetting::new(
+                device,
+                uniform_buffers,
+                attributes.storage_buffers,
+                attributes.tex_views,
+                new_samplers,
+                stages,
+            )
+        };
 
         // Create the vertex and index buffers
         let (vertex_buf, index_data) = if let Some(vi) = attributes.vertices_and_indices {
@@ -285,7 +308,7 @@ impl ViewNode {
         let index_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
             contents: &index_data.as_bytes(),
-            usage: wgpu::BufferUsages::INDEX,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
         });
 
         let default_layout_attributes = T::vertex_attributes(0);
@@ -319,39 +342,35 @@ impl ViewNode {
             (None, pipeline_layout)
         };
 
-        // Create the render pipeline
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("image_view pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &attributes.shader_module,
-                entry_point: "vs_main",
-                buffers: &vertex_buffer_layouts,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &attributes.shader_module,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: corlor_format,
-                    blend: attributes.color_blend_state,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: attributes.primitive_topology,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: attributes.cull_mode,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                ..Default::default()
-            },
-            // ??????
-            depth_stencil: if attributes.use_depth_stencil {
-                Some(crate::util::depth_stencil::create_state())
-            } else {
-                None
-            },
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
+        let pipeline_state = PipelineState {
+            vertex_buffer_layouts: vertex_buffer_layouts
+                .iter()
+                .map(|layout| (layout.array_stride, layout.step_mode, layout.attributes.to_vec()))
+                .collect(),
+            color_format: corlor_format,
+            color_blend_state: attributes.color_blend_state,
+            primitive_topology: attributes.primitive_topology,
+            cull_mode: attributes.cull_mode,
+            use_depth_stencil: attributes.use_depth_stencil,
+        };
+        let mut layouts = vec![bg_setting.layout_entries.clone()];
+        if let Some(dy_bg) = &dy_uniform_bg {
+            layouts.push(dy_bg.layout_entries.clone());
+        }
+        let key = PipelineKey::render(
+            attributes.shader_module,
+            layouts,
+            corlor_format,
+            attributes.color_blend_state,
+            attributes.use_depth_stencil,
+        )
+        .with_vertex(
+            pipeline_state.vertex_buffer_layouts.clone(),
+            attributes.primitive_topology,
+            attributes.cull_mode,
+        );
+        let pipeline = resource_pool::render_pipeline(key, || {
+            pipeline_state.create_pipeline(device, &pipeline_layout, attributes.shader_module)
         });
 
         ViewNode {
@@ -363,10 +382,29 @@ impl ViewNode {
             bg_setting,
             dy_uniform_bg,
             pipeline,
+            pipeline_layout,
+            pipeline_state,
             clear_color: crate::util::utils::alpha_color(),
         }
     }
 
+    // 替换索引，数量不能超过创建时的索引数
+    pub fn update_indices(&mut self, queue: &wgpu::Queue, indices: &[u32]) {
+        queue.write_buffer(&self.index_buf, 0, indices.as_bytes());
+        self.index_count = indices.len();
+    }
+
+    // 热重载：绑定关系不变，用新的 shader 重建 pipeline，出错时保留旧的 pipeline
+    pub fn reload(&mut self, device: &wgpu::Device, shader_module: &wgpu::ShaderModule) {
+        let pipeline = crate::util::shader::try_create(device, || {
+            self.pipeline_state.create_pipeline(device, &self.pipeline_layout, shader_module)
+        });
+        match pipeline {
+            Ok(pipeline) => self.pipeline = Rc::new(pipeline),
+            Err(e) => crate::console_log!("view pipeline reload error: {}", e),
+        }
+    }
+
     // 视口的宽高发生变化
     pub fn resize(&mut self, queue: &wgpu::Queue, tex_rect: Option<app_surface::math::Rect>) {
         if let Some(buf) = &self.vertex_buf {
@@ -434,3 +472,61 @@ impl ViewNode {
         rpass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count);
     }
 }
+
+struct PipelineState {
+    vertex_buffer_layouts: Vec<resource_pool::VertexLayout>,
+    color_format: wgpu::TextureFormat,
+    color_blend_state: Option<wgpu::BlendState>,
+    primitive_topology: wgpu::PrimitiveTopology,
+    cull_mode: Option<wgpu::Face>,
+    use_depth_stencil: bool,
+}
+
+impl PipelineState {
+    fn create_pipeline(
+        &self, device: &wgpu::Device, pipeline_layout: &wgpu::PipelineLayout,
+        shader_module: &wgpu::ShaderModule,
+    ) -> wgpu::RenderPipeline {
+        let vertex_buffer_layouts: Vec<wgpu::VertexBufferLayout> = self
+            .vertex_buffer_layouts
+            .iter()
+            .map(|(array_stride, step_mode, attributes)| wgpu::VertexBufferLayout {
+                array_stride: *array_stride,
+                step_mode: *step_mode,
+                attributes,
+            })
+            .collect();
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("image_view pipeline"),
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader_module,
+                entry_point: "vs_main",
+                buffers: &vertex_buffer_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.color_format,
+                    blend: self.color_blend_state,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: self.primitive_topology,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: self.cull_mode,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                ..Default::default()
+            },
+            depth_stencil: if self.use_depth_stencil {
+                Some(crate::util::depth_stencil::create_state())
+            } else {
+                None
+            },
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+}
@@ -0,0 +1,472 @@
+// WGSL 预处理：运行时的 util::shader 与构建时的 build.rs 共用
+//
+// #include "a.wgsl", "b.wgsl"   同一个文件只展开一次；只展开 include 时，条件编译块里的 include 只在块内去重
+// #define NAME value           之后代码中的 NAME 替换为 value，value 可以为空
+// #undef NAME
+// #ifdef NAME / #ifndef NAME / #else / #endif
+// #slot(name)                  在行内插入外部传入的代码，#insert_code_segment 等同于 #slot(code_segment)
+//
+// build.rs 与 examples 通过 #[path] 引用此文件，各自只用到其中一部分
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+const SLOT: &str = "#slot(";
+const LEGACY_SLOT: &str = "#insert_code_segment";
+pub const DEFAULT_SLOT: &str = "code_segment";
+
+// file 为 shader-wgsl 下的相对路径，line 从 1 开始
+#[derive(Debug)]
+pub struct PreprocessError {
+    pub file: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file, self.line, self.message)
+    }
+}
+
+impl std::error::Error for PreprocessError {}
+
+#[derive(Debug)]
+pub struct PreprocessedShader {
+    pub code: String,
+    files: Vec<String>,
+    // 输出的每一行对应的源文件序号与行号
+    line_map: Vec<(usize, usize)>,
+}
+
+impl PreprocessedShader {
+    // line 为展开后代码的行号，从 1 开始
+    pub fn source_location(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, source_line) = self.line_map.get(line.checked_sub(1)?)?;
+        Some((&self.files[*file], *source_line))
+    }
+
+    // 展开时用到的所有文件，第一个为 shader 自身
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    // 把 naga 错误信息中的 `wgsl:行:列` 换成源文件的位置
+    pub fn map_error_message(&self, message: &str) -> String {
+        let mut output = String::new();
+        let mut rest = message;
+        while let Some(i) = rest.find("wgsl:") {
+            output.push_str(&rest[..i]);
+            let after = &rest[i + 5..];
+            let digits = after.chars().take_while(|c| c.is_ascii_digit()).count();
+            match after[..digits].parse::<usize>().ok().and_then(|l| self.source_location(l)) {
+                Some((file, line)) => {
+                    output.push_str(&format!("{}:{}", file, line));
+                    rest = &after[digits..];
+                }
+                None => {
+                    output.push_str("wgsl:");
+                    rest = after;
+                }
+            }
+        }
+        output.push_str(rest);
+        output
+    }
+}
+
+struct Condition {
+    is_active: bool,
+    has_else: bool,
+    line: usize,
+}
+
+struct State {
+    defines: HashMap<String, String>,
+    included: HashSet<String>,
+    output: PreprocessedShader,
+}
+
+pub struct ShaderPreprocessor<'a> {
+    // 按 shader-wgsl 下的相对路径读取源码
+    loader: Box<dyn Fn(&str) -> Option<String> + 'a>,
+    defines: HashMap<String, String>,
+    slots: HashMap<String, String>,
+    // 只展开 include，宏、条件编译与 slot 保留，用于构建时的检查与查看展开后的代码
+    is_includes_only: bool,
+}
+
+impl<'a> ShaderPreprocessor<'a> {
+    pub fn new(loader: impl Fn(&str) -> Option<String> + 'a) -> Self {
+        Self {
+            loader: Box::new(loader),
+            defines: HashMap::new(),
+            slots: HashMap::new(),
+            is_includes_only: false,
+        }
+    }
+
+    pub fn from_dir(dir: PathBuf) -> Self {
+        Self::new(move |path| std::fs::read_to_string(dir.join(path)).ok())
+    }
+
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn slot(mut self, name: &str, code: &str) -> Self {
+        self.slots.insert(name.to_string(), code.to_string());
+        self
+    }
+
+    pub fn includes_only(mut self) -> Self {
+        self.is_includes_only = true;
+        self
+    }
+
+    // shader_name 不带扩展名，如 "lbm/present"
+    pub fn process(&self, shader_name: &str) -> Result<PreprocessedShader, PreprocessError> {
+        let file = format!("{}.wgsl", shader_name);
+        match (self.loader)(&file) {
+            Some(source) => self.process_source(&file, &source),
+            None => {
+                Err(PreprocessError { file, line: 0, message: "cannot read shader".to_string() })
+            }
+        }
+    }
+
+    pub fn process_source(
+        &self, file: &str, source: &str,
+    ) -> Result<PreprocessedShader, PreprocessError> {
+        let mut state = State {
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+            output: PreprocessedShader { code: String::new(), files: vec![], line_map: vec![] },
+        };
+        state.included.insert(file.to_string());
+        self.expand(&mut state, file, source)?;
+        Ok(state.output)
+    }
+
+    fn expand(&self, state: &mut State, file: &str, source: &str) -> Result<(), PreprocessError> {
+        let file_index = state.output.files.len();
+        state.output.files.push(file.to_string());
+        let mut conditions: Vec<Condition> = vec![];
+        // 只展开 include 时，进入条件编译块之前已经展开的文件
+        let mut included_before_blocks: Vec<HashSet<String>> = vec![];
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let error = |message: String| PreprocessError {
+                file: file.to_string(),
+                line: line_number,
+                message,
+            };
+            let is_active = conditions.iter().all(|c| c.is_active);
+            let trimmed = line.trim_start();
+            let is_directive = trimmed.starts_with('#')
+                && !trimmed.starts_with(SLOT)
+                && !trimmed.starts_with(LEGACY_SLOT);
+            if is_directive {
+                let directive = &trimmed[1..];
+                let name = directive.split_whitespace().next().unwrap_or("");
+                if self.is_includes_only && name != "include" {
+                    // 条件编译在运行时才确定，块内展开的文件不影响块外的 include
+                    match name {
+                        "ifdef" | "ifndef" => included_before_blocks.push(state.included.clone()),
+                        "else" => {
+                            if let Some(included) = included_before_blocks.last() {
+                                state.included = included.clone();
+                            }
+                        }
+                        "endif" => {
+                            if let Some(included) = included_before_blocks.pop() {
+                                state.included = included;
+                            }
+                        }
+                        _ => {}
+                    }
+                    state.output.code.push_str(line);
+                    state.output.code.push('\n');
+                    state.output.line_map.push((file_index, line_number));
+                    continue;
+                }
+                let arg = directive[name.len()..].trim();
+                let require_arg = || {
+                    if arg.is_empty() {
+                        Err(error(format!("`#{}` needs a name", name)))
+                    } else {
+                        Ok(arg)
+                    }
+                };
+                match name {
+                    "ifdef" | "ifndef" => {
+                        let is_defined = state.defines.contains_key(require_arg()?);
+                        conditions.push(Condition {
+                            is_active: is_defined == (name == "ifdef"),
+                            has_else: false,
+                            line: line_number,
+                        });
+                    }
+                    "else" => {
+                        let condition = conditions
+                            .last_mut()
+                            .ok_or_else(|| error("`#else` without `#ifdef`".to_string()))?;
+                        if condition.has_else {
+                            return Err(error("duplicate `#else`".to_string()));
+                        }
+                        condition.has_else = true;
+                        condition.is_active = !condition.is_active;
+                    }
+                    "endif" => {
+                        if conditions.pop().is_none() {
+                            return Err(error("`#endif` without `#ifdef`".to_string()));
+                        }
+                    }
+                    _ if !is_active => {}
+                    "define" => {
+                        let mut parts = require_arg()?.splitn(2, char::is_whitespace);
+                        let key = parts.next().unwrap_or("").to_string();
+                        let value = substitute(&state.defines, parts.next().unwrap_or("").trim());
+                        state.defines.insert(key, value);
+                    }
+                    "undef" => {
+                        state.defines.remove(require_arg()?);
+                    }
+                    "include" => {
+                        for import in require_arg()?.split(',') {
+                            let path = import.trim().trim_matches('"');
+                            if path.is_empty() {
+                                return Err(error("empty include path".to_string()));
+                            }
+                            if !state.included.insert(path.to_string()) {
+                                continue;
+                            }
+                            let code = (self.loader)(path)
+                                .ok_or_else(|| error(format!("cannot find include `{}`", path)))?;
+                            self.expand(state, path, &code)?;
+                        }
+                    }
+                    _ => return Err(error(format!("unknown directive `#{}`", name))),
+                }
+                continue;
+            }
+            if !is_active {
+                continue;
+            }
+            let line = substitute(&state.defines, &self.fill_slots(line).map_err(error)?);
+            for output_line in line.split('\n') {
+                state.output.code.push_str(output_line.trim_end_matches('\r'));
+                state.output.code.push('\n');
+                state.output.line_map.push((file_index, line_number));
+            }
+        }
+        if let Some(condition) = conditions.last() {
+            return Err(PreprocessError {
+                file: file.to_string(),
+                line: condition.line,
+                message: "unterminated `#ifdef`".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn fill_slots(&self, line: &str) -> Result<String, String> {
+        if !line.contains(SLOT) && !line.contains(LEGACY_SLOT) {
+            return Ok(line.to_string());
+        }
+        let legacy = format!("{}{})", SLOT, DEFAULT_SLOT);
+        let line = line.replace(LEGACY_SLOT, &legacy);
+        let mut output = String::new();
+        let mut rest = line.as_str();
+        while let Some(start) = rest.find(SLOT) {
+            output.push_str(&rest[..start]);
+            let after = &rest[start + SLOT.len()..];
+            let end = after.find(')').ok_or_else(|| "unclosed `#slot(`".to_string())?;
+            let name = after[..end].trim();
+            match self.slots.get(name) {
+                Some(code) => output.push_str(code),
+                None if self.is_includes_only => {
+                    output.push_str(&rest[start..start + SLOT.len() + end + 1])
+                }
+                None => return Err(format!("slot `{}` is not filled", name)),
+            }
+            rest = &after[end + 1..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+// 替换代码中的宏，注释保持不变
+fn substitute(defines: &HashMap<String, String>, line: &str) -> String {
+    if defines.values().all(|value| value.is_empty()) {
+        return line.to_string();
+    }
+    let (code, comment) = match line.find("//") {
+        Some(i) => line.split_at(i),
+        None => (line, ""),
+    };
+    let mut output = String::with_capacity(line.len());
+    let mut token = String::new();
+    let push_token = |output: &mut String, token: &mut String| {
+        match defines.get(token.as_str()).filter(|value| !value.is_empty()) {
+            Some(value) => output.push_str(value),
+            None => output.push_str(token),
+        }
+        token.clear();
+    };
+    for c in code.chars() {
+        if c == '_' || c.is_ascii_alphanumeric() {
+            token.push(c);
+        } else {
+            push_token(&mut output, &mut token);
+            output.push(c);
+        }
+    }
+    push_token(&mut output, &mut token);
+    output.push_str(comment);
+    output
+}
+
+// 有入口函数且没有被其它 shader include 的文件，返回不带扩展名的相对路径，如 "lbm/present"
+pub fn entry_shaders(root: &Path) -> Vec<String> {
+    let mut files = vec![];
+    collect_wgsl(root, &mut files);
+    let mut included = vec![];
+    for file in files.iter() {
+        let code = std::fs::read_to_string(file).unwrap_or_default();
+        for line in code.lines() {
+            if let Some(imports) = line.trim_start().strip_prefix("#include") {
+                for import in imports.split(',') {
+                    included.push(root.join(import.trim().trim_matches('"')));
+                }
+            }
+        }
+    }
+    let mut names = vec![];
+    for file in files.iter() {
+        let code = std::fs::read_to_string(file).unwrap_or_default();
+        let has_entry = ["@compute", "@vertex", "@fragment"].iter().any(|s| code.contains(s));
+        if has_entry && !included.contains(file) {
+            let name = file.strip_prefix(root).unwrap().with_extension("");
+            names.push(name.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    names.sort();
+    names
+}
+
+// shader-wgsl 下的所有 wgsl 文件，返回带扩展名的相对路径，如 "lbm/present.wgsl"
+pub fn wgsl_files(root: &Path) -> Vec<String> {
+    let mut files = vec![];
+    collect_wgsl(root, &mut files);
+    let mut names: Vec<String> = files
+        .iter()
+        .map(|file| file.strip_prefix(root).unwrap().to_string_lossy().replace('\\', "/"))
+        .collect();
+    names.sort();
+    names
+}
+
+fn collect_wgsl(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_wgsl(&path, files);
+        } else if path.extension().map_or(false, |ext| ext == "wgsl") {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preprocessor<'a>(files: &'a [(&'a str, &'a str)]) -> ShaderPreprocessor<'a> {
+        ShaderPreprocessor::new(move |path| {
+            files.iter().find(|(name, _)| *name == path).map(|(_, code)| code.to_string())
+        })
+    }
+
+    #[test]
+    fn include_each_file_once() {
+        let files = [
+            ("main.wgsl", "#include \"a.wgsl\", \"b.wgsl\"\nfn main() {}"),
+            ("a.wgsl", "#include \"common.wgsl\"\nfn a() {}"),
+            ("b.wgsl", "#include \"common.wgsl\"\nfn b() {}"),
+            ("common.wgsl", "let PI = 3.14;"),
+        ];
+        let shader = preprocessor(&files).process("main").unwrap();
+        assert_eq!(shader.code, "let PI = 3.14;\nfn a() {}\nfn b() {}\nfn main() {}\n");
+        assert_eq!(shader.files(), ["main.wgsl", "a.wgsl", "common.wgsl", "b.wgsl"]);
+    }
+
+    #[test]
+    fn includes_only_keeps_conditional_includes_local() {
+        let files = [
+            ("main.wgsl", "#ifdef A\n#include \"common.wgsl\"\n#endif\n#include \"common.wgsl\""),
+            ("common.wgsl", "let PI = 3.14;"),
+        ];
+        let shader = preprocessor(&files).includes_only().process("main").unwrap();
+        assert_eq!(shader.code, "#ifdef A\nlet PI = 3.14;\n#endif\nlet PI = 3.14;\n");
+    }
+
+    #[test]
+    fn nested_conditionals() {
+        let source = "#ifdef A\n#ifndef B\na_only\n#else\na_and_b\n#endif\n#else\nno_a\n#endif";
+        let process = |defines: &[&str]| {
+            let mut preprocessor = preprocessor(&[]);
+            for name in defines {
+                preprocessor = preprocessor.define(name, "");
+            }
+            preprocessor.process_source("main.wgsl", source).unwrap().code
+        };
+        assert_eq!(process(&[]), "no_a\n");
+        assert_eq!(process(&["A"]), "a_only\n");
+        assert_eq!(process(&["A", "B"]), "a_and_b\n");
+        assert_eq!(process(&["B"]), "no_a\n");
+    }
+
+    #[test]
+    fn unterminated_ifdef() {
+        let source = "fn main() {}\n#ifdef A\n#ifdef B\n#endif";
+        let error = preprocessor(&[]).process_source("main.wgsl", source).unwrap_err();
+        assert_eq!((error.file.as_str(), error.line), ("main.wgsl", 2));
+        assert!(error.message.contains("unterminated"));
+    }
+
+    #[test]
+    fn missing_include() {
+        let files = [("main.wgsl", "fn main() {}\n#include \"missing.wgsl\"")];
+        let error = preprocessor(&files).process("main").unwrap_err();
+        assert_eq!((error.file.as_str(), error.line), ("main.wgsl", 2));
+        assert!(error.message.contains("missing.wgsl"));
+
+        let error = preprocessor(&files).process("other").unwrap_err();
+        assert_eq!((error.file.as_str(), error.line), ("other.wgsl", 0));
+    }
+
+    #[test]
+    fn map_error_message_to_source_lines() {
+        let files = [
+            ("main.wgsl", "#define N 4\n#include \"common.wgsl\"\nfn main() {}"),
+            ("common.wgsl", "// common\nfn helper() {}"),
+        ];
+        let shader = preprocessor(&files).process("main").unwrap();
+        assert_eq!(shader.source_location(2), Some(("common.wgsl", 2)));
+        assert_eq!(shader.source_location(3), Some(("main.wgsl", 3)));
+        assert_eq!(shader.source_location(4), None);
+        assert_eq!(
+            shader.map_error_message("error at wgsl:3:4, see wgsl:2:1 and wgsl:9:1"),
+            "error at main.wgsl:3:4, see common.wgsl:2:1 and wgsl:9:1"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+// 把 GPU 上的数据读回 CPU
+//
+// 先把数据复制到一个 MAP_READ 的 staging buffer，提交后再映射它：
+//   - wasm 上映射的回调由浏览器触发，只能 await
+//   - native 上回调只会在 device.poll 中触发，可以用 wait 阻塞到读回完成；
+//     await 也会在第一次 poll 时阻塞，native 上应直接使用 read
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use zerocopy::FromBytes;
+
+#[derive(Default)]
+struct MapState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+// 已经提交了复制命令、正在等待映射的 staging buffer
+pub(crate) struct PendingRead {
+    staging_buf: wgpu::Buffer,
+    state: Arc<Mutex<MapState>>,
+}
+
+impl PendingRead {
+    // 在 validation error scope 中录制复制命令并提交，源资源缺少 COPY_SRC 等错误会直接返回
+    pub(crate) fn submit(
+        device: &wgpu::Device, queue: &wgpu::Queue, size: wgpu::BufferAddress,
+        copy: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::Buffer),
+    ) -> Result<Self, String> {
+        let staging_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback buf"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let command_buf = super::shader::try_create(device, || {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("readback encoder"),
+            });
+            copy(&mut encoder, &staging_buf);
+            encoder.finish()
+        })?;
+        queue.submit(Some(command_buf));
+
+        let state = Arc::new(Mutex::new(MapState::default()));
+        let callback_state = state.clone();
+        staging_buf.slice(..).map_async(wgpu::MapMode::Read, move |res| {
+            let mut state = callback_state.lock().unwrap();
+            state.result = Some(res);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Ok(Self { staging_buf, state })
+    }
+
+    pub(crate) async fn bytes(self, device: &wgpu::Device) -> Result<Vec<u8>, String> {
+        MapFuture { device, state: self.state.clone() }.await?;
+        Ok(self.take_bytes())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn wait(self, device: &wgpu::Device) -> Result<Vec<u8>, String> {
+        device.poll(wgpu::Maintain::Wait);
+        let result = self.state.lock().unwrap().result.take();
+        match result {
+            Some(Ok(())) => Ok(self.take_bytes()),
+            Some(Err(e)) => Err(format!("failed to map the readback buffer: {}", e)),
+            None => Err("the readback buffer is not mapped after device.poll".to_string()),
+        }
+    }
+
+    fn take_bytes(self) -> Vec<u8> {
+        let bytes = self.staging_buf.slice(..).get_mapped_range().to_vec();
+        self.staging_buf.unmap();
+        bytes
+    }
+}
+
+struct MapFuture<'a> {
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    device: &'a wgpu::Device,
+    state: Arc<Mutex<MapState>>,
+}
+
+impl<'a> Future for MapFuture<'a> {
+    type Output = Result<(), String>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // native 上没有后台线程推进映射，阻塞到提交的命令执行完，避免执行器空转
+        #[cfg(not(target_arch = "wasm32"))]
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(Ok(())) => Poll::Ready(Ok(())),
+            Some(Err(e)) => Poll::Ready(Err(format!("failed to map the readback buffer: {}", e))),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+// 按 T 的大小切分字节，末尾不足一个 T 的部分丢弃
+pub(crate) fn cast_bytes<T: FromBytes + Copy>(bytes: &[u8]) -> Vec<T> {
+    let item_size = std::mem::size_of::<T>();
+    if item_size == 0 {
+        return vec![];
+    }
+    bytes.chunks_exact(item_size).map(|chunk| T::read_from(chunk).unwrap()).collect()
+}
+
+// 纹理复制到 buffer 时每行的字节数需要按 256 对齐
+pub(crate) fn padded_bytes_per_row(bytes_per_row: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (bytes_per_row + align - 1) / align * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{load_texture, BufferObj};
+
+    // 没有可用的 GPU 时跳过需要设备的测试
+    #[cfg(not(target_arch = "wasm32"))]
+    fn test_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))?;
+        let desc = wgpu::DeviceDescriptor {
+            label: None,
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::downlevel_defaults(),
+        };
+        pollster::block_on(adapter.request_device(&desc, None)).ok()
+    }
+
+    #[test]
+    fn pad_bytes_per_row() {
+        assert_eq!(padded_bytes_per_row(0), 0);
+        assert_eq!(padded_bytes_per_row(1), 256);
+        assert_eq!(padded_bytes_per_row(256), 256);
+        assert_eq!(padded_bytes_per_row(257), 512);
+    }
+
+    #[test]
+    fn cast_drops_trailing_bytes() {
+        let bytes = [1_u8, 0, 0, 0, 2, 0, 0, 0, 3];
+        assert_eq!(cast_bytes::<u32>(&bytes), vec![1, 2]);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn read_buffer() {
+        let (device, queue) = match test_device() {
+            Some(device) => device,
+            None => return,
+        };
+        let data: Vec<u32> = (0..64).collect();
+        let usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let buffer = BufferObj::create_buffer(&device, Some(&data), None, usage, None);
+        assert_eq!(buffer.read::<u32>(&device, &queue).unwrap(), data);
+        let read_async = pollster::block_on(buffer.read_async::<u32>(&device, &queue));
+        assert_eq!(read_async.unwrap(), data);
+
+        // 没有 COPY_SRC 时返回错误
+        let buffer = BufferObj::create_storage_buffer(&device, &data, None);
+        assert!(buffer.read::<u32>(&device, &queue).is_err());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn read_texture_without_row_padding() {
+        let (device, queue) = match test_device() {
+            Some(device) => device,
+            None => return,
+        };
+        // 每行 12 字节，复制时补齐到 256
+        let size = wgpu::Extent3d { width: 3, height: 2, depth_or_array_layers: 1 };
+        let texture = load_texture::empty(
+            &device,
+            wgpu::TextureFormat::Rgba8Unorm,
+            size,
+            None,
+            Some(wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST),
+            None,
+        );
+        let pixels: Vec<[u8; 4]> = (0..6).map(|i| [i, i + 1, i + 2, 255]).collect();
+        let bytes: Vec<u8> = pixels.iter().flatten().cloned().collect();
+        queue.write_texture(
+            texture.tex.as_image_copy(),
+            &bytes,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(12),
+                rows_per_image: None,
+            },
+            size,
+        );
+        assert_eq!(texture.read::<[u8; 4]>(&device, &queue).unwrap(), pixels);
+    }
+}
@@ -0,0 +1,441 @@
+// 声明式的帧图：替代各个 player 里手写的 encoder 流程
+//
+// 每个 pass 声明它读写的资源，compile 时按依赖排定执行顺序：
+// 同一资源的写入按声明顺序执行，读取排在所有写入之后，出现环或未知资源时返回错误。
+// 资源用名称标识：
+//   - 节点自己持有的 buffer/纹理用 import 声明
+//   - add_texture 声明的纹理在 compile 时由图按 surface 尺寸分配；
+//     surface 尺寸变化时 player 整体重建，旧纹理回收到 resource_pool，新的图直接复用并清空
+//   - FRAME 为当前帧的 surface 纹理
+// T 为执行时传给各个 pass 的上下文，通常是 player 自身
+use super::resource_pool::PooledTexture;
+use super::{AnyTexture, GpuProfiler};
+use std::collections::HashMap;
+
+pub const FRAME: &str = "frame";
+
+// 由图分配的纹理，尺寸为 surface 尺寸乘以 scale
+pub struct TextureDesc {
+    pub format: wgpu::TextureFormat,
+    pub scale: f32,
+    pub usage: wgpu::TextureUsages,
+}
+
+type ComputeFn<T> = Box<dyn for<'a> Fn(&'a T, &mut wgpu::ComputePass<'a>)>;
+type RenderFn<T> = Box<dyn for<'a> Fn(&'a T, &mut wgpu::RenderPass<'a>)>;
+
+enum PassKind<T> {
+    Compute(ComputeFn<T>),
+    Render { target: &'static str, load_op: wgpu::LoadOp<wgpu::Color>, draw: RenderFn<T> },
+}
+
+pub struct GraphPass<T> {
+    name: &'static str,
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+    condition: Option<Box<dyn Fn(&T) -> bool>>,
+    repeat: Option<Box<dyn Fn(&T) -> u32>>,
+    kind: PassKind<T>,
+}
+
+impl<T> GraphPass<T> {
+    // 每帧执行前检查，返回 false 时跳过此 pass
+    pub fn run_if(&mut self, condition: impl Fn(&T) -> bool + 'static) -> &mut Self {
+        self.condition = Some(Box::new(condition));
+        self
+    }
+
+    // 每帧执行 count 次，只用于 compute pass
+    // 执行顺序上相邻的 repeat pass 组成一组交替执行，例如每次 lbm 迭代之后紧跟着计算旋度；
+    // 一组的次数取组内第一个启用的 pass 的 count。不计时的时候整组共用一个 compute pass
+    pub fn repeat(&mut self, count: impl Fn(&T) -> u32 + 'static) -> &mut Self {
+        assert!(matches!(self.kind, PassKind::Compute(_)), "only compute passes can repeat");
+        self.repeat = Some(Box::new(count));
+        self
+    }
+
+    fn is_enabled(&self, context: &T) -> bool {
+        self.condition.as_ref().map_or(true, |condition| condition(context))
+    }
+
+    fn repeat_count(&self, context: &T) -> u32 {
+        self.repeat.as_ref().map_or(1, |count| count(context))
+    }
+}
+
+struct GraphTexture {
+    desc: TextureDesc,
+    texture: Option<PooledTexture>,
+}
+
+pub struct RenderGraph<T> {
+    passes: Vec<GraphPass<T>>,
+    imports: Vec<&'static str>,
+    textures: HashMap<&'static str, GraphTexture>,
+    // compile 之后的执行顺序
+    order: Vec<usize>,
+}
+
+#[allow(dead_code)]
+impl<T> RenderGraph<T> {
+    pub fn new() -> Self {
+        Self { passes: vec![], imports: vec![], textures: HashMap::new(), order: vec![] }
+    }
+
+    // 节点自己持有的资源
+    pub fn import(&mut self, names: &[&'static str]) -> &mut Self {
+        self.imports.extend_from_slice(names);
+        self
+    }
+
+    pub fn add_texture(&mut self, name: &'static str, desc: TextureDesc) -> &mut Self {
+        self.textures.insert(name, GraphTexture { desc, texture: None });
+        self
+    }
+
+    pub fn add_compute_pass(
+        &mut self, name: &'static str, reads: &[&'static str], writes: &[&'static str],
+        dispatch: impl for<'a> Fn(&'a T, &mut wgpu::ComputePass<'a>) + 'static,
+    ) -> &mut GraphPass<T> {
+        self.push_pass(name, reads, writes, PassKind::Compute(Box::new(dispatch)))
+    }
+
+    // target 为 FRAME 或 add_texture 声明的纹理
+    pub fn add_render_pass(
+        &mut self, name: &'static str, reads: &[&'static str], target: &'static str,
+        load_op: wgpu::LoadOp<wgpu::Color>,
+        draw: impl for<'a> Fn(&'a T, &mut wgpu::RenderPass<'a>) + 'static,
+    ) -> &mut GraphPass<T> {
+        let kind = PassKind::Render { target, load_op, draw: Box::new(draw) };
+        self.push_pass(name, reads, &[target], kind)
+    }
+
+    fn push_pass(
+        &mut self, name: &'static str, reads: &[&'static str], writes: &[&'static str],
+        kind: PassKind<T>,
+    ) -> &mut GraphPass<T> {
+        self.passes.push(GraphPass {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+            condition: None,
+            repeat: None,
+            kind,
+        });
+        self.passes.last_mut().unwrap()
+    }
+
+    // 检查资源、排定执行顺序并分配纹理，之后的节点才能用 texture() 绑定图里的纹理
+    pub fn compile(
+        &mut self, device: &wgpu::Device, queue: &wgpu::Queue, surface_size: (u32, u32),
+    ) -> Result<(), String> {
+        self.validate()?;
+        self.order = self.sort()?;
+        self.allocate(device, queue, surface_size);
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for pass in self.passes.iter() {
+            for name in pass.reads.iter().chain(pass.writes.iter()) {
+                if !self.is_known(name) {
+                    return Err(format!("pass `{}` uses unknown resource `{}`", pass.name, name));
+                }
+            }
+            if let PassKind::Render { target, .. } = &pass.kind {
+                if *target != FRAME && !self.textures.contains_key(target) {
+                    return Err(format!(
+                        "render pass `{}` targets `{}`, which is not a graph texture",
+                        pass.name, target
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_known(&self, name: &str) -> bool {
+        name == FRAME || self.imports.contains(&name) || self.textures.contains_key(name)
+    }
+
+    // 拓扑排序，没有依赖关系的 pass 保持声明顺序
+    fn sort(&self) -> Result<Vec<usize>, String> {
+        let count = self.passes.len();
+        let mut edges: Vec<Vec<usize>> = vec![vec![]; count];
+        let mut in_degree = vec![0; count];
+        let mut add_edge = |from: usize, to: usize| {
+            if from != to && !edges[from].contains(&to) {
+                edges[from].push(to);
+                in_degree[to] += 1;
+            }
+        };
+        let mut resources: Vec<&'static str> = vec![];
+        for pass in self.passes.iter() {
+            for name in pass.reads.iter().chain(pass.writes.iter()) {
+                if !resources.contains(name) {
+                    resources.push(name);
+                }
+            }
+        }
+        for name in resources {
+            let writers: Vec<usize> =
+                (0..count).filter(|i| self.passes[*i].writes.contains(&name)).collect();
+            for pair in writers.windows(2) {
+                add_edge(pair[0], pair[1]);
+            }
+            for reader in (0..count).filter(|i| self.passes[*i].reads.contains(&name)) {
+                // 同时读写的 pass 只算作写入
+                if writers.contains(&reader) {
+                    continue;
+                }
+                for writer in writers.iter() {
+                    add_edge(*writer, reader);
+                }
+            }
+        }
+
+        let mut order = vec![];
+        let mut is_done = vec![false; count];
+        while order.len() < count {
+            let next = (0..count).find(|i| !is_done[*i] && in_degree[*i] == 0);
+            let index = match next {
+                Some(index) => index,
+                None => {
+                    let names: Vec<&str> =
+                        (0..count).filter(|i| !is_done[*i]).map(|i| self.passes[i].name).collect();
+                    return Err(format!("render graph has a cycle among {:?}", names));
+                }
+            };
+            is_done[index] = true;
+            order.push(index);
+            for to in edges[index].iter() {
+                in_degree[*to] -= 1;
+            }
+        }
+        Ok(order)
+    }
+
+    fn allocate(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, surface_size: (u32, u32)) {
+        self.recycle();
+        for (name, item) in self.textures.iter_mut() {
+            let size = wgpu::Extent3d {
+                width: ((surface_size.0 as f32 * item.desc.scale) as u32).max(1),
+                height: ((surface_size.1 as f32 * item.desc.scale) as u32).max(1),
+                depth_or_array_layers: 1,
+            };
+            let desc = &item.desc;
+            item.texture =
+                Some(PooledTexture::new(device, queue, desc.format, size, desc.usage, Some(*name)));
+        }
+    }
+
+    // 把图里的纹理还给 resource_pool，之后需要重新 compile 才能使用
+    pub fn recycle(&mut self) {
+        for item in self.textures.values_mut() {
+            if let Some(mut texture) = item.texture.take() {
+                texture.recycle();
+            }
+        }
+    }
+
+    pub fn texture(&self, name: &str) -> &AnyTexture {
+        match self.textures.get(name).and_then(|item| item.texture.as_ref()) {
+            Some(texture) => texture,
+            None => panic!("render graph texture `{}` is not allocated, call compile first", name),
+        }
+    }
+
+    // 按 compile 排定的顺序把所有 pass 录制到 encoder，每个 pass 单独计时
+    pub fn execute(
+        &self, encoder: &mut wgpu::CommandEncoder, context: &T, frame_view: &wgpu::TextureView,
+        profiler: &mut GpuProfiler,
+    ) {
+        debug_assert!(self.order.len() == self.passes.len(), "render graph is not compiled");
+        let mut i = 0;
+        while i < self.order.len() {
+            let pass = &self.passes[self.order[i]];
+            if pass.repeat.is_none() {
+                if pass.is_enabled(context) {
+                    self.execute_pass(encoder, context, frame_view, profiler, pass);
+                }
+                i += 1;
+                continue;
+            }
+            let (group_len, group) = self.repeat_group(i, context);
+            i += group_len;
+            let count = match group.first() {
+                Some(first) => first.repeat_count(context),
+                None => continue,
+            };
+            if count == 0 {
+                continue;
+            }
+            if profiler.is_timing() {
+                for _ in 0..count {
+                    for pass in group.iter() {
+                        self.execute_pass(encoder, context, frame_view, profiler, pass);
+                    }
+                }
+            } else {
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some(group[0].name),
+                });
+                for _ in 0..count {
+                    for pass in group.iter() {
+                        if let PassKind::Compute(dispatch) = &pass.kind {
+                            dispatch(context, &mut cpass);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // 从 order[start] 开始相邻的 repeat pass：(组内 pass 数, 其中启用的 pass)
+    fn repeat_group(&self, start: usize, context: &T) -> (usize, Vec<&GraphPass<T>>) {
+        let group_len = self.order[start..]
+            .iter()
+            .take_while(|index| self.passes[**index].repeat.is_some())
+            .count();
+        let group = self.order[start..start + group_len]
+            .iter()
+            .map(|index| &self.passes[*index])
+            .filter(|pass| pass.is_enabled(context))
+            .collect();
+        (group_len, group)
+    }
+
+    fn execute_pass(
+        &self, encoder: &mut wgpu::CommandEncoder, context: &T, frame_view: &wgpu::TextureView,
+        profiler: &mut GpuProfiler, pass: &GraphPass<T>,
+    ) {
+        let scope = profiler.begin_scope(encoder, pass.name);
+        match &pass.kind {
+            PassKind::Compute(dispatch) => {
+                let mut cpass = encoder
+                    .begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some(pass.name) });
+                dispatch(context, &mut cpass);
+            }
+            PassKind::Render { target, load_op, draw } => {
+                let view =
+                    if *target == FRAME { frame_view } else { &self.texture(target).tex_view };
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some(pass.name),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations { load: *load_op, store: true },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                draw(context, &mut rpass);
+            }
+        }
+        profiler.end_scope(encoder, scope);
+    }
+}
+
+impl<T> Drop for RenderGraph<T> {
+    fn drop(&mut self) {
+        self.recycle();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Context {
+        skip_first: bool,
+    }
+
+    fn new_graph(resources: &[&'static str]) -> RenderGraph<Context> {
+        let mut graph = RenderGraph::new();
+        graph.import(resources);
+        graph
+    }
+
+    fn names(graph: &RenderGraph<Context>, order: &[usize]) -> Vec<&'static str> {
+        order.iter().map(|i| graph.passes[*i].name).collect()
+    }
+
+    #[test]
+    fn writers_run_before_readers() {
+        let mut graph = new_graph(&["field", "particles"]);
+        graph.add_compute_pass("draw", &["field", "particles"], &[], |_, _| {});
+        graph.add_compute_pass("advect", &["field"], &["particles"], |_, _| {});
+        graph.add_compute_pass("solve", &[], &["field"], |_, _| {});
+        graph.add_compute_pass("forces", &[], &["field"], |_, _| {});
+        let order = graph.sort().unwrap();
+        assert_eq!(names(&graph, &order), vec!["solve", "forces", "advect", "draw"]);
+    }
+
+    #[test]
+    fn independent_passes_keep_declaration_order() {
+        let mut graph = new_graph(&["a", "b", "c"]);
+        graph.add_compute_pass("c", &[], &["c"], |_, _| {});
+        graph.add_compute_pass("a", &["a"], &["a"], |_, _| {});
+        graph.add_compute_pass("b", &["b"], &[], |_, _| {});
+        let order = graph.sort().unwrap();
+        assert_eq!(order, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn cycle_is_reported() {
+        let mut graph = new_graph(&["x", "y"]);
+        graph.add_compute_pass("first", &["x"], &["y"], |_, _| {});
+        graph.add_compute_pass("second", &["y"], &["x"], |_, _| {});
+        graph.add_compute_pass("free", &[], &[], |_, _| {});
+        let e = graph.sort().unwrap_err();
+        assert!(e.contains("cycle") && e.contains("first") && e.contains("second"), "{}", e);
+        assert!(!e.contains("free"), "{}", e);
+    }
+
+    #[test]
+    fn unknown_resource_is_reported() {
+        let mut graph = new_graph(&["field"]);
+        graph.add_compute_pass("solve", &["field"], &["velocity"], |_, _| {});
+        let e = graph.validate().unwrap_err();
+        assert_eq!(e, "pass `solve` uses unknown resource `velocity`");
+
+        let mut graph = new_graph(&["field"]);
+        graph.add_render_pass("present", &["field"], "field", wgpu::LoadOp::Load, |_, _| {});
+        assert!(graph.validate().unwrap_err().contains("not a graph texture"));
+
+        let mut graph = new_graph(&["field"]);
+        graph.add_render_pass("present", &["field"], FRAME, wgpu::LoadOp::Load, |_, _| {});
+        assert!(graph.validate().is_ok());
+    }
+
+    #[test]
+    fn adjacent_repeat_passes_form_a_group() {
+        let mut graph = new_graph(&["lattice", "curl", "frame_data", "trail"]);
+        graph
+            .add_compute_pass("collide", &[], &["lattice"], |_, _| {})
+            .repeat(|_| 3)
+            .run_if(|context: &Context| !context.skip_first);
+        graph.add_compute_pass("curl", &["lattice"], &["curl"], |_, _| {}).repeat(|_| 5);
+        graph.add_compute_pass("present", &["curl"], &["frame_data"], |_, _| {});
+        graph.add_compute_pass("decay", &[], &["trail"], |_, _| {}).repeat(|_| 2);
+        graph.order = graph.sort().unwrap();
+
+        let context = Context::default();
+        let (len, group) = graph.repeat_group(0, &context);
+        assert_eq!(len, 2);
+        let group_names: Vec<&str> = group.iter().map(|pass| pass.name).collect();
+        assert_eq!(group_names, vec!["collide", "curl"]);
+        assert_eq!(group[0].repeat_count(&context), 3);
+
+        // 第一个 pass 被跳过时，次数取下一个启用的 pass
+        let context = Context { skip_first: true };
+        let (len, group) = graph.repeat_group(0, &context);
+        assert_eq!(len, 2);
+        assert_eq!(group.len(), 1);
+        assert_eq!(group[0].repeat_count(&context), 5);
+
+        // 不相邻的 repeat pass 单独成组
+        assert_eq!(graph.repeat_group(2, &context).0, 0);
+        assert_eq!(graph.repeat_group(3, &context).0, 1);
+    }
+}
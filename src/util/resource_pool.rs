@@ -0,0 +1,381 @@
+// 跨 player 复用的 GPU 资源
+//
+// CombinateCanvas 切换场景或 resize 时会重建整个 player：
+//   - shader 按名称、插入的代码段与宏缓存，同样的 shader 不再重新预处理与编译
+//   - shader 的反射结果按同样的 key 缓存，不再重复用 naga 解析
+//   - compute 与全屏绘制节点的 pipeline 按 shader 与 bind group layout 缓存
+//   - ViewNode 的 pipeline 还按顶点布局与图元状态区分
+//   - 纹理与 buffer 按描述回收，下一个 player 申请同样的描述时直接取出并清空
+//     帧图里的纹理与节点里按格子数分配的资源用 PooledTexture / PooledBuffer 持有
+// 同一时间只支持一个 device，创建了新的 device 之后需要先调用 clear，各个 canvas 的构造函数里已经调用
+// wgpu 的对象在 wasm 上不能跨线程，所以池是线程局部的
+use super::{AnyTexture, BufferObj, ShaderReflection};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::rc::Rc;
+use wgpu::ShaderModule;
+use zerocopy::AsBytes;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct ShaderKey {
+    name: String,
+    code_segment: Option<String>,
+    defines: Vec<(String, String)>,
+}
+
+impl ShaderKey {
+    pub fn new(name: &str, code_segment: Option<&str>, defines: &[(&str, &str)]) -> Self {
+        ShaderKey {
+            name: name.to_string(),
+            code_segment: code_segment.map(|code| code.to_string()),
+            defines: defines.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    // 缓存中的 shader 的地址，shader 移出缓存时对应的 pipeline 一起移除，地址不会被复用
+    module: usize,
+    // 为空时 layout 由 shader 推导
+    layouts: Vec<Vec<wgpu::BindGroupLayoutEntry>>,
+    push_constants: Vec<wgpu::PushConstantRange>,
+    // 渲染 pipeline 的 (格式, 混合, 是否有深度)，compute pipeline 为 None
+    target: Option<(wgpu::TextureFormat, Option<wgpu::BlendState>, bool)>,
+    // 带顶点 buffer 的渲染 pipeline 的顶点布局、图元类型与剔除面
+    vertex: Option<(Vec<VertexLayout>, wgpu::PrimitiveTopology, Option<wgpu::Face>)>,
+}
+
+// (array_stride, step_mode, attributes)
+pub type VertexLayout = (wgpu::BufferAddress, wgpu::VertexStepMode, Vec<wgpu::VertexAttribute>);
+
+impl PipelineKey {
+    pub fn compute(
+        module: &ShaderModule, layouts: Vec<Vec<wgpu::BindGroupLayoutEntry>>,
+        push_constants: Vec<wgpu::PushConstantRange>,
+    ) -> Self {
+        PipelineKey {
+            module: module as *const _ as usize,
+            layouts,
+            push_constants,
+            target: None,
+            vertex: None,
+        }
+    }
+
+    pub fn render(
+        module: &ShaderModule, layouts: Vec<Vec<wgpu::BindGroupLayoutEntry>>,
+        format: wgpu::TextureFormat, blend_state: Option<wgpu::BlendState>,
+        use_depth_stencil: bool,
+    ) -> Self {
+        PipelineKey {
+            module: module as *const _ as usize,
+            layouts,
+            push_constants: vec![],
+            target: Some((format, blend_state, use_depth_stencil)),
+            vertex: None,
+        }
+    }
+
+    pub fn with_vertex(
+        mut self, layouts: Vec<VertexLayout>, topology: wgpu::PrimitiveTopology,
+        cull_mode: Option<wgpu::Face>,
+    ) -> Self {
+        self.vertex = Some((layouts, topology, cull_mode));
+        self
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TextureKey {
+    pub format: wgpu::TextureFormat,
+    pub size: wgpu::Extent3d,
+    pub usage: wgpu::TextureUsages,
+    pub view_dimension: wgpu::TextureViewDimension,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BufferKey {
+    pub size: wgpu::BufferAddress,
+    pub usage: wgpu::BufferUsages,
+}
+
+impl BufferKey {
+    pub fn of(buffer: &BufferObj) -> Self {
+        BufferKey { size: buffer.size, usage: buffer.usage }
+    }
+}
+
+// 回收的资源经过一次 trim 还没有被取走时才释放
+struct Recycled<K, T> {
+    key: K,
+    item: T,
+    is_stale: bool,
+}
+
+#[derive(Default)]
+struct ResourcePool {
+    shaders: HashMap<ShaderKey, Rc<ShaderModule>>,
+    reflections: HashMap<ShaderKey, Rc<ShaderReflection>>,
+    compute_pipelines: HashMap<PipelineKey, Rc<wgpu::ComputePipeline>>,
+    render_pipelines: HashMap<PipelineKey, Rc<wgpu::RenderPipeline>>,
+    textures: Vec<Recycled<TextureKey, AnyTexture>>,
+    buffers: Vec<Recycled<BufferKey, BufferObj>>,
+}
+
+impl ResourcePool {
+    fn is_pooled_shader(&self, module: usize) -> bool {
+        self.shaders.values().any(|shader| Rc::as_ptr(shader) as usize == module)
+    }
+
+    fn remove_pipelines(&mut self, module: &Rc<ShaderModule>) {
+        let module = Rc::as_ptr(module) as usize;
+        self.compute_pipelines.retain(|key, _| key.module != module);
+        self.render_pipelines.retain(|key, _| key.module != module);
+    }
+}
+
+thread_local! {
+    static POOL: RefCell<ResourcePool> = RefCell::new(ResourcePool::default());
+}
+
+// create 在池外调用，里面可以再访问池
+pub fn shader(key: ShaderKey, create: impl FnOnce() -> ShaderModule) -> Rc<ShaderModule> {
+    if let Some(module) = POOL.with(|pool| pool.borrow().shaders.get(&key).cloned()) {
+        return module;
+    }
+    let module = Rc::new(create());
+    POOL.with(|pool| pool.borrow_mut().shaders.insert(key, module.clone()));
+    module
+}
+
+pub fn reflection(
+    key: ShaderKey, create: impl FnOnce() -> ShaderReflection,
+) -> Rc<ShaderReflection> {
+    if let Some(reflection) = POOL.with(|pool| pool.borrow().reflections.get(&key).cloned()) {
+        return reflection;
+    }
+    let reflection = Rc::new(create());
+    POOL.with(|pool| pool.borrow_mut().reflections.insert(key, reflection.clone()));
+    reflection
+}
+
+// 热重载后替换缓存中的 shader，之后重建的 player 使用新的 shader
+pub fn replace_shader(key: ShaderKey, module: ShaderModule) -> Rc<ShaderModule> {
+    let module = Rc::new(module);
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        if let Some(old) = pool.shaders.insert(key, module.clone()) {
+            pool.remove_pipelines(&old);
+        }
+    });
+    module
+}
+
+// 源文件有变化的 shader 的所有变体都移出缓存，没有被热重载的变体在下次使用时重新编译
+pub fn evict_shaders(changed: &[String]) {
+    if changed.is_empty() {
+        return;
+    }
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        pool.reflections.retain(|key, _| !changed.contains(&key.name));
+        let evicted: Vec<ShaderKey> =
+            pool.shaders.keys().filter(|key| changed.contains(&key.name)).cloned().collect();
+        for key in evicted {
+            if let Some(old) = pool.shaders.remove(&key) {
+                pool.remove_pipelines(&old);
+            }
+        }
+    });
+}
+
+// shader 不是来自缓存时直接创建，不缓存
+pub fn compute_pipeline(
+    key: PipelineKey, create: impl FnOnce() -> wgpu::ComputePipeline,
+) -> Rc<wgpu::ComputePipeline> {
+    let (cached, is_pooled) = POOL.with(|pool| {
+        let pool = pool.borrow();
+        (pool.compute_pipelines.get(&key).cloned(), pool.is_pooled_shader(key.module))
+    });
+    if let Some(pipeline) = cached {
+        return pipeline;
+    }
+    let pipeline = Rc::new(create());
+    if is_pooled {
+        POOL.with(|pool| pool.borrow_mut().compute_pipelines.insert(key, pipeline.clone()));
+    }
+    pipeline
+}
+
+pub fn render_pipeline(
+    key: PipelineKey, create: impl FnOnce() -> wgpu::RenderPipeline,
+) -> Rc<wgpu::RenderPipeline> {
+    let (cached, is_pooled) = POOL.with(|pool| {
+        let pool = pool.borrow();
+        (pool.render_pipelines.get(&key).cloned(), pool.is_pooled_shader(key.module))
+    });
+    if let Some(pipeline) = cached {
+        return pipeline;
+    }
+    let pipeline = Rc::new(create());
+    if is_pooled {
+        POOL.with(|pool| pool.borrow_mut().render_pipelines.insert(key, pipeline.clone()));
+    }
+    pipeline
+}
+
+pub fn take_texture(key: &TextureKey) -> Option<AnyTexture> {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let index = pool.textures.iter().position(|item| item.key == *key)?;
+        Some(pool.textures.swap_remove(index).item)
+    })
+}
+
+pub fn recycle_texture(key: TextureKey, texture: AnyTexture) {
+    POOL.with(|pool| {
+        pool.borrow_mut().textures.push(Recycled { key, item: texture, is_stale: false })
+    });
+}
+
+pub fn take_buffer(key: &BufferKey) -> Option<BufferObj> {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        let index = pool.buffers.iter().position(|item| item.key == *key)?;
+        Some(pool.buffers.swap_remove(index).item)
+    })
+}
+
+pub fn recycle_buffer(buffer: BufferObj) {
+    let key = BufferKey::of(&buffer);
+    POOL.with(|pool| {
+        pool.borrow_mut().buffers.push(Recycled { key, item: buffer, is_stale: false })
+    });
+}
+
+// 池里取出的纹理保留着上一个 player 的内容，清空需要 COPY_DST
+pub struct PooledTexture {
+    key: TextureKey,
+    texture: Option<AnyTexture>,
+}
+
+impl PooledTexture {
+    pub fn new(
+        device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat,
+        size: wgpu::Extent3d, usage: wgpu::TextureUsages, label: Option<&'static str>,
+    ) -> Self {
+        let key = TextureKey {
+            format,
+            size,
+            usage: usage | wgpu::TextureUsages::COPY_DST,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        };
+        let texture = match take_texture(&key) {
+            Some(texture) => {
+                texture.clear(queue);
+                texture
+            }
+            None => super::load_texture::empty(
+                device,
+                format,
+                size,
+                Some(key.view_dimension),
+                Some(key.usage),
+                label,
+            ),
+        };
+        PooledTexture { key, texture: Some(texture) }
+    }
+
+    // 还给池，之后不能再使用
+    pub fn recycle(&mut self) {
+        if let Some(texture) = self.texture.take() {
+            recycle_texture(self.key.clone(), texture);
+        }
+    }
+}
+
+impl Deref for PooledTexture {
+    type Target = AnyTexture;
+    fn deref(&self) -> &AnyTexture {
+        self.texture.as_ref().expect("pooled texture is already recycled")
+    }
+}
+
+pub struct PooledBuffer {
+    buffer: Option<BufferObj>,
+}
+
+impl PooledBuffer {
+    // 与 BufferObj::create_empty_storage_buffer 相同，取出的 buffer 会被清零
+    pub fn empty_storage(
+        device: &wgpu::Device, queue: &wgpu::Queue, size: wgpu::BufferAddress,
+        label: Option<&'static str>,
+    ) -> Self {
+        let key = BufferKey { size, usage: BufferObj::empty_storage_usage(false) };
+        let buffer = match take_buffer(&key) {
+            Some(buffer) => {
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("clear pooled buffer encoder"),
+                });
+                encoder.clear_buffer(&buffer.buffer, 0, None);
+                queue.submit(Some(encoder.finish()));
+                buffer
+            }
+            None => BufferObj::create_empty_storage_buffer(device, size, false, label),
+        };
+        PooledBuffer { buffer: Some(buffer) }
+    }
+
+    // 与 BufferObj::create_storage_buffer 相同，取出的 buffer 写入 slice
+    pub fn storage<T>(
+        device: &wgpu::Device, queue: &wgpu::Queue, slice: &[T], label: Option<&'static str>,
+    ) -> Self
+    where
+        T: 'static + AsBytes + Copy,
+    {
+        let key = BufferKey {
+            size: std::mem::size_of_val(slice) as wgpu::BufferAddress,
+            usage: BufferObj::storage_usage(),
+        };
+        let buffer = match take_buffer(&key) {
+            Some(buffer) => {
+                queue.write_buffer(&buffer.buffer, 0, slice.as_bytes());
+                buffer
+            }
+            None => BufferObj::create_storage_buffer(device, slice, label),
+        };
+        PooledBuffer { buffer: Some(buffer) }
+    }
+
+    pub fn recycle(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            recycle_buffer(buffer);
+        }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = BufferObj;
+    fn deref(&self) -> &BufferObj {
+        self.buffer.as_ref().expect("pooled buffer is already recycled")
+    }
+}
+
+// 重建 player 之后调用：释放上一次 trim 之后一直没有被取走的纹理与 buffer
+// 只保留一代，来回切换两种尺寸时仍能复用
+pub fn trim() {
+    POOL.with(|pool| {
+        let mut pool = pool.borrow_mut();
+        pool.textures.retain(|item| !item.is_stale);
+        pool.textures.iter_mut().for_each(|item| item.is_stale = true);
+        pool.buffers.retain(|item| !item.is_stale);
+        pool.buffers.iter_mut().for_each(|item| item.is_stale = true);
+    });
+}
+
+pub fn clear() {
+    POOL.with(|pool| *pool.borrow_mut() = ResourcePool::default());
+}
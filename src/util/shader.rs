@@ -1,13 +1,14 @@
-use std::{borrow::Cow, fs::read_to_string, path::PathBuf};
+use super::preprocessor::{PreprocessError, PreprocessedShader, ShaderPreprocessor, DEFAULT_SLOT};
+use super::resource_pool::{self, ShaderKey};
+use super::shader_registry::embedded_shader;
+use super::shader_watcher;
+use std::{borrow::Cow, path::PathBuf, rc::Rc};
 use wgpu::{ShaderModule, ShaderModuleDescriptor, ShaderSource};
 
-const SHADER_IMPORT: &str = "#include ";
-const SHADER_SEGMENT: &str = "#insert_code_segment";
-
 #[allow(dead_code)]
 pub fn create_shader_module(
     device: &wgpu::Device, shader_name: &'static str, label: Option<&str>,
-) -> ShaderModule {
+) -> Rc<ShaderModule> {
     insert_code_then_create(device, shader_name, None, label)
 }
 
@@ -15,113 +16,147 @@ pub fn create_shader_module(
 pub fn insert_code_then_create(
     device: &wgpu::Device, shader_name: &'static str, code_segment: Option<&str>,
     label: Option<&str>,
+) -> Rc<ShaderModule> {
+    create_shader_variant(device, shader_name, code_segment, &[], label)
+}
+
+// defines: 预处理的宏，用于同一个 shader 的不同变体
+// 同样的名称、代码段与宏只编译一次，重建 player 时直接取 resource_pool 中的缓存
+#[allow(dead_code)]
+pub fn create_shader_variant(
+    device: &wgpu::Device, shader_name: &'static str, code_segment: Option<&str>,
+    defines: &[(&str, &str)], label: Option<&str>,
+) -> Rc<ShaderModule> {
+    let key = ShaderKey::new(shader_name, code_segment, defines);
+    resource_pool::shader(key, || compile_shader(device, shader_name, code_segment, defines, label))
+}
+
+fn compile_shader(
+    device: &wgpu::Device, shader_name: &'static str, code_segment: Option<&str>,
+    defines: &[(&str, &str)], label: Option<&str>,
 ) -> ShaderModule {
     // @Kvark 20210402 ：Please don't use EXPERIMENTAL_TRANSLATION on Metal for this shader for now.
     // let flags = ShaderFlags::VALIDATION | ShaderFlags::EXPERIMENTAL_TRANSLATION;
     // let flags = ShaderFlags::VALIDATION;
     // let flags = ShaderFlags::default();
 
-    // env!("CARGO_MANIFEST_DIR") 是编译时执行的，得到的是当前所编辑的库的所在路径，而不是项目的路径
-    // std::env::var("CARGO_MANIFEST_DIR") 在 xcode debug 时不存在
-    // std::env::current_dir() 在 xcode debug 时只能获得相对路径： “/”
-    let base_dir = app_surface::fs::application_root_dir();
-    let (fold, shader_name) = if cfg!(any(target_os = "ios", target_arch = "wasm32")) {
-        ("shader-preprocessed-wgsl", shader_name.replace("/", "_"))
-    } else {
-        ("shader-wgsl", shader_name.to_string())
-    };
-    let code = request_shader_code(&base_dir, fold, &shader_name);
-
-    let shader_source = if cfg!(any(target_os = "ios", target_arch = "wasm32")) {
-        code.to_string()
-    } else {
-        let mut shader_source = String::new();
-        parse_shader_source(&code, &mut shader_source, &base_dir);
-        shader_source
-    };
-
-    let final_source = if let Some(segment) = code_segment {
-        let mut output = String::new();
-        for line in shader_source.lines() {
-            if line.contains(SHADER_SEGMENT) {
-                output.push_str(segment);
-            } else {
-                output.push_str(line);
-            }
-            output.push_str("\n ");
-        }
-        output
-    } else {
-        shader_source
+    let shader = match preprocess(shader_name, code_segment, defines) {
+        Ok(shader) => shader,
+        Err(e) => panic!("shader preprocess error: {}", e),
     };
+    if let Some(dir) = shader_override_dir() {
+        shader_watcher::watch(&dir, shader_name, shader.files());
+    }
 
-    device.create_shader_module(ShaderModuleDescriptor {
-        label,
-        source: ShaderSource::Wgsl(Cow::Borrowed(&final_source)),
-    })
+    let module = try_create(device, || {
+        device.create_shader_module(ShaderModuleDescriptor {
+            label,
+            source: ShaderSource::Wgsl(Cow::Borrowed(&shader.code)),
+        })
+    });
+    // 错误信息中的行号换成源文件的位置
+    match module {
+        Ok(module) => module,
+        Err(error) => panic!("{}: {}", shader_name, shader.map_error_message(&error)),
+    }
 }
 
-#[cfg(target_arch = "wasm32")]
-fn request_shader_code(base_dir: &str, fold: &str, shader_name: &str) -> String {
-    // 主线程中同步的 XMLHttpRequest 已不赞成使用(2021/05/07)
-    let mut request = web_sys::XmlHttpRequest::new().unwrap();
-    request.set_response_type(web_sys::XmlHttpRequestResponseType::None);
-    let url = base_dir.to_string() + "/" + &shader_name + ".wgsl";
-    request.open_with_async("get", &url, false);
-    request.send();
-    request.response_text().unwrap().unwrap()
+// 热重载：shader_name 在 changed 中时重新创建 shader
+// 出错时打印错误并返回 None，调用方继续使用旧的 pipeline
+#[allow(dead_code)]
+pub fn reload_shader(
+    device: &wgpu::Device, changed: &[String], shader_name: &str,
+) -> Option<Rc<ShaderModule>> {
+    reload_shader_variant(device, changed, shader_name, None, &[])
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-fn request_shader_code(base_dir: &str, fold: &str, shader_name: &str) -> String {
-    let path = PathBuf::from(base_dir).join(fold).join(format!("{shader_name}.wgsl"));
-    let code = match read_to_string(&path) {
-        Ok(code) => code,
+#[allow(dead_code)]
+pub fn reload_shader_variant(
+    device: &wgpu::Device, changed: &[String], shader_name: &str, code_segment: Option<&str>,
+    defines: &[(&str, &str)],
+) -> Option<Rc<ShaderModule>> {
+    if !changed.iter().any(|name| name == shader_name) {
+        return None;
+    }
+    let shader = match preprocess(shader_name, code_segment, defines) {
+        Ok(shader) => shader,
         Err(e) => {
-            panic!("Unable to read {:?}: {:?}", path, e)
+            crate::console_log!("shader reload error: {}", e);
+            return None;
         }
     };
-    code
+    if let Some(dir) = shader_override_dir() {
+        shader_watcher::watch(&dir, shader_name, shader.files());
+    }
+    let module = try_create(device, || {
+        device.create_shader_module(ShaderModuleDescriptor {
+            label: Some(shader_name),
+            source: ShaderSource::Wgsl(Cow::Borrowed(&shader.code)),
+        })
+    });
+    let module = match module {
+        Ok(module) => module,
+        Err(error) => {
+            crate::console_log!(
+                "shader reload error: {}: {}",
+                shader_name,
+                shader.map_error_message(&error)
+            );
+            return None;
+        }
+    };
+    crate::console_log!("shader reloaded: {}", shader_name);
+    let key = ShaderKey::new(shader_name, code_segment, defines);
+    Some(resource_pool::replace_shader(key, module))
 }
 
-fn parse_shader_source(source: &str, output: &mut String, base_path: &str) {
-    for line in source.lines() {
-        if line.starts_with(SHADER_IMPORT) {
-            let imports = line[SHADER_IMPORT.len()..].split(',');
-            // For each import, get the source, and recurse.
-            for import in imports {
-                if let Some(include) = get_shader_funcs(import, base_path) {
-                    parse_shader_source(&include, output, base_path);
-                } else {
-                    println!("shader parse error -------");
-                    println!("can't find shader functions: {}", import);
-                    println!("--------------------------");
-                }
-            }
-        } else {
-            output.push_str(line);
-            output.push_str("\n ");
-            // 移除注释
-            // let need_delete = match line.find("//") {
-            //     Some(_) => {
-            //         let segments: Vec<&str> = line.split("//").collect();
-            //         segments.len() > 1 && segments.first().unwrap().trim().is_empty()
-            //     }
-            //     None => false,
-            // };
-            // if !need_delete {
-            //     output.push_str(line);
-            //     output.push_str("\n");
-            // }
+// 在 validation error scope 中创建 GPU 对象，出错时返回错误信息
+// wasm 上 pop_error_scope 是异步的，直接返回创建的对象
+pub fn try_create<T>(device: &wgpu::Device, create: impl FnOnce() -> T) -> Result<T, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let object = create();
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(error) => Err(error.to_string()),
+            None => Ok(object),
         }
     }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = device;
+        Ok(create())
+    }
 }
 
-fn get_shader_funcs(key: &str, base_path: &str) -> Option<String> {
-    let path = PathBuf::from(base_path).join("shader-wgsl").join(key.replace('"', ""));
-    let shader = match read_to_string(&path) {
-        Ok(code) => code,
-        Err(e) => panic!("Unable to read {:?}: {:?}", path, e),
+fn configure<'a>(
+    preprocessor: ShaderPreprocessor<'a>, code_segment: Option<&str>, defines: &[(&str, &str)],
+) -> ShaderPreprocessor<'a> {
+    let mut preprocessor = match code_segment {
+        Some(code) => preprocessor.slot(DEFAULT_SLOT, code),
+        None => preprocessor,
     };
-    Some(shader)
+    for (name, value) in defines {
+        preprocessor = preprocessor.define(name, value);
+    }
+    preprocessor
+}
+
+// 默认使用编译进二进制的 shader 源文件
+// 桌面端设置了 NATURE_SHADER_DIR 时读取该目录下的源码，修改 shader 后不需要重新编译
+pub fn preprocess(
+    shader_name: &str, code_segment: Option<&str>, defines: &[(&str, &str)],
+) -> Result<PreprocessedShader, PreprocessError> {
+    let preprocessor = match shader_override_dir() {
+        Some(dir) => ShaderPreprocessor::from_dir(dir),
+        None => ShaderPreprocessor::new(|path| embedded_shader(path).map(str::to_string)),
+    };
+    configure(preprocessor, code_segment, defines).process(shader_name)
+}
+
+pub fn shader_override_dir() -> Option<PathBuf> {
+    if cfg!(any(target_os = "ios", target_os = "android", target_arch = "wasm32")) {
+        return None;
+    }
+    std::env::var_os("NATURE_SHADER_DIR").map(PathBuf::from)
 }
@@ -0,0 +1,344 @@
+// 用 naga 解析 shader 中声明的资源绑定
+//
+// 节点按 shader 里的变量名绑定资源，bind group layout 由这里的反射结果生成，
+// 调整 WGSL 中 @binding 的顺序不需要改 Rust 代码，缺少或类型不匹配的资源会在创建节点时报错
+use super::resource_pool::{self, ShaderKey};
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+use naga::{AddressSpace, ImageClass, ImageDimension, ScalarKind, ShaderStage, StorageAccess};
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BindingKind {
+    UniformBuffer,
+    StorageBuffer { read_only: bool },
+    // Float 类型的 filterable 取决于绑定纹理的格式，创建 layout 时再确定
+    Texture { sample_type: wgpu::TextureSampleType, multisampled: bool },
+    // 格式不在下面的对应表中时为 None
+    StorageTexture { access: wgpu::StorageTextureAccess, format: Option<wgpu::TextureFormat> },
+    Sampler { comparison: bool },
+}
+
+impl BindingKind {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            BindingKind::UniformBuffer => "uniform buffer",
+            BindingKind::StorageBuffer { .. } => "storage buffer",
+            BindingKind::Texture { .. } => "texture",
+            BindingKind::StorageTexture { .. } => "storage texture",
+            BindingKind::Sampler { .. } => "sampler",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReflectedBinding {
+    pub name: String,
+    pub group: u32,
+    pub binding: u32,
+    pub kind: BindingKind,
+    // 纹理的维度，buffer 与 sampler 为 None
+    pub view_dimension: Option<wgpu::TextureViewDimension>,
+    // buffer 至少需要的字节数，运行时数组按一个元素计算
+    pub min_size: u64,
+    // uniform struct 的成员：(名称, 偏移, 大小)
+    pub members: Vec<(String, u64, u64)>,
+    // 使用了此变量的入口函数所在的阶段
+    pub visibility: wgpu::ShaderStages,
+}
+
+pub struct ShaderReflection {
+    pub shader_name: String,
+    bindings: Vec<ReflectedBinding>,
+}
+
+#[allow(dead_code)]
+impl ShaderReflection {
+    // 与 create_shader_variant 使用同一份预处理后的源码，结果缓存在 resource_pool 中
+    pub fn new(
+        shader_name: &str, code_segment: Option<&str>, defines: &[(&str, &str)],
+    ) -> Rc<Self> {
+        let key = ShaderKey::new(shader_name, code_segment, defines);
+        resource_pool::reflection(key, || {
+            let code = match super::shader::preprocess(shader_name, code_segment, defines) {
+                Ok(shader) => shader.code,
+                Err(e) => panic!("shader preprocess error: {}", e),
+            };
+            match ShaderReflection::from_wgsl(shader_name, &code) {
+                Ok(reflection) => reflection,
+                Err(e) => panic!("{}: {}", shader_name, e),
+            }
+        })
+    }
+
+    pub fn from_wgsl(shader_name: &str, code: &str) -> Result<Self, String> {
+        let module = naga::front::wgsl::parse_str(code).map_err(|e| e.emit_to_string(code))?;
+        let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+            .validate(&module)
+            .map_err(|e| e.to_string())?;
+
+        let mut bindings = vec![];
+        for (handle, var) in module.global_variables.iter() {
+            let res = match var.binding.as_ref() {
+                Some(res) => res,
+                None => continue,
+            };
+            let name = match var.name.as_ref() {
+                Some(name) => name.clone(),
+                None => format!("@group({}) @binding({})", res.group, res.binding),
+            };
+            let mut visibility = wgpu::ShaderStages::NONE;
+            for (index, entry) in module.entry_points.iter().enumerate() {
+                if !info.get_entry_point(index)[handle].is_empty() {
+                    visibility |= match entry.stage {
+                        ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
+                        ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+                        ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+                    };
+                }
+            }
+            let inner = &module.types[var.ty].inner;
+            let mut view_dimension = None;
+            let kind = match (var.space, inner) {
+                (AddressSpace::Uniform, _) => BindingKind::UniformBuffer,
+                (AddressSpace::Storage { access }, _) => {
+                    BindingKind::StorageBuffer { read_only: !access.contains(StorageAccess::STORE) }
+                }
+                (AddressSpace::Handle, naga::TypeInner::Image { dim, arrayed, class }) => {
+                    view_dimension = Some(map_view_dimension(*dim, *arrayed));
+                    match class {
+                        ImageClass::Sampled { kind, multi } => BindingKind::Texture {
+                            sample_type: match kind {
+                                ScalarKind::Sint => wgpu::TextureSampleType::Sint,
+                                ScalarKind::Uint => wgpu::TextureSampleType::Uint,
+                                _ => wgpu::TextureSampleType::Float { filterable: true },
+                            },
+                            multisampled: *multi,
+                        },
+                        ImageClass::Depth { multi } => BindingKind::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            multisampled: *multi,
+                        },
+                        ImageClass::Storage { format, access } => BindingKind::StorageTexture {
+                            access: if !access.contains(StorageAccess::LOAD) {
+                                wgpu::StorageTextureAccess::WriteOnly
+                            } else if !access.contains(StorageAccess::STORE) {
+                                wgpu::StorageTextureAccess::ReadOnly
+                            } else {
+                                wgpu::StorageTextureAccess::ReadWrite
+                            },
+                            format: map_storage_format(*format),
+                        },
+                    }
+                }
+                (AddressSpace::Handle, naga::TypeInner::Sampler { comparison }) => {
+                    BindingKind::Sampler { comparison: *comparison }
+                }
+                _ => return Err(format!("unsupported binding `{}`", name)),
+            };
+            let min_size = match kind {
+                BindingKind::UniformBuffer | BindingKind::StorageBuffer { .. } => {
+                    inner.size(&module.constants) as u64
+                }
+                _ => 0,
+            };
+            let mut members = vec![];
+            if let (BindingKind::UniformBuffer, naga::TypeInner::Struct { members: items, .. }) =
+                (kind, inner)
+            {
+                for member in items.iter() {
+                    members.push((
+                        member.name.clone().unwrap_or_default(),
+                        member.offset as u64,
+                        module.types[member.ty].inner.size(&module.constants) as u64,
+                    ));
+                }
+            }
+            bindings.push(ReflectedBinding {
+                name,
+                group: res.group,
+                binding: res.binding,
+                kind,
+                view_dimension,
+                min_size,
+                members,
+                visibility,
+            });
+        }
+        bindings.sort_by_key(|item| (item.group, item.binding));
+
+        Ok(ShaderReflection { shader_name: shader_name.to_string(), bindings })
+    }
+
+    // 按 @binding 排序
+    pub fn bindings(&self, group: u32) -> Vec<&ReflectedBinding> {
+        self.bindings.iter().filter(|item| item.group == group).collect()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ReflectedBinding> {
+        self.bindings.iter().find(|item| item.name == name)
+    }
+}
+
+fn map_view_dimension(dim: ImageDimension, arrayed: bool) -> wgpu::TextureViewDimension {
+    match (dim, arrayed) {
+        (ImageDimension::D1, _) => wgpu::TextureViewDimension::D1,
+        (ImageDimension::D2, false) => wgpu::TextureViewDimension::D2,
+        (ImageDimension::D2, true) => wgpu::TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => wgpu::TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => wgpu::TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => wgpu::TextureViewDimension::CubeArray,
+    }
+}
+
+// 只列出了项目中用到的存储纹理格式
+fn map_storage_format(format: naga::StorageFormat) -> Option<wgpu::TextureFormat> {
+    use naga::StorageFormat as Sf;
+    use wgpu::TextureFormat as Tf;
+    Some(match format {
+        Sf::R32Float => Tf::R32Float,
+        Sf::R32Uint => Tf::R32Uint,
+        Sf::R32Sint => Tf::R32Sint,
+        Sf::R16Float => Tf::R16Float,
+        Sf::Rg32Float => Tf::Rg32Float,
+        Sf::Rgba8Unorm => Tf::Rgba8Unorm,
+        Sf::Rgba16Float => Tf::Rgba16Float,
+        Sf::Rgba32Float => Tf::Rgba32Float,
+        Sf::Rgba32Uint => Tf::Rgba32Uint,
+        Sf::Rgba32Sint => Tf::Rgba32Sint,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHADER: &str = r#"
+struct Params {
+    scale: f32,
+    offset: vec3<f32>,
+    size: vec2<f32>,
+};
+
+struct FloatBuffer {
+    data: array<f32>,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> input: FloatBuffer;
+@group(0) @binding(2) var<storage, read_write> output: FloatBuffer;
+@group(0) @binding(3) var tex: texture_2d<f32>;
+@group(0) @binding(4) var int_tex: texture_2d_array<i32>;
+@group(0) @binding(5) var depth_tex: texture_depth_2d;
+@group(0) @binding(6) var samp: sampler;
+@group(0) @binding(7) var shadow_samp: sampler_comparison;
+@group(1) @binding(1) var unused_tex: texture_3d<u32>;
+@group(1) @binding(0) var out_tex: texture_storage_2d<rgba8unorm, write>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(params.offset * f32(index), 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return textureSample(tex, samp, params.size);
+}
+
+@compute @workgroup_size(1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    output.data[id.x] = input.data[id.x] * params.scale;
+    textureStore(out_tex, vec2<i32>(id.xy), vec4<f32>(1.0));
+}
+"#;
+
+    fn reflect() -> ShaderReflection {
+        ShaderReflection::from_wgsl("test", SHADER).unwrap()
+    }
+
+    #[test]
+    fn maps_binding_kinds() {
+        let reflection = reflect();
+        let kind = |name: &str| reflection.find(name).unwrap().kind;
+        assert_eq!(kind("params"), BindingKind::UniformBuffer);
+        assert_eq!(kind("input"), BindingKind::StorageBuffer { read_only: true });
+        assert_eq!(kind("output"), BindingKind::StorageBuffer { read_only: false });
+        assert_eq!(
+            kind("tex"),
+            BindingKind::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                multisampled: false
+            }
+        );
+        assert_eq!(
+            kind("int_tex"),
+            BindingKind::Texture {
+                sample_type: wgpu::TextureSampleType::Sint,
+                multisampled: false
+            }
+        );
+        assert_eq!(
+            kind("depth_tex"),
+            BindingKind::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                multisampled: false
+            }
+        );
+        assert_eq!(kind("samp"), BindingKind::Sampler { comparison: false });
+        assert_eq!(kind("shadow_samp"), BindingKind::Sampler { comparison: true });
+        assert_eq!(
+            kind("out_tex"),
+            BindingKind::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: Some(wgpu::TextureFormat::Rgba8Unorm)
+            }
+        );
+    }
+
+    #[test]
+    fn maps_view_dimensions_and_sizes() {
+        let reflection = reflect();
+        let item = |name: &str| reflection.find(name).unwrap();
+        assert_eq!(item("tex").view_dimension, Some(wgpu::TextureViewDimension::D2));
+        assert_eq!(item("int_tex").view_dimension, Some(wgpu::TextureViewDimension::D2Array));
+        assert_eq!(item("unused_tex").view_dimension, Some(wgpu::TextureViewDimension::D3));
+        assert_eq!(item("samp").view_dimension, None);
+
+        // 运行时数组按一个元素计算
+        assert_eq!(item("input").min_size, 4);
+        assert_eq!(item("params").min_size, 48);
+        let members: Vec<(&str, u64, u64)> =
+            item("params").members.iter().map(|(n, o, s)| (n.as_str(), *o, *s)).collect();
+        assert_eq!(members, vec![("scale", 0, 4), ("offset", 16, 12), ("size", 32, 8)]);
+        assert!(item("output").members.is_empty());
+    }
+
+    #[test]
+    fn visibility_follows_entry_points() {
+        let reflection = reflect();
+        let visibility = |name: &str| reflection.find(name).unwrap().visibility;
+        assert_eq!(
+            visibility("params"),
+            wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE
+        );
+        assert_eq!(visibility("tex"), wgpu::ShaderStages::FRAGMENT);
+        assert_eq!(visibility("samp"), wgpu::ShaderStages::FRAGMENT);
+        assert_eq!(visibility("output"), wgpu::ShaderStages::COMPUTE);
+        assert_eq!(visibility("out_tex"), wgpu::ShaderStages::COMPUTE);
+        assert_eq!(visibility("shadow_samp"), wgpu::ShaderStages::NONE);
+    }
+
+    #[test]
+    fn bindings_are_sorted_by_group_and_binding() {
+        let reflection = reflect();
+        let names: Vec<&str> = reflection.bindings(1).iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["out_tex", "unused_tex"]);
+        assert_eq!(reflection.bindings(0).len(), 8);
+        assert!(reflection.bindings(2).is_empty());
+    }
+
+    #[test]
+    fn parse_error_is_reported() {
+        assert!(ShaderReflection::from_wgsl("broken", "fn cs_main( {").is_err());
+    }
+}
@@ -0,0 +1,2 @@
+// build.rs 生成的 embedded_shader(path)，按 shader-wgsl 下的相对路径返回编译进二进制的源文件
+include!(concat!(env!("OUT_DIR"), "/shader_registry.rs"));
@@ -0,0 +1,83 @@
+// 桌面端的 shader 热重载
+//
+// 设置了 NATURE_SHADER_DIR 时，创建 shader 会记录它展开用到的所有文件（含 include 的文件），
+// canvas 每帧调用 changed_shaders() 轮询这些文件的修改时间，
+// 返回受影响的 shader 名，由 player 的 reload_shaders 原地重建对应节点的 pipeline
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+// 轮询间隔，避免每帧都访问文件系统
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+struct ShaderWatcher {
+    dir: Option<PathBuf>,
+    // shader 名 -> 展开时用到的文件
+    shaders: HashMap<String, Vec<String>>,
+    // 文件 -> 上一次轮询时的修改时间
+    modified: HashMap<String, Option<SystemTime>>,
+    last_poll: Option<Instant>,
+}
+
+lazy_static::lazy_static! {
+    static ref WATCHER: Mutex<ShaderWatcher> = Mutex::new(ShaderWatcher {
+        dir: None,
+        shaders: HashMap::new(),
+        modified: HashMap::new(),
+        last_poll: None,
+    });
+}
+
+fn modified_time(dir: &Path, file: &str) -> Option<SystemTime> {
+    std::fs::metadata(dir.join(file)).and_then(|meta| meta.modified()).ok()
+}
+
+// 重建后 include 的文件可能有增减，每次都用最新的列表替换
+pub(crate) fn watch(dir: &Path, shader_name: &str, files: &[String]) {
+    let mut watcher = WATCHER.lock().unwrap();
+    watcher.dir = Some(dir.to_path_buf());
+    for file in files {
+        if !watcher.modified.contains_key(file) {
+            let time = modified_time(dir, file);
+            watcher.modified.insert(file.clone(), time);
+        }
+    }
+    watcher.shaders.insert(shader_name.to_string(), files.to_vec());
+}
+
+// 返回自上次轮询以来源文件有变化的 shader 名
+pub fn changed_shaders() -> Vec<String> {
+    let mut watcher = WATCHER.lock().unwrap();
+    let dir = match watcher.dir.clone() {
+        Some(dir) => dir,
+        None => return vec![],
+    };
+    if let Some(last_poll) = watcher.last_poll {
+        if last_poll.elapsed() < POLL_INTERVAL {
+            return vec![];
+        }
+    }
+    watcher.last_poll = Some(Instant::now());
+
+    let mut changed_files = vec![];
+    for (file, time) in watcher.modified.iter_mut() {
+        let new_time = modified_time(&dir, file);
+        // 编辑器保存时文件可能短暂不存在，等它重新出现再比较
+        if new_time.is_some() && new_time != *time {
+            *time = new_time;
+            changed_files.push(file.clone());
+        }
+    }
+    if changed_files.is_empty() {
+        return vec![];
+    }
+    let mut names: Vec<String> = watcher
+        .shaders
+        .iter()
+        .filter(|(_, files)| files.iter().any(|file| changed_files.contains(file)))
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
@@ -0,0 +1,273 @@
+// 带类型的 uniform buffer
+//
+// 用 uniform_layout! 列出 Rust struct 中与 WGSL struct 对应的字段（不含手动补齐的 padding）：
+//   - 创建 UniformBuffer 时按 WGSL uniform 地址空间的对齐规则检查各字段的偏移与 struct 大小
+//   - 通过 Bindings::uniform 绑定到节点时，再与 shader 反射出的 struct 成员逐个比对偏移与大小
+// 出错时的信息里附带由 Rust 字段生成的 WGSL struct，可以直接对照或粘贴到 shader 中
+use super::BufferObj;
+use zerocopy::AsBytes;
+
+// Rust 字段类型对应的 WGSL 类型
+pub trait WgslType {
+    const SIZE: u64;
+    const ALIGN: u64;
+    fn wgsl() -> String;
+}
+
+macro_rules! impl_wgsl_type {
+    ($($ty:ty => $name:expr),*) => {
+        $(
+            impl WgslType for $ty {
+                const SIZE: u64 = 4;
+                const ALIGN: u64 = 4;
+                fn wgsl() -> String {
+                    $name.to_string()
+                }
+            }
+            impl WgslType for [$ty; 2] {
+                const SIZE: u64 = 8;
+                const ALIGN: u64 = 8;
+                fn wgsl() -> String {
+                    format!("vec2<{}>", $name)
+                }
+            }
+            impl WgslType for [$ty; 3] {
+                const SIZE: u64 = 12;
+                const ALIGN: u64 = 16;
+                fn wgsl() -> String {
+                    format!("vec3<{}>", $name)
+                }
+            }
+            impl WgslType for [$ty; 4] {
+                const SIZE: u64 = 16;
+                const ALIGN: u64 = 16;
+                fn wgsl() -> String {
+                    format!("vec4<{}>", $name)
+                }
+            }
+            // uniform 中数组的步长必须是 16 的倍数，所以只支持 vec4 数组
+            // mat4x4<f32> 与 array<vec4<f32>, 4> 的布局相同
+            impl<const N: usize> WgslType for [[$ty; 4]; N] {
+                const SIZE: u64 = 16 * N as u64;
+                const ALIGN: u64 = 16;
+                fn wgsl() -> String {
+                    format!("array<vec4<{}>, {}>", $name, N)
+                }
+            }
+        )*
+    };
+}
+impl_wgsl_type!(i32 => "i32", u32 => "u32", f32 => "f32");
+
+pub struct WgslField {
+    pub name: &'static str,
+    pub ty: String,
+    // 在 Rust struct 中的偏移
+    pub offset: u64,
+    pub size: u64,
+    pub align: u64,
+}
+
+impl WgslField {
+    pub fn new<S, F: WgslType>(name: &'static str, base: *const S, field: *const F) -> Self {
+        WgslField {
+            name,
+            ty: F::wgsl(),
+            offset: (field as usize - base as usize) as u64,
+            size: F::SIZE,
+            align: F::ALIGN,
+        }
+    }
+}
+
+pub trait UniformLayout: AsBytes + Copy + 'static {
+    fn fields() -> Vec<WgslField>;
+
+    fn type_name() -> &'static str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+}
+
+// uniform_layout!(FieldUniform { lattice_size, lattice_pixel_size, speed_ty });
+macro_rules! uniform_layout {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        impl $crate::util::UniformLayout for $ty {
+            fn fields() -> Vec<$crate::util::WgslField> {
+                let uninit = std::mem::MaybeUninit::<$ty>::uninit();
+                let base = uninit.as_ptr();
+                // 只取字段的地址，不读取未初始化的内存
+                vec![$($crate::util::WgslField::new(stringify!($field), base, unsafe {
+                    std::ptr::addr_of!((*base).$field)
+                })),*]
+            }
+        }
+    };
+}
+pub(crate) use uniform_layout;
+
+fn round_up(value: u64, align: u64) -> u64 {
+    (value + align - 1) / align * align
+}
+
+// 按 WGSL 的规则排布字段，检查与 Rust struct 的实际布局是否一致
+pub fn check_layout<T: UniformLayout>() -> Result<(), String> {
+    let mut end = 0;
+    let mut struct_align = 4;
+    for field in T::fields() {
+        let offset = round_up(end, field.align);
+        if field.offset != offset {
+            return Err(format!(
+                "`{}.{}` is at offset {}, but WGSL places it at {}, fix the padding before it\n{}",
+                T::type_name(),
+                field.name,
+                field.offset,
+                offset,
+                wgsl_struct::<T>()
+            ));
+        }
+        end = offset + field.size;
+        struct_align = struct_align.max(field.align);
+    }
+    let size = round_up(end, struct_align);
+    if std::mem::size_of::<T>() as u64 != size {
+        return Err(format!(
+            "`{}` is {} bytes, but the WGSL struct is {} bytes, fix the trailing padding\n{}",
+            T::type_name(),
+            std::mem::size_of::<T>(),
+            size,
+            wgsl_struct::<T>()
+        ));
+    }
+    Ok(())
+}
+
+// 与 Rust struct 对应的 WGSL struct
+pub fn wgsl_struct<T: UniformLayout>() -> String {
+    let mut code = format!("struct {} {{\n", T::type_name());
+    for field in T::fields() {
+        code += &format!("    {}: {},\n", field.name, field.ty);
+    }
+    code + "};\n"
+}
+
+pub struct UniformBuffer<T: UniformLayout> {
+    data: T,
+    pub buffer: BufferObj,
+}
+
+#[allow(dead_code)]
+impl<T: UniformLayout> UniformBuffer<T> {
+    pub fn new(device: &wgpu::Device, data: T, label: Option<&'static str>) -> Self {
+        if let Err(e) = check_layout::<T>() {
+            panic!("{}", e);
+        }
+        let buffer = BufferObj::create_uniform_buffer(device, &data, label);
+        UniformBuffer { data, buffer }
+    }
+
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
+    // 修改数据后整体写入 GPU
+    pub fn update(&mut self, queue: &wgpu::Queue, update: impl FnOnce(&mut T)) {
+        update(&mut self.data);
+        queue.write_buffer(&self.buffer.buffer, 0, self.data.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fluid::LbmUniform;
+    use crate::pbd::{BinUniform, ClothUniform, CollisionUniform};
+    use crate::{FieldUniform, ParticleUniform};
+
+    // 用 naga 解析 shader 文件中的 struct：(成员, struct 大小)
+    fn reflect_struct(code: &str, name: &str) -> (Vec<(String, u64, u64)>, u64) {
+        let start = code.find(&format!("struct {} {{", name)).expect("struct not found");
+        let end = start + code[start..].find("};").expect("struct not closed") + 2;
+        let module = naga::front::wgsl::parse_str(&code[start..end]).unwrap();
+        let (_, ty) = module.types.iter().find(|(_, ty)| ty.name.as_deref() == Some(name)).unwrap();
+        match &ty.inner {
+            naga::TypeInner::Struct { members, span } => {
+                let members = members
+                    .iter()
+                    .map(|member| {
+                        let size = module.types[member.ty].inner.size(&module.constants);
+                        (member.name.clone().unwrap(), member.offset as u64, size as u64)
+                    })
+                    .filter(|(name, _, _)| !name.starts_with("padding") && !name.starts_with('_'))
+                    .collect();
+                (members, *span as u64)
+            }
+            _ => panic!("`{}` is not a struct", name),
+        }
+    }
+
+    fn check_against_shader<T: UniformLayout>(shader_file: &str) {
+        check_layout::<T>().unwrap();
+
+        let path = format!("{}/shader-wgsl/{}", env!("CARGO_MANIFEST_DIR"), shader_file);
+        let code = std::fs::read_to_string(path).unwrap();
+        let (shader_members, shader_size) = reflect_struct(&code, T::type_name());
+        let fields: Vec<(String, u64, u64)> = T::fields()
+            .into_iter()
+            .map(|field| (field.name.to_string(), field.offset, field.size))
+            .collect();
+        assert_eq!(shader_members, fields);
+        assert_eq!(shader_size, std::mem::size_of::<T>() as u64);
+
+        // 生成的 WGSL struct 与 shader 中的布局一致
+        let (generated_members, generated_size) =
+            reflect_struct(&wgsl_struct::<T>(), T::type_name());
+        assert_eq!(generated_members, shader_members);
+        assert_eq!(generated_size, shader_size);
+    }
+
+    #[test]
+    fn field_uniform_layout() {
+        check_against_shader::<FieldUniform>("struct/field.wgsl");
+    }
+
+    #[test]
+    fn particle_uniform_layout() {
+        check_against_shader::<ParticleUniform>("struct/particle.wgsl");
+    }
+
+    #[test]
+    fn lbm_uniform_layout() {
+        check_against_shader::<LbmUniform>("lbm/struct/lbm_uniform.wgsl");
+    }
+
+    #[test]
+    fn cloth_uniform_layout() {
+        check_against_shader::<ClothUniform>("pbd/struct/cloth_uniform.wgsl");
+    }
+
+    #[test]
+    fn bin_uniform_layout() {
+        check_against_shader::<BinUniform>("pbd/struct/cloth_collision.wgsl");
+    }
+
+    #[test]
+    fn collision_uniform_layout() {
+        check_against_shader::<CollisionUniform>("pbd/struct/cloth_collision.wgsl");
+    }
+
+    #[test]
+    fn misplaced_field_is_reported() {
+        #[repr(C)]
+        #[derive(Copy, Clone, AsBytes)]
+        struct Misplaced {
+            scale: f32,
+            _padding: f32,
+            offset: [f32; 4],
+        }
+        uniform_layout!(Misplaced { scale, offset });
+        let e = check_layout::<Misplaced>().err().unwrap();
+        assert!(e.contains("`Misplaced.offset` is at offset 8, but WGSL places it at 16"));
+        assert!(e.contains("offset: vec4<f32>,"));
+    }
+}
@@ -25,7 +25,20 @@ const CANVAS_ANIMATION_SUSPEND: &'static str = "canvas_animation_suspend";
 
 const CANVAS_RESET: &'static str = "canvas_reset";
 
-const ALL_CUSTOM_EVENTS: [&'static str; 10] = [
+// 录制的脚本以文本形式存放在 localStorage 的 input_script 中
+const INPUT_RECORDING_START: &'static str = "input_recording_start";
+const INPUT_RECORDING_STOP: &'static str = "input_recording_stop";
+const INPUT_REPLAY: &'static str = "input_replay";
+
+// 编辑工具：localStorage 的 edit_tool 为 0~5，依次是外力、障碍物、橡皮擦、墙、入口、出口
+const EDIT_TOOL_CHANGED: &'static str = "edit_tool_changed";
+// 外力笔刷：brush_radius 为像素半径，brush_falloff 为 0~1 的边缘衰减
+const BRUSH_CHANGED: &'static str = "brush_changed";
+
+const CANVAS_UNDO: &'static str = "canvas_undo";
+const CANVAS_REDO: &'static str = "canvas_redo";
+
+const ALL_CUSTOM_EVENTS: [&'static str; 17] = [
     FIELD_TYPE,
     FIELD_ANIMATION_TYPE,
     FLUID_VISCOSITY_CHANGED,
@@ -36,6 +49,13 @@ const ALL_CUSTOM_EVENTS: [&'static str; 10] = [
     CANVAS_ANIMATION_RESUME,
     CANVAS_ANIMATION_SUSPEND,
     CANVAS_RESET,
+    INPUT_RECORDING_START,
+    INPUT_RECORDING_STOP,
+    INPUT_REPLAY,
+    EDIT_TOOL_CHANGED,
+    BRUSH_CHANGED,
+    CANVAS_UNDO,
+    CANVAS_REDO,
 ];
 
 #[wasm_bindgen]
@@ -158,7 +178,15 @@ pub fn run() {
                         };
                     }
                     WindowEvent::Touch(touch) => {
-                        println!("{:?}", touch);
+                        let phase = match touch.phase {
+                            winit::event::TouchPhase::Started => TouchPhase::Started,
+                            winit::event::TouchPhase::Moved => TouchPhase::Moved,
+                            winit::event::TouchPhase::Ended => TouchPhase::Ended,
+                            winit::event::TouchPhase::Cancelled => TouchPhase::Cancelled,
+                        };
+                        let pos = Position::new(touch.location.x as f32, touch.location.y as f32);
+                        // 鼠标使用 id 0
+                        surface_view.pointer_event(touch.id + 1, phase, pos);
                     }
                     WindowEvent::CursorMoved { position, .. } => {
                         last_touch_point = Position::new(position.x as f32, position.y as f32);
@@ -176,6 +204,7 @@ pub fn run() {
                             let field_ty = match val.as_str() {
                                 "1" => crate::FieldType::Field,
                                 "2" => crate::FieldType::Fluid,
+                                "3" => crate::FieldType::ClothInFluid,
                                 _ => crate::FieldType::Field,
                             };
                             surface_view.update_field_type(field_ty, animation_ty);
@@ -213,6 +242,11 @@ pub fn run() {
                         }
                         CANVAS_ANIMATION_RESUME => {
                             // *control_flow = ControlFlow::Poll;
+                            surface_view.resume();
+                        }
+                        CANVAS_ANIMATION_SUSPEND => {
+                            // 继续重绘，只是不再推进模拟
+                            surface_view.pause();
                         }
                         CANVAS_SIZE_NEED_CHANGE => {
                             // need_poll = false;
@@ -231,6 +265,51 @@ pub fn run() {
                         CANVAS_RESET => {
                             surface_view.reset();
                         }
+                        CANVAS_UNDO => {
+                            surface_view.undo();
+                        }
+                        CANVAS_REDO => {
+                            surface_view.redo();
+                        }
+                        INPUT_RECORDING_START => {
+                            surface_view.start_input_recording();
+                        }
+                        INPUT_RECORDING_STOP => {
+                            if let Some(script) = surface_view.stop_input_recording() {
+                                storage.set_item("input_script", &script.to_text()).unwrap();
+                            }
+                        }
+                        INPUT_REPLAY => {
+                            let text =
+                                storage.get_item("input_script").unwrap().unwrap_or_default();
+                            let looping = storage.get_item("input_replay_looping").unwrap();
+                            let looping = looping.as_deref() == Some("1");
+                            match crate::InputScript::from_text(&text) {
+                                Ok(script) => surface_view.start_replay(script, looping),
+                                Err(e) => console_log!("invalid input script: {}", e),
+                            }
+                        }
+                        EDIT_TOOL_CHANGED => {
+                            let val = storage.get_item("edit_tool").unwrap().unwrap();
+                            let tool = match val.as_str() {
+                                "1" => crate::EditTool::Obstacle,
+                                "2" => crate::EditTool::Eraser,
+                                "3" => crate::EditTool::Wall,
+                                "4" => crate::EditTool::Inlet,
+                                "5" => crate::EditTool::Outlet,
+                                _ => crate::EditTool::Force,
+                            };
+                            surface_view.set_edit_tool(tool);
+                        }
+                        BRUSH_CHANGED => {
+                            let radius = storage.get_item("brush_radius").unwrap().unwrap();
+                            let falloff = storage.get_item("brush_falloff").unwrap().unwrap();
+                            if let (Ok(radius), Ok(falloff)) =
+                                (radius.parse::<f32>(), falloff.parse::<f32>())
+                            {
+                                surface_view.set_brush(radius, falloff);
+                            }
+                        }
                         _ => (),
                     }
 
@@ -255,6 +334,7 @@ fn get_animation_type(val: String) -> FieldAnimationType {
         "2" => FieldAnimationType::JuliaSet,
         "3" => FieldAnimationType::Poiseuille,
         "4" => FieldAnimationType::Custom,
+        "5" => FieldAnimationType::WindTunnel,
         _ => FieldAnimationType::Basic,
     }
 }
@@ -1,7 +1,2 @@
-mod generated_shader_map;
-
-mod create_shader_module;
-pub use create_shader_module::*;
-
 mod ffi;
 pub use ffi::*;
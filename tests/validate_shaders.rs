@@ -0,0 +1,141 @@
+// 离线检查 shader-wgsl 下的所有 shader，不需要 GPU
+//
+// cargo test --test validate_shaders                               预处理后用 naga 解析与校验
+// VALIDATE_SHADERS_CROSS=1 cargo test --test validate_shaders      同时转译为 MSL、SPIR-V 与 GLSL ES 3.1
+use naga::back::{glsl, msl, spv};
+use naga::valid::{Capabilities, ModuleInfo, ValidationFlags, Validator};
+use nature::{get_velocity_code_segment, FieldAnimationType};
+use std::path::PathBuf;
+
+#[path = "../src/util/preprocessor.rs"]
+mod preprocessor;
+use preprocessor::{entry_shaders, ShaderPreprocessor, DEFAULT_SLOT};
+
+// 需要填充 code slot 的 shader，只校验它的各个变体
+const SLOT_SHADERS: [&str; 1] = ["field_setting"];
+
+fn shader_root() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("shader-wgsl")
+}
+
+fn is_cross() -> bool {
+    matches!(std::env::var("VALIDATE_SHADERS_CROSS").as_deref(), Ok(value) if value != "0")
+}
+
+const ANIMATION_TYPES: [FieldAnimationType; 7] = [
+    FieldAnimationType::Basic,
+    FieldAnimationType::JuliaSet,
+    FieldAnimationType::Spirl,
+    FieldAnimationType::Poiseuille,
+    FieldAnimationType::LidDrivenCavity,
+    FieldAnimationType::WindTunnel,
+    FieldAnimationType::Custom,
+];
+
+// 新增动画类型时这里会编译失败，提醒把它加入 ANIMATION_TYPES
+fn animation_name(ty: FieldAnimationType) -> &'static str {
+    match ty {
+        FieldAnimationType::Basic => "Basic",
+        FieldAnimationType::JuliaSet => "JuliaSet",
+        FieldAnimationType::Spirl => "Spirl",
+        FieldAnimationType::Poiseuille => "Poiseuille",
+        FieldAnimationType::LidDrivenCavity => "LidDrivenCavity",
+        FieldAnimationType::WindTunnel => "WindTunnel",
+        FieldAnimationType::Custom => "Custom",
+    }
+}
+
+#[test]
+fn entry_shaders_are_valid() {
+    let root = shader_root();
+    let mut errors = vec![];
+    for name in entry_shaders(&root) {
+        if SLOT_SHADERS.contains(&name.as_str()) {
+            continue;
+        }
+        let preprocessor = ShaderPreprocessor::from_dir(root.clone());
+        if let Err(e) = check(&name, &preprocessor) {
+            errors.push(e);
+        }
+    }
+    assert!(errors.is_empty(), "{} shaders failed:\n{}", errors.len(), errors.join("\n"));
+}
+
+#[test]
+fn field_setting_variants_are_valid() {
+    let mut errors = vec![];
+    for ty in ANIMATION_TYPES {
+        let preprocessor = ShaderPreprocessor::from_dir(shader_root())
+            .slot(DEFAULT_SLOT, get_velocity_code_segment(ty));
+        if let Err(e) = check("field_setting", &preprocessor) {
+            errors.push(format!("{}: {}", animation_name(ty), e));
+        }
+    }
+    assert!(errors.is_empty(), "{} variants failed:\n{}", errors.len(), errors.join("\n"));
+}
+
+fn check(name: &str, preprocessor: &ShaderPreprocessor) -> Result<(), String> {
+    let shader = preprocessor.process(name).map_err(|e| format!("{}: {}", name, e))?;
+    let module = naga::front::wgsl::parse_str(&shader.code).map_err(|e| {
+        format!("{}:\n{}", name, shader.map_error_message(&e.emit_to_string(&shader.code)))
+    })?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| {
+            let mut message = format!("{}: {}", name, e);
+            for (span, label) in e.spans() {
+                if let Some(range) = span.to_range() {
+                    let line = shader.code[..range.start].matches('\n').count() + 1;
+                    match shader.source_location(line) {
+                        Some((file, line)) => {
+                            message += &format!("\n    {}:{}: {}", file, line, label)
+                        }
+                        None => message += &format!("\n    wgsl:{}: {}", line, label),
+                    }
+                }
+            }
+            message
+        })?;
+    if is_cross() {
+        let errors = cross_compile(&module, &info);
+        if !errors.is_empty() {
+            return Err(format!("{}: {}", name, errors.join("; ")));
+        }
+    }
+    Ok(())
+}
+
+// 检查各平台后端能否转译
+fn cross_compile(module: &naga::Module, info: &ModuleInfo) -> Vec<String> {
+    let mut errors = vec![];
+    if let Err(e) =
+        msl::write_string(module, info, &msl::Options::default(), &msl::PipelineOptions::default())
+    {
+        errors.push(format!("msl: {:?}", e));
+    }
+    if let Err(e) = spv::write_vec(module, info, &spv::Options::default(), None) {
+        errors.push(format!("spir-v: {:?}", e));
+    }
+    let options = glsl::Options { version: glsl::Version::Embedded(310), ..Default::default() };
+    for entry in module.entry_points.iter() {
+        let pipeline_options = glsl::PipelineOptions {
+            shader_stage: entry.stage,
+            entry_point: entry.name.clone(),
+            multiview: None,
+        };
+        let mut output = String::new();
+        let result = glsl::Writer::new(
+            &mut output,
+            module,
+            info,
+            &options,
+            &pipeline_options,
+            naga::proc::BoundsCheckPolicies::default(),
+        )
+        .and_then(|mut writer| writer.write());
+        if let Err(e) = result {
+            errors.push(format!("glsl {}: {:?}", entry.name, e));
+        }
+    }
+    errors
+}